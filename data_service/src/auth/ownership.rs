@@ -0,0 +1,64 @@
+use crate::auth::session::AuthenticatedProfile;
+use crate::routes::errors::{ ApiError, OwnershipViolation };
+use repository::repo::profile::model::ProfileQueryResult;
+use repository::repo::profile::profile::QueryProfileFn;
+
+/// A profile belongs to the caller when it is the same chain object as the signed in profile, or when both were
+/// created by the same wallet. Profiles without a recorded wallet only match themselves
+pub fn owns_profile(caller: &ProfileQueryResult, target: &ProfileQueryResult) -> bool {
+    let same_chain_object = caller.chain_id == target.chain_id && caller.chain_asset_id == target.chain_asset_id;
+    let same_wallet = match (&caller.owner_address, &target.owner_address) {
+        (Some(caller_address), Some(target_address)) => caller_address.eq_ignore_ascii_case(target_address),
+        _ => false,
+    };
+
+    same_chain_object || same_wallet
+}
+
+/// Checks the caller may write as or to the profile before any repository call changes data on its behalf
+pub async fn require_profile_owner<T: QueryProfileFn>(
+    db_repo: &T,
+    auth: &AuthenticatedProfile,
+    profile_id: i64
+) -> Result<ProfileQueryResult, ApiError> {
+    let caller = db_repo.query_profile(auth.profile_id).await?
+        .ok_or_else(|| ApiError::Unauthorized("signed in profile no longer exists".to_string()))?;
+    let target = db_repo.query_profile(profile_id).await?.ok_or(ApiError::NotFound)?;
+
+    if !owns_profile(&caller, &target) {
+        return Err(ApiError::Forbidden(OwnershipViolation::new("profile", profile_id)));
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn profile(id: i64, chain_asset_id: &str, owner_address: Option<&str>) -> ProfileQueryResult {
+        ProfileQueryResult {
+            id,
+            updated_at: Utc::now(),
+            chain_asset_id: chain_asset_id.to_string(),
+            chain_id: 1,
+            user_name: format!("user{}", id),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: owner_address.map(|address| address.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_owns_profile() {
+        let caller = profile(1, "0xa1", Some("0xABC"));
+
+        assert!(owns_profile(&caller, &profile(1, "0xa1", Some("0xabc"))));
+        assert!(owns_profile(&caller, &profile(2, "0xa2", Some("0xabc"))), "profiles of the same wallet");
+        assert!(!owns_profile(&caller, &profile(3, "0xa3", Some("0xdef"))));
+        assert!(!owns_profile(&profile(4, "0xa4", None), &profile(5, "0xa5", None)));
+        assert!(owns_profile(&profile(4, "0xa4", None), &profile(4, "0xa4", None)));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::auth::token::AccessTokenSigner;
+use crate::routes::errors::ApiError;
+use actix_web::{ dev::Payload, http::header, web, FromRequest, HttpRequest };
+use chrono::Utc;
+use futures_util::future::LocalBoxFuture;
+use log::error;
+use rand::RngCore;
+use repository::repo::auth::auth::QuerySessionFn;
+use repository::repo::error::RepositoryError;
+use sha2::{ Digest, Sha256 };
+use std::sync::Arc;
+
+/// Browsers cannot set headers on a websocket upgrade, so they offer this subprotocol followed by the access token,
+/// `new WebSocket(url, ["access_token", token])`, and the server answers with this subprotocol alone
+pub const ACCESS_TOKEN_PROTOCOL: &str = "access_token";
+
+/// Session lookup the extractor uses, registered as app data apart from the generic app state
+pub type SessionStore = dyn QuerySessionFn + Send + Sync;
+
+pub fn session_store<T: QuerySessionFn + Send + Sync + 'static>(db_repo: T) -> web::Data<SessionStore> {
+    web::Data::from(Arc::new(db_repo) as Arc<SessionStore>)
+}
+
+/// Random hex string used for challenge nonces and refresh tokens
+pub fn new_random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Only this hash is stored, a leaked session table does not give out usable refresh tokens
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// The profile and session of the access token in the `Authorization: Bearer <token>` header, or for a websocket
+/// upgrade in the `Sec-WebSocket-Protocol` header after ACCESS_TOKEN_PROTOCOL. Handlers taking it
+/// reject requests without a valid token before they run. The session is looked up too, so tokens of a revoked
+/// session stop working right away. The signer and the session store must be registered as app data
+#[derive(Debug, Clone, Copy)]
+pub struct AuthenticatedProfile {
+    pub profile_id: i64,
+    pub session_id: i64,
+}
+
+impl FromRequest for AuthenticatedProfile {
+    type Error = ApiError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move { authenticate_request(&req).await })
+    }
+}
+
+async fn authenticate_request(req: &HttpRequest) -> Result<AuthenticatedProfile, ApiError> {
+    let signer = req.app_data::<web::Data<AccessTokenSigner>>().ok_or_else(|| {
+        error!("no access token signer registered as app data");
+        ApiError::InternalServerError
+    })?;
+    let sessions = req.app_data::<web::Data<SessionStore>>().ok_or_else(|| {
+        error!("no session store registered as app data");
+        ApiError::InternalServerError
+    })?;
+    let token = bearer_token(req)
+        .or_else(|| websocket_protocol_token(req))
+        .ok_or_else(|| ApiError::Unauthorized("a bearer token is required".to_string()))?;
+
+    let claims = signer.verify(token).map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    let auth = AuthenticatedProfile { profile_id: claims.profile_id, session_id: claims.session_id };
+    if session_is_active(sessions.get_ref(), auth).await? {
+        Ok(auth)
+    } else {
+        Err(ApiError::Unauthorized("session was revoked or has expired".to_string()))
+    }
+}
+
+/// Whether the session of auth is still unrevoked and unexpired, open websockets check it again from time to time
+pub async fn session_is_active(sessions: &SessionStore, auth: AuthenticatedProfile) -> Result<bool, RepositoryError> {
+    Ok(matches!(
+        sessions.query_session(auth.session_id).await?,
+        Some(session) if session.profile_id == auth.profile_id && session.expires_at > Utc::now()
+    ))
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// Subprotocols offered by a websocket upgrade, browsers may send them in one or several headers
+pub fn websocket_protocols(req: &HttpRequest) -> impl Iterator<Item = &str> {
+    req.headers()
+        .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|protocol| protocol.trim())
+}
+
+fn websocket_protocol_token(req: &HttpRequest) -> Option<&str> {
+    let mut protocols = websocket_protocols(req);
+    protocols.find(|protocol| *protocol == ACCESS_TOKEN_PROTOCOL)?;
+    protocols.next().filter(|token| !token.is_empty())
+}
@@ -0,0 +1,139 @@
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use blake2::{ digest::consts::U32, Blake2b, Digest };
+use ed25519_consensus::{ Signature, VerificationKey };
+
+/// Flag byte sui puts in front of ed25519 signatures and public keys, other key schemes are not accepted
+const ED25519_FLAG: u8 = 0x00;
+/// Intent scope, version and app id of a sui personal message
+const PERSONAL_MESSAGE_INTENT: [u8; 3] = [3, 0, 0];
+const SIGNATURE_LEN: usize = 64;
+const PUBLIC_KEY_LEN: usize = 32;
+
+type Blake2b256 = Blake2b<U32>;
+
+#[derive(Debug, PartialEq)]
+pub enum SignatureError {
+    Encoding,
+    UnsupportedScheme(u8),
+    InvalidPublicKey,
+    Invalid,
+}
+
+impl std::error::Error for SignatureError {}
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Encoding => write!(f, "signature must be base64 of flag, signature and public key"),
+            Self::UnsupportedScheme(flag) => write!(f, "signature scheme {} is not supported, sign with an ed25519 key", flag),
+            Self::InvalidPublicKey => write!(f, "signature public key is not a valid ed25519 key"),
+            Self::Invalid => write!(f, "signature does not match the message"),
+        }
+    }
+}
+
+/// The digest wallets sign for signPersonalMessage, the message is bcs encoded as a byte vector behind its intent
+pub fn personal_message_digest(message: &[u8]) -> [u8; 32] {
+    let mut length = Vec::new();
+    let mut remaining = message.len();
+    loop {
+        let byte = (remaining & 0x7f) as u8;
+        remaining >>= 7;
+        if remaining == 0 {
+            length.push(byte);
+            break;
+        }
+        length.push(byte | 0x80);
+    }
+
+    let mut hasher = Blake2b256::new();
+    hasher.update(PERSONAL_MESSAGE_INTENT);
+    hasher.update(&length);
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Sui addresses are the blake2b hash of the key scheme flag followed by the public key
+pub fn sui_address(public_key: &[u8; PUBLIC_KEY_LEN]) -> String {
+    let mut hasher = Blake2b256::new();
+    hasher.update([ED25519_FLAG]);
+    hasher.update(public_key);
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Lowercase form of a full length sui address, None when address is not one
+pub fn normalize_sui_address(address: &str) -> Option<String> {
+    let hex = address.strip_prefix("0x")?;
+    (hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| address.to_lowercase())
+}
+
+/// Checks a serialized sui signature over a personal message and returns the address of the key that signed it
+pub fn verify_personal_message(message: &[u8], signature: &str) -> Result<String, SignatureError> {
+    let bytes = STANDARD.decode(signature).map_err(|_| SignatureError::Encoding)?;
+    if bytes.len() != 1 + SIGNATURE_LEN + PUBLIC_KEY_LEN {
+        return Err(SignatureError::Encoding);
+    }
+    if bytes[0] != ED25519_FLAG {
+        return Err(SignatureError::UnsupportedScheme(bytes[0]));
+    }
+
+    let signature = Signature::try_from(&bytes[1..1 + SIGNATURE_LEN]).map_err(|_| SignatureError::Encoding)?;
+    let public_key: [u8; PUBLIC_KEY_LEN] = bytes[1 + SIGNATURE_LEN..].try_into().map_err(|_| SignatureError::Encoding)?;
+    let verification_key = VerificationKey::try_from(public_key).map_err(|_| SignatureError::InvalidPublicKey)?;
+    verification_key
+        .verify(&signature, &personal_message_digest(message))
+        .map_err(|_| SignatureError::Invalid)?;
+
+    Ok(sui_address(&public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::fixtures::sign_personal_message;
+    use ed25519_consensus::SigningKey;
+
+    #[test]
+    fn test_verify_personal_message() {
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let signature = sign_personal_message(&signing_key, b"Sign in to dechat");
+
+        let address = verify_personal_message(b"Sign in to dechat", &signature).unwrap();
+
+        assert!(address == sui_address(&signing_key.verification_key().to_bytes()));
+        assert!(address.len() == 66);
+        assert!(verify_personal_message(b"Sign in to another app", &signature) == Err(SignatureError::Invalid));
+    }
+
+    #[test]
+    fn test_rejects_malformed_signatures() {
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let mut bytes = STANDARD.decode(sign_personal_message(&signing_key, b"message")).unwrap();
+
+        assert!(verify_personal_message(b"message", "not base64!") == Err(SignatureError::Encoding));
+        assert!(verify_personal_message(b"message", &STANDARD.encode(&bytes[..64])) == Err(SignatureError::Encoding));
+        bytes[0] = 0x01;
+        assert!(verify_personal_message(b"message", &STANDARD.encode(&bytes)) == Err(SignatureError::UnsupportedScheme(1)));
+    }
+
+    #[test]
+    fn test_normalize_sui_address() {
+        let address = format!("0x{}", "Ab".repeat(32));
+
+        assert!(normalize_sui_address(&address) == Some(format!("0x{}", "ab".repeat(32))));
+        assert!(normalize_sui_address(&address[2..]).is_none());
+        assert!(normalize_sui_address(&address[..64]).is_none());
+        assert!(normalize_sui_address(&format!("0x{}", "zz".repeat(32))).is_none());
+    }
+
+    #[test]
+    fn test_personal_message_digest_length_prefix() {
+        let long_message = vec![b'a'; 300];
+
+        let mut hasher = Blake2b256::new();
+        hasher.update([3, 0, 0, 0xac, 0x02]);
+        hasher.update(&long_message);
+        let expected: [u8; 32] = hasher.finalize().into();
+
+        assert!(personal_message_digest(&long_message) == expected);
+    }
+}
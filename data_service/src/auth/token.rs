@@ -0,0 +1,105 @@
+use base64::{ engine::general_purpose::URL_SAFE_NO_PAD, Engine };
+use chrono::{ DateTime, TimeZone, Utc };
+use hmac::{ Hmac, Mac };
+use serde::{ Deserialize, Serialize };
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What an access token vouches for, exp is in unix seconds
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct AccessClaims {
+    pub profile_id: i64,
+    pub session_id: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenError {
+    Malformed,
+    InvalidSignature,
+    Expired,
+}
+
+impl std::error::Error for TokenError {}
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "access token is malformed"),
+            Self::InvalidSignature => write!(f, "access token signature is invalid"),
+            Self::Expired => write!(f, "access token has expired"),
+        }
+    }
+}
+
+/// Signs and checks access tokens, base64url of the json claims and of their hmac-sha256 joined by a dot.
+/// Only the signature and expiry are checked here, AuthenticatedProfile also looks up the session so tokens of a
+/// revoked session stop working right away
+pub struct AccessTokenSigner {
+    key: Vec<u8>,
+}
+
+impl AccessTokenSigner {
+    pub fn new(key: &[u8]) -> Self {
+        Self { key: key.to_vec() }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).expect("hmac accepts keys of any length")
+    }
+
+    pub fn sign(&self, profile_id: i64, session_id: i64, expires_at: DateTime<Utc>) -> String {
+        let claims = AccessClaims { profile_id, session_id, exp: expires_at.timestamp() };
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).expect("claims serialize to json"));
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+
+        format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+
+    pub fn verify(&self, token: &str) -> Result<AccessClaims, TokenError> {
+        let (payload, signature) = token.split_once('.').ok_or(TokenError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| TokenError::Malformed)?;
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&signature).map_err(|_| TokenError::InvalidSignature)?;
+
+        let claims: AccessClaims = URL_SAFE_NO_PAD.decode(payload).ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or(TokenError::Malformed)?;
+        match Utc.timestamp_opt(claims.exp, 0).single() {
+            Some(expires_at) if expires_at > Utc::now() => Ok(claims),
+            _ => Err(TokenError::Expired),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = AccessTokenSigner::new(b"secret");
+
+        let token = signer.sign(1, 2, Utc::now() + Duration::minutes(15));
+
+        let claims = signer.verify(&token).unwrap();
+        assert!(claims.profile_id == 1 && claims.session_id == 2);
+        assert!(AccessTokenSigner::new(b"other secret").verify(&token) == Err(TokenError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_rejects_tampered_and_expired_tokens() {
+        let signer = AccessTokenSigner::new(b"secret");
+        let token = signer.sign(1, 2, Utc::now() + Duration::minutes(15));
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged_claims = AccessClaims { profile_id: 3, session_id: 2, exp: (Utc::now() + Duration::minutes(15)).timestamp() };
+        let forged = format!("{}.{}", URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged_claims).unwrap()), signature);
+
+        assert!(signer.verify(&forged) == Err(TokenError::InvalidSignature));
+        assert!(signer.verify("no dot") == Err(TokenError::Malformed));
+        assert!(signer.verify(&signer.sign(1, 2, Utc::now() - Duration::seconds(1))) == Err(TokenError::Expired));
+    }
+}
@@ -1,6 +1,21 @@
+pub mod auth {
+    pub mod ownership;
+    pub mod session;
+    pub mod signature;
+    pub mod token;
+}
+pub mod realtime {
+    pub mod hub;
+    pub mod listener;
+}
 pub mod routes {
+    pub mod auth;
+    pub mod follow;
     pub mod profile;
     pub mod post;
+    pub mod realtime;
+    pub mod errors;
+    pub mod route_utils;
 }
 pub mod app_state;
 pub mod test_helpers {
@@ -10,27 +25,76 @@ pub mod test_helpers {
 use std::env;
 use dotenv::dotenv;
 use actix_web::{ web, App, HttpServer, middleware::Logger };
+use app_state::AppState;
+use repository::repo::base::DbRepo;
+use auth::session::session_store;
+use auth::token::AccessTokenSigner;
+use realtime::hub::PostHub;
+use realtime::listener::run_entity_listener;
+use routes::auth::{ create_address_challenge, create_challenge, get_sessions, login, refresh, revoke_all_sessions, revoke_session };
+use routes::follow::{ follow_user, unfollow_user };
+use routes::realtime::subscribe_posts;
+use routes::profile::{ create_profile, get_profile, get_profile_by_user, update_profile };
+use routes::post::{ create_post, create_response_post, create_share_post, get_post, get_posts_by_user };
 
 pub async fn run() -> std::io::Result<()> {
     dotenv().ok();
 
     let port: u16 = env::var("PORT").unwrap().parse().unwrap();
     let host = env::var("HOST").unwrap();
+    let access_token_secret = env::var("ACCESS_TOKEN_SECRET").unwrap();
 
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
+    let app_data = web::Data::new(AppState {
+        client: reqwest::Client::new(),
+        db_repo: DbRepo::init().await,
+    });
+    let token_signer = web::Data::new(AccessTokenSigner::new(access_token_secret.as_bytes()));
+    let sessions = session_store(app_data.db_repo.clone());
+    let post_hub = web::Data::new(PostHub::new());
+    actix_web::rt::spawn(run_entity_listener(app_data.db_repo.clone(), post_hub.clone()));
+
     HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
-            //.app_data(app_data.clone())
+            .app_data(app_data.clone())
+            .app_data(token_signer.clone())
+            .app_data(sessions.clone())
+            .app_data(post_hub.clone())
             .service(
                 web::scope("/v1")
                     // .service(web::resource("/msg/{id}").route(web::get().to(get_message::<DbRepo>)))
                     // .service(web::resource("/msg").route(web::post().to(create_message::<DbRepo>)))
                     // .service(web::resource("/msgs").route(web::post().to(get_messages::<DbRepo>)))
-                    // .service(web::resource("/profile/{id}").route(web::get().to(get_profile::<DbRepo>)))
-                    // .service(web::resource("/profile/username/{user_name}").route(web::get().to(get_profile_by_user::<DbRepo>)))
-                    // .service(web::resource("/profile").route(web::post().to(create_profile::<DbRepo>)))
+                    .service(
+                        web::resource("/profile/{id}")
+                            .route(web::get().to(get_profile::<DbRepo>))
+                            .route(web::put().to(update_profile::<DbRepo>))
+                    )
+                    .service(web::resource("/profile/username/{user_name}").route(web::get().to(get_profile_by_user::<DbRepo>)))
+                    .service(
+                        web::resource("/profile/{id}/following/{following_id}")
+                            .route(web::post().to(follow_user::<DbRepo>))
+                            .route(web::delete().to(unfollow_user::<DbRepo>))
+                    )
+                    .service(web::resource("/profile").route(web::post().to(create_profile::<DbRepo>)))
+                    .service(web::resource("/post/{id}").route(web::get().to(get_post::<DbRepo>)))
+                    .service(web::resource("/post/{id}/response").route(web::post().to(create_response_post::<DbRepo>)))
+                    .service(web::resource("/post/{id}/share").route(web::post().to(create_share_post::<DbRepo>)))
+                    .service(web::resource("/post").route(web::post().to(create_post::<DbRepo>)))
+                    .service(web::resource("/posts/user/{user_id}").route(web::get().to(get_posts_by_user::<DbRepo>)))
+                    .service(web::resource("/auth/challenge").route(web::post().to(create_challenge::<DbRepo>)))
+                    .service(web::resource("/auth/address_challenge").route(web::post().to(create_address_challenge::<DbRepo>)))
+                    .service(web::resource("/auth/login").route(web::post().to(login::<DbRepo>)))
+                    .service(web::resource("/auth/refresh").route(web::post().to(refresh::<DbRepo>)))
+                    .service(
+                        web::resource("/auth/sessions")
+                            .route(web::get().to(get_sessions::<DbRepo>))
+                            .route(web::delete().to(revoke_all_sessions::<DbRepo>))
+                    )
+                    .service(web::resource("/auth/sessions/{id}").route(web::delete().to(revoke_session::<DbRepo>)))
+                    .service(web::resource("/ws").route(web::get().to(subscribe_posts)))
             )
     })
     .bind((host, port))?
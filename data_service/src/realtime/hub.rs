@@ -0,0 +1,257 @@
+use log::error;
+use repository::repo::error::RepositoryError;
+use repository::repo::follow::follow::QueryIsFollowingFn;
+use repository::repo::post::model::PostWithProfileQueryResult;
+use repository::repo::post::post::QueryPostFn;
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::{ Arc, RwLock, RwLockReadGuard, RwLockWriteGuard };
+use tokio::sync::mpsc::{ self, error::TrySendError };
+
+/// Posts a subscription holds for a client that is not reading, further posts are dropped until it catches up
+pub const SUBSCRIPTION_BUFFER: usize = 64;
+/// Replies deeper than this below a subscribed post are not followed up to it
+const MAX_THREAD_DEPTH: usize = 64;
+
+/// What a subscription receives. Home is the timeline of the signed in profile, its own posts and those of
+/// everyone it follows. Thread gets the replies anywhere below the post, Profile everything the profile writes
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Topic {
+    Home,
+    Thread { post_id: i64 },
+    Profile { profile_id: i64 },
+}
+
+struct Subscriber {
+    profile_id: i64,
+    topic: Topic,
+    sender: mpsc::Sender<Arc<PostWithProfileQueryResult>>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// The receiving end of a subscription, dropped counts the posts lost since the last take_dropped
+pub struct Subscription {
+    pub id: u64,
+    pub(crate) receiver: mpsc::Receiver<Arc<PostWithProfileQueryResult>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Subscription {
+    pub async fn recv(&mut self) -> Option<Arc<PostWithProfileQueryResult>> {
+        self.receiver.recv().await
+    }
+
+    pub fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// Fans new posts out to the subscriptions of connected clients. Every subscription has its own bounded
+/// buffer so a slow client, or one busy topic of it, never holds up publishing or the other subscriptions
+#[derive(Default)]
+pub struct PostHub {
+    subscribers: RwLock<HashMap<u64, Subscriber>>,
+    next_id: AtomicU64,
+}
+
+impl PostHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<u64, Subscriber>> {
+        self.subscribers.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<u64, Subscriber>> {
+        self.subscribers.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn subscribe(&self, profile_id: i64, topic: Topic) -> Subscription {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.write().insert(id, Subscriber { profile_id, topic, sender, dropped: Arc::clone(&dropped) });
+
+        Subscription { id, receiver, dropped }
+    }
+
+    pub fn unsubscribe(&self, id: u64) {
+        self.write().remove(&id);
+    }
+
+    pub fn subscription_count(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Hands the post to every matching subscription and returns how many took it
+    pub async fn publish<T: QueryPostFn + QueryIsFollowingFn>(
+        &self,
+        db_repo: &T,
+        post: PostWithProfileQueryResult
+    ) -> Result<usize, RepositoryError> {
+        let candidates: Vec<(u64, i64, Topic)> = self.read().iter()
+            .map(|(id, subscriber)| (*id, subscriber.profile_id, subscriber.topic.clone()))
+            .collect();
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        let has_thread_subscribers = candidates.iter().any(|(_, _, topic)| matches!(topic, Topic::Thread { .. }));
+        let ancestor_ids = match has_thread_subscribers {
+            true => thread_ancestor_ids(db_repo, &post).await?,
+            false => vec![],
+        };
+        let mut is_following: HashMap<i64, bool> = HashMap::new();
+        let mut matched_ids = vec![];
+        for (id, profile_id, topic) in candidates {
+            let matches = match topic {
+                Topic::Profile { profile_id } => post.user_id == profile_id,
+                Topic::Thread { post_id } => ancestor_ids.contains(&post_id),
+                Topic::Home if profile_id == post.user_id => true,
+                Topic::Home => match is_following.get(&profile_id) {
+                    Some(following) => *following,
+                    None => {
+                        let following = db_repo.query_is_following(profile_id, post.user_id).await?;
+                        is_following.insert(profile_id, following);
+                        following
+                    },
+                },
+            };
+            if matches {
+                matched_ids.push(id);
+            }
+        }
+
+        let post = Arc::new(post);
+        let subscribers = self.read();
+        let mut delivered = 0;
+        for id in matched_ids {
+            let Some(subscriber) = subscribers.get(&id) else { continue };
+            match subscriber.sender.try_send(Arc::clone(&post)) {
+                Ok(()) => delivered += 1,
+                Err(TrySendError::Full(_)) => {
+                    subscriber.dropped.fetch_add(1, Ordering::Relaxed);
+                },
+                Err(TrySendError::Closed(_)) => (),
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Publishes a post that was just inserted, failures are logged as the insert itself already succeeded
+    pub async fn publish_post_id<T: QueryPostFn + QueryIsFollowingFn>(&self, db_repo: &T, post_id: i64) {
+        let published = match db_repo.query_post(post_id).await {
+            Ok(Some(post)) => self.publish(db_repo, post).await.map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = published {
+            error!("failed to publish post {}: {}", post_id, e);
+        }
+    }
+}
+
+/// The posts a reply sits below, its direct respondee first
+async fn thread_ancestor_ids<T: QueryPostFn>(db_repo: &T, post: &PostWithProfileQueryResult) -> Result<Vec<i64>, RepositoryError> {
+    let mut ancestor_ids = vec![];
+    let mut next_id = post.respondee_post_id;
+    while let Some(id) = next_id {
+        if ancestor_ids.len() >= MAX_THREAD_DEPTH || ancestor_ids.contains(&id) {
+            break;
+        }
+        ancestor_ids.push(id);
+        next_id = db_repo.query_post(id).await?.and_then(|ancestor| ancestor.respondee_post_id);
+    }
+
+    Ok(ancestor_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use repository::repo::follow::follow::FollowUserFn;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::post::post::{ InsertPostFn, InsertResponsePostFn };
+    use repository::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use repository::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    async fn insert_profile(repo: &InMemoryRepo, user_name: &str) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: user_name.to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: None,
+        }).await.unwrap()
+    }
+
+    async fn insert_post(repo: &InMemoryRepo, user_id: i64, respondee_post_id: Option<i64>) -> PostWithProfileQueryResult {
+        let chain_asset_id = get_fake_chain_asset_id();
+        let post_id = match respondee_post_id {
+            Some(respondee_post_id) => repo
+                .insert_response_post(&chain_asset_id, SUI_CHAIN_ID, user_id, "reply", None, respondee_post_id).await,
+            None => repo.insert_standalone_post(&chain_asset_id, SUI_CHAIN_ID, user_id, "hello", None).await,
+        };
+        repo.query_post(post_id.unwrap().id).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_publish_matches_topics() {
+        let repo = InMemoryRepo::new();
+        let jill_id = insert_profile(&repo, "jill").await;
+        let bob_id = insert_profile(&repo, "bob").await;
+        let tom_id = insert_profile(&repo, "tom").await;
+        repo.follow_user(jill_id, bob_id).await.unwrap();
+        let hub = PostHub::new();
+        let mut jill_home = hub.subscribe(jill_id, Topic::Home);
+        let mut tom_home = hub.subscribe(tom_id, Topic::Home);
+        let mut bob_profile = hub.subscribe(tom_id, Topic::Profile { profile_id: bob_id });
+
+        let root = insert_post(&repo, bob_id, None).await;
+        assert!(hub.publish(&repo, root.clone()).await.unwrap() == 2);
+
+        let mut thread = hub.subscribe(tom_id, Topic::Thread { post_id: root.id });
+        let reply = insert_post(&repo, tom_id, Some(root.id)).await;
+        let nested_reply = insert_post(&repo, jill_id, Some(reply.id)).await;
+        hub.publish(&repo, reply.clone()).await.unwrap();
+        hub.publish(&repo, nested_reply.clone()).await.unwrap();
+
+        assert!(jill_home.recv().await.unwrap().id == root.id);
+        assert!(jill_home.recv().await.unwrap().id == nested_reply.id, "own posts are on the home timeline");
+        assert!(bob_profile.recv().await.unwrap().id == root.id);
+        assert!(tom_home.recv().await.unwrap().id == reply.id);
+        assert!(thread.recv().await.unwrap().id == reply.id);
+        assert!(thread.recv().await.unwrap().id == nested_reply.id);
+        assert!(jill_home.receiver.try_recv().is_err() && bob_profile.receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_full_subscription_drops_only_its_own_posts() {
+        let repo = InMemoryRepo::new();
+        let jill_id = insert_profile(&repo, "jill").await;
+        let hub = PostHub::new();
+        let slow = hub.subscribe(jill_id, Topic::Profile { profile_id: jill_id });
+        let mut fast = hub.subscribe(jill_id, Topic::Home);
+        let post = insert_post(&repo, jill_id, None).await;
+
+        for _ in 0..SUBSCRIPTION_BUFFER + 3 {
+            hub.publish(&repo, post.clone()).await.unwrap();
+            assert!(fast.recv().await.is_some());
+        }
+
+        assert!(slow.take_dropped() == 3);
+        assert!(slow.take_dropped() == 0);
+        assert!(fast.take_dropped() == 0);
+        hub.unsubscribe(slow.id);
+        hub.unsubscribe(fast.id);
+        assert!(hub.subscription_count() == 0);
+        assert!(hub.publish(&repo, post).await.unwrap() == 0);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::realtime::hub::PostHub;
+use log::{ error, info };
+use repository::repo::base::DbRepo;
+use repository::repo::follow::follow::QueryIsFollowingFn;
+use repository::repo::notify::model::{ EntityInserted, EntityKind };
+use repository::repo::post::post::QueryPostFn;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Wait before listening again after the database could not be reached
+const RELISTEN_DELAY: Duration = Duration::from_secs(5);
+
+/// Runs for the lifetime of the server. Every insert is notified by the database, whether it came from a request
+/// to this or another data_service instance or from event_handler ingesting chain events
+pub async fn run_entity_listener(db_repo: DbRepo, hub: actix_web::web::Data<PostHub>) {
+    loop {
+        match db_repo.listen_entity_inserted().await {
+            Ok(mut listener) => {
+                info!("listening for inserted entities");
+                loop {
+                    match listener.recv().await {
+                        Ok(inserted) => handle_entity_inserted(&db_repo, &hub, inserted).await,
+                        Err(e) => {
+                            error!("entity listener failed: {}", e);
+                            break;
+                        },
+                    }
+                }
+            },
+            Err(e) => error!("failed to listen for inserted entities: {}", e),
+        }
+        sleep(RELISTEN_DELAY).await;
+    }
+}
+
+/// Home subscriptions look follows up as each post arrives, so no other kind of insert has to reach the hub
+pub async fn handle_entity_inserted<T: QueryPostFn + QueryIsFollowingFn>(db_repo: &T, hub: &PostHub, inserted: EntityInserted) {
+    match inserted.entity {
+        EntityKind::Post => hub.publish_post_id(db_repo, inserted.id).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::realtime::hub::Topic;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::post::post::InsertPostFn;
+    use repository::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use repository::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    #[tokio::test]
+    async fn test_inserted_posts_are_pushed_to_subscriptions() {
+        let repo = InMemoryRepo::new();
+        let user_id = repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: "jill".to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: None,
+        }).await.unwrap();
+        let hub = PostHub::new();
+        let mut subscription = hub.subscribe(user_id, Topic::Profile { profile_id: user_id });
+        let post_id = repo.insert_standalone_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, user_id, "hello", None).await.unwrap();
+
+        handle_entity_inserted(&repo, &hub, EntityInserted { entity: EntityKind::Post, id: post_id.id }).await;
+        handle_entity_inserted(&repo, &hub, EntityInserted { entity: EntityKind::Post, id: post_id.id + 100 }).await;
+
+        let pushed = subscription.recv().await.unwrap();
+        assert!(pushed.id == post_id.id);
+        assert!(pushed.message == Some("hello".to_string()));
+        assert!(pushed.user_name == "jill");
+        assert!(subscription.receiver.try_recv().is_err());
+    }
+}
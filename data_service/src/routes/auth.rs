@@ -0,0 +1,581 @@
+use crate::app_state::AppState;
+use crate::auth::session::{ hash_token, new_random_token, AuthenticatedProfile };
+use crate::auth::signature::{ normalize_sui_address, verify_personal_message };
+use crate::auth::token::AccessTokenSigner;
+use crate::routes::errors::ApiError;
+use actix_web::{ http::header, web, HttpRequest, HttpResponse };
+use chrono::{ DateTime, Duration, Utc };
+use repository::repo::auth::auth::{
+    InsertAddressChallengeFn, InsertAuthChallengeFn, InsertSessionFn, QuerySessionByTokenHashFn, QuerySessionsByProfileFn, RevokeAllSessionsFn,
+    RevokeSessionFn, RotateSessionTokenFn, TakeAuthChallengeFn
+};
+use repository::repo::auth::model::SessionQueryResult;
+use repository::repo::error::RepositoryError;
+use repository::repo::profile::profile::QueryProfileFn;
+use serde::{ Deserialize, Serialize };
+
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Unused challenges a profile or address may have at once, more are refused until some are taken or expire
+const MAX_OUTSTANDING_CHALLENGES: i64 = 5;
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+/// Every refresh pushes the expiry out again, a device only has to sign in again after this long unused
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+const DEVICE_NAME_MAX_LEN: usize = 100;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ChallengeRequest {
+    pub profile_id: i64,
+}
+
+/// A wallet asking to create a profile, it does not need to have one yet
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AddressChallengeRequest {
+    pub address: String,
+}
+
+/// message is what the wallet signs as a personal message, it names the profile so a signature can not be replayed for another
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ChallengeResponse {
+    pub nonce: String,
+    pub message: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// signature is the serialized sui signature, base64 of the scheme flag, signature and public key.
+/// device_name labels the session in the session list, the user agent is used when it is left out
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LoginRequest {
+    pub nonce: String,
+    pub signature: String,
+    pub device_name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// The access token goes into the bearer header of requests, the refresh token is only sent to get new tokens
+/// and is replaced by every refresh
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TokenResponse {
+    pub profile_id: i64,
+    pub session_id: i64,
+    pub access_token: String,
+    pub access_token_expires_at: DateTime<Utc>,
+    pub refresh_token: String,
+    pub refresh_token_expires_at: DateTime<Utc>,
+}
+
+/// A signed in device, current marks the session of the access token used to list them
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SessionResponse {
+    pub id: i64,
+    pub device_name: String,
+    pub created_at: DateTime<Utc>,
+    pub refreshed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: SessionQueryResult, current_session_id: i64) -> Self {
+        Self {
+            current: session.id == current_session_id,
+            id: session.id,
+            device_name: session.device_name,
+            created_at: session.created_at,
+            refreshed_at: session.refreshed_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+pub fn challenge_message(profile_id: i64, nonce: &str) -> String {
+    format!("Sign in to dechat as profile {}\nnonce: {}", profile_id, nonce)
+}
+
+pub fn address_challenge_message(address: &str, nonce: &str) -> String {
+    format!("Create a dechat profile for {}\nnonce: {}", address, nonce)
+}
+
+fn device_name(req: &HttpRequest, device_name: Option<&str>) -> String {
+    let user_agent = || req.headers().get(header::USER_AGENT).and_then(|value| value.to_str().ok());
+    device_name.or_else(user_agent).unwrap_or_default().chars().take(DEVICE_NAME_MAX_LEN).collect()
+}
+
+fn token_response(
+    signer: &AccessTokenSigner,
+    profile_id: i64,
+    session_id: i64,
+    refresh_token: String,
+    refresh_token_expires_at: DateTime<Utc>
+) -> TokenResponse {
+    let access_token_expires_at = Utc::now() + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
+    TokenResponse {
+        profile_id,
+        session_id,
+        access_token: signer.sign(profile_id, session_id, access_token_expires_at),
+        access_token_expires_at,
+        refresh_token,
+        refresh_token_expires_at,
+    }
+}
+
+pub async fn create_challenge<T: QueryProfileFn + InsertAuthChallengeFn>(
+    app_data: web::Data<AppState<T>>,
+    json: web::Json<ChallengeRequest>
+) -> Result<web::Json<ChallengeResponse>, ApiError> {
+    let profile = app_data.db_repo.query_profile(json.profile_id).await?.ok_or(ApiError::NotFound)?;
+    if profile.owner_address.is_none() {
+        return Err(ApiError::BadRequest("profile has no on-chain owner to sign in with".to_string()));
+    }
+
+    let nonce = new_random_token();
+    let expires_at = Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES);
+    app_data.db_repo.insert_auth_challenge(profile.id, &nonce, expires_at, MAX_OUTSTANDING_CHALLENGES).await?;
+
+    Ok(web::Json(ChallengeResponse { message: challenge_message(profile.id, &nonce), nonce, expires_at }))
+}
+
+/// Challenge a wallet signs to create a profile, see verify_address_challenge
+pub async fn create_address_challenge<T: InsertAddressChallengeFn>(
+    app_data: web::Data<AppState<T>>,
+    json: web::Json<AddressChallengeRequest>
+) -> Result<web::Json<ChallengeResponse>, ApiError> {
+    let address = normalize_sui_address(&json.address)
+        .ok_or_else(|| ApiError::BadRequest("address is not a sui address".to_string()))?;
+
+    let nonce = new_random_token();
+    let expires_at = Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES);
+    app_data.db_repo.insert_address_challenge(&address, &nonce, expires_at, MAX_OUTSTANDING_CHALLENGES).await?;
+
+    Ok(web::Json(ChallengeResponse { message: address_challenge_message(&address, &nonce), nonce, expires_at }))
+}
+
+/// Uses up the address challenge of nonce and returns its address once signature shows that wallet signed it
+pub async fn verify_address_challenge<T: TakeAuthChallengeFn>(
+    db_repo: &T,
+    nonce: &str,
+    signature: &str
+) -> Result<String, ApiError> {
+    let challenge = db_repo.take_auth_challenge(nonce).await?
+        .ok_or_else(|| ApiError::Unauthorized("challenge not found or already used".to_string()))?;
+    if challenge.expires_at <= Utc::now() {
+        return Err(ApiError::Unauthorized("challenge has expired".to_string()));
+    }
+    let address = challenge.address
+        .ok_or_else(|| ApiError::Unauthorized("challenge is for signing in, not for an address".to_string()))?;
+
+    let signer_address = verify_personal_message(address_challenge_message(&address, &challenge.nonce).as_bytes(), signature)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    if signer_address != address {
+        return Err(ApiError::Unauthorized("signature is not from the challenged address".to_string()));
+    }
+    Ok(address)
+}
+
+/// Starts a session for the device. The challenge is used up by the attempt whether the signature checks out or not
+pub async fn login<T: TakeAuthChallengeFn + QueryProfileFn + InsertSessionFn>(
+    app_data: web::Data<AppState<T>>,
+    signer: web::Data<AccessTokenSigner>,
+    req: HttpRequest,
+    json: web::Json<LoginRequest>
+) -> Result<web::Json<TokenResponse>, ApiError> {
+    let challenge = app_data.db_repo.take_auth_challenge(&json.nonce).await?
+        .ok_or_else(|| ApiError::Unauthorized("challenge not found or already used".to_string()))?;
+    if challenge.expires_at <= Utc::now() {
+        return Err(ApiError::Unauthorized("challenge has expired".to_string()));
+    }
+
+    let profile_id = challenge.profile_id
+        .ok_or_else(|| ApiError::Unauthorized("challenge is for creating a profile, not for signing in".to_string()))?;
+
+    let message = challenge_message(profile_id, &challenge.nonce);
+    let signer_address = verify_personal_message(message.as_bytes(), &json.signature)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    let owner_address = app_data.db_repo.query_profile(profile_id).await?
+        .and_then(|profile| profile.owner_address);
+    if owner_address.map(|address| address.to_lowercase()) != Some(signer_address) {
+        return Err(ApiError::Unauthorized("signature is not from the profile's owner".to_string()));
+    }
+
+    let refresh_token = new_random_token();
+    let refresh_token_expires_at = Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS);
+    let session_id = app_data.db_repo.insert_session(
+        profile_id,
+        &hash_token(&refresh_token),
+        &device_name(&req, json.device_name.as_deref()),
+        refresh_token_expires_at
+    ).await?;
+
+    Ok(web::Json(token_response(&signer, profile_id, session_id, refresh_token, refresh_token_expires_at)))
+}
+
+/// Trades a refresh token for a new access and refresh token, the presented refresh token stops working
+pub async fn refresh<T: QuerySessionByTokenHashFn + RotateSessionTokenFn>(
+    app_data: web::Data<AppState<T>>,
+    signer: web::Data<AccessTokenSigner>,
+    json: web::Json<RefreshRequest>
+) -> Result<web::Json<TokenResponse>, ApiError> {
+    let token_hash = hash_token(&json.refresh_token);
+    let session = match app_data.db_repo.query_session_by_token_hash(&token_hash).await? {
+        Some(session) if session.expires_at > Utc::now() => session,
+        Some(_) => return Err(ApiError::Unauthorized("session has expired".to_string())),
+        None => return Err(ApiError::Unauthorized("refresh token not found or already used".to_string())),
+    };
+
+    let refresh_token = new_random_token();
+    let refresh_token_expires_at = Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS);
+    app_data.db_repo
+        .rotate_session_token(session.id, &token_hash, &hash_token(&refresh_token), refresh_token_expires_at)
+        .await
+        .map_err(|e| match e {
+            RepositoryError::NotFound => ApiError::Unauthorized("refresh token not found or already used".to_string()),
+            e => e.into(),
+        })?;
+
+    Ok(web::Json(token_response(&signer, session.profile_id, session.id, refresh_token, refresh_token_expires_at)))
+}
+
+pub async fn get_sessions<T: QuerySessionsByProfileFn>(
+    app_data: web::Data<AppState<T>>,
+    auth: AuthenticatedProfile
+) -> Result<web::Json<Vec<SessionResponse>>, ApiError> {
+    let sessions = app_data.db_repo.query_sessions_by_profile(auth.profile_id).await?;
+
+    Ok(web::Json(sessions.into_iter().map(|session| SessionResponse::new(session, auth.session_id)).collect()))
+}
+
+/// Signs a device out, only sessions of the signed in profile can be revoked
+pub async fn revoke_session<T: RevokeSessionFn>(
+    app_data: web::Data<AppState<T>>,
+    auth: AuthenticatedProfile,
+    path: web::Path<i64>
+) -> Result<HttpResponse, ApiError> {
+    app_data.db_repo.revoke_session(auth.profile_id, path.into_inner()).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Signs every device of the profile out, including the one making the request
+pub async fn revoke_all_sessions<T: RevokeAllSessionsFn>(
+    app_data: web::Data<AppState<T>>,
+    auth: AuthenticatedProfile
+) -> Result<HttpResponse, ApiError> {
+    app_data.db_repo.revoke_all_sessions(auth.profile_id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::signature::sui_address;
+    use crate::test_helpers::fixtures::{ bearer_header, get_app, sign_personal_message };
+    use actix_web::{ dev::{ Service, ServiceResponse }, http::StatusCode, test, Error };
+    use actix_http::Request;
+    use ed25519_consensus::SigningKey;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use repository::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    async fn insert_owned_profile(repo: &InMemoryRepo, signing_key: &SigningKey) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: "jill".to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: Some(sui_address(&signing_key.verification_key().to_bytes())),
+        }).await.unwrap()
+    }
+
+    async fn login_with_key(
+        app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+        signing_key: &SigningKey,
+        profile_id: i64,
+        user_agent: &str
+    ) -> TokenResponse {
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/challenge")
+            .set_json(ChallengeRequest { profile_id })
+            .to_request();
+        let challenge: ChallengeResponse = test::call_and_read_body_json(app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/login")
+            .insert_header((header::USER_AGENT, user_agent))
+            .set_json(LoginRequest {
+                signature: sign_personal_message(signing_key, challenge.message.as_bytes()),
+                nonce: challenge.nonce,
+                device_name: None,
+            })
+            .to_request();
+        test::call_and_read_body_json(app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_address_challenge_is_not_a_login() {
+        let repo = InMemoryRepo::new();
+        let signing_key = SigningKey::new(rand::thread_rng());
+        insert_owned_profile(&repo, &signing_key).await;
+        let app = get_app(repo.clone()).await;
+        let address = sui_address(&signing_key.verification_key().to_bytes());
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/address_challenge")
+            .set_json(AddressChallengeRequest { address: "0xabc".to_string() })
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/address_challenge")
+            .set_json(AddressChallengeRequest { address: address.to_uppercase().replacen("0X", "0x", 1) })
+            .to_request();
+        let challenge: ChallengeResponse = test::call_and_read_body_json(&app, req).await;
+        assert!(challenge.message == address_challenge_message(&address, &challenge.nonce));
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/login")
+            .set_json(LoginRequest {
+                nonce: challenge.nonce.clone(),
+                signature: sign_personal_message(&signing_key, challenge.message.as_bytes()),
+                device_name: None,
+            })
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_login_with_signed_challenge() {
+        let repo = InMemoryRepo::new();
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let profile_id = insert_owned_profile(&repo, &signing_key).await;
+        let app = get_app(repo.clone()).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/challenge")
+            .set_json(ChallengeRequest { profile_id })
+            .to_request();
+        let challenge: ChallengeResponse = test::call_and_read_body_json(&app, req).await;
+        assert!(challenge.message == challenge_message(profile_id, &challenge.nonce));
+
+        let signature = sign_personal_message(&signing_key, challenge.message.as_bytes());
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/login")
+            .set_json(LoginRequest {
+                nonce: challenge.nonce.clone(),
+                signature: signature.clone(),
+                device_name: Some("laptop".to_string()),
+            })
+            .to_request();
+        let tokens: TokenResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert!(tokens.profile_id == profile_id);
+        let stored = repo.query_session_by_token_hash(&hash_token(&tokens.refresh_token)).await.unwrap().unwrap();
+        assert!(stored.id == tokens.session_id && stored.profile_id == profile_id);
+        assert!(stored.device_name == "laptop");
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/login")
+            .set_json(LoginRequest { nonce: challenge.nonce, signature, device_name: None })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED, "a nonce can only be used once");
+    }
+
+    #[actix_web::test]
+    async fn test_login_rejects_other_keys() {
+        let repo = InMemoryRepo::new();
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let profile_id = insert_owned_profile(&repo, &signing_key).await;
+        let app = get_app(repo).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/challenge")
+            .set_json(ChallengeRequest { profile_id })
+            .to_request();
+        let challenge: ChallengeResponse = test::call_and_read_body_json(&app, req).await;
+
+        let other_key = SigningKey::new(rand::thread_rng());
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/login")
+            .set_json(LoginRequest {
+                nonce: challenge.nonce,
+                signature: sign_personal_message(&other_key, challenge.message.as_bytes()),
+                device_name: None,
+            })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_login_rejects_expired_challenge() {
+        let repo = InMemoryRepo::new();
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let profile_id = insert_owned_profile(&repo, &signing_key).await;
+        let nonce = new_random_token();
+        repo.insert_auth_challenge(profile_id, &nonce, Utc::now() - Duration::minutes(1), MAX_OUTSTANDING_CHALLENGES).await.unwrap();
+        let app = get_app(repo).await;
+
+        let signature = sign_personal_message(&signing_key, challenge_message(profile_id, &nonce).as_bytes());
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/login")
+            .set_json(LoginRequest { nonce, signature, device_name: None })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_challenge_needs_owned_profile() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/challenge")
+            .set_json(ChallengeRequest { profile_id: 999 })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_challenges_are_capped_per_profile() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_owned_profile(&repo, &SigningKey::new(rand::thread_rng())).await;
+        let app = get_app(repo).await;
+
+        for _ in 0..MAX_OUTSTANDING_CHALLENGES {
+            let req = test::TestRequest::post()
+                .uri("/v1/auth/challenge")
+                .set_json(ChallengeRequest { profile_id })
+                .to_request();
+            assert!(test::call_service(&app, req).await.status() == StatusCode::OK);
+        }
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/challenge")
+            .set_json(ChallengeRequest { profile_id })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[actix_web::test]
+    async fn test_refresh_rotates_refresh_token() {
+        let repo = InMemoryRepo::new();
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let profile_id = insert_owned_profile(&repo, &signing_key).await;
+        let app = get_app(repo).await;
+        let tokens = login_with_key(&app, &signing_key, profile_id, "laptop").await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/refresh")
+            .set_json(RefreshRequest { refresh_token: tokens.refresh_token.clone() })
+            .to_request();
+        let refreshed: TokenResponse = test::call_and_read_body_json(&app, req).await;
+
+        assert!(refreshed.session_id == tokens.session_id);
+        assert!(refreshed.refresh_token != tokens.refresh_token);
+        let req = test::TestRequest::get()
+            .uri("/v1/auth/sessions")
+            .insert_header(bearer_header(&refreshed.access_token))
+            .to_request();
+        let sessions: Vec<SessionResponse> = test::call_and_read_body_json(&app, req).await;
+        assert!(sessions.len() == 1 && sessions[0].refreshed_at.is_some());
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/refresh")
+            .set_json(RefreshRequest { refresh_token: tokens.refresh_token })
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED, "a refresh token can only be used once");
+    }
+
+    #[actix_web::test]
+    async fn test_list_and_revoke_sessions() {
+        let repo = InMemoryRepo::new();
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let profile_id = insert_owned_profile(&repo, &signing_key).await;
+        let app = get_app(repo).await;
+        let laptop = login_with_key(&app, &signing_key, profile_id, "laptop").await;
+        let phone = login_with_key(&app, &signing_key, profile_id, "phone").await;
+        let tablet = login_with_key(&app, &signing_key, profile_id, "tablet").await;
+
+        let req = test::TestRequest::get()
+            .uri("/v1/auth/sessions")
+            .insert_header(bearer_header(&laptop.access_token))
+            .to_request();
+        let sessions: Vec<SessionResponse> = test::call_and_read_body_json(&app, req).await;
+        let devices: Vec<(&str, bool)> = sessions.iter().map(|session| (session.device_name.as_str(), session.current)).collect();
+        assert!(devices == vec![("tablet", false), ("phone", false), ("laptop", true)]);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/v1/auth/sessions/{}", phone.session_id))
+            .insert_header(bearer_header(&laptop.access_token))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::NO_CONTENT);
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/refresh")
+            .set_json(RefreshRequest { refresh_token: phone.refresh_token })
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::delete()
+            .uri("/v1/auth/sessions")
+            .insert_header(bearer_header(&laptop.access_token))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::NO_CONTENT);
+        for refresh_token in [laptop.refresh_token, tablet.refresh_token] {
+            let req = test::TestRequest::post()
+                .uri("/v1/auth/refresh")
+                .set_json(RefreshRequest { refresh_token })
+                .to_request();
+            assert!(test::call_service(&app, req).await.status() == StatusCode::UNAUTHORIZED);
+        }
+
+        let req = test::TestRequest::get().uri("/v1/auth/sessions").to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_access_token_of_revoked_session_is_rejected() {
+        let repo = InMemoryRepo::new();
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let profile_id = insert_owned_profile(&repo, &signing_key).await;
+        let app = get_app(repo).await;
+        let laptop = login_with_key(&app, &signing_key, profile_id, "laptop").await;
+        let stolen = login_with_key(&app, &signing_key, profile_id, "stolen").await;
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/v1/auth/sessions/{}", stolen.session_id))
+            .insert_header(bearer_header(&laptop.access_token))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::NO_CONTENT);
+
+        let req = test::TestRequest::get()
+            .uri("/v1/auth/sessions")
+            .insert_header(bearer_header(&stolen.access_token))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::UNAUTHORIZED, "the access token has not expired yet");
+
+        let req = test::TestRequest::delete()
+            .uri("/v1/auth/sessions")
+            .insert_header(bearer_header(&laptop.access_token))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::NO_CONTENT);
+        let req = test::TestRequest::get()
+            .uri("/v1/auth/sessions")
+            .insert_header(bearer_header(&laptop.access_token))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status() == StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,108 @@
+use actix_web::{ HttpResponse, ResponseError, http::StatusCode };
+use log::error;
+use repository::repo::error::RepositoryError;
+use serde::{ Deserialize, Serialize };
+
+pub const NOT_OWNER_CODE: &str = "not_owner";
+
+/// Details of a 403 sent along with the message, code lets clients tell ownership failures apart
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct OwnershipViolation {
+    pub code: String,
+    pub resource: String,
+    pub resource_id: i64,
+}
+
+impl OwnershipViolation {
+    pub fn new(resource: &str, resource_id: i64) -> Self {
+        Self { code: NOT_OWNER_CODE.to_string(), resource: resource.to_string(), resource_id }
+    }
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(OwnershipViolation),
+    NotFound,
+    Conflict(String),
+    PayloadTooLarge(String),
+    TooManyRequests(String),
+    Unavailable,
+    InternalServerError,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(flatten)]
+    ownership: Option<OwnershipViolation>,
+}
+
+impl std::error::Error for ApiError {}
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadRequest(msg) => write!(f, "{}", msg),
+            Self::Unauthorized(msg) => write!(f, "{}", msg),
+            Self::Forbidden(violation) => write!(
+                f,
+                "{} {} does not belong to the signed in wallet",
+                violation.resource,
+                violation.resource_id
+            ),
+            Self::NotFound => write!(f, "Not found"),
+            Self::Conflict(msg) => write!(f, "{}", msg),
+            Self::PayloadTooLarge(msg) => write!(f, "{}", msg),
+            Self::TooManyRequests(msg) => write!(f, "{}", msg),
+            Self::Unavailable => write!(f, "Service unavailable, please try again later"),
+            Self::InternalServerError => write!(f, "Internal server error"),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let ownership = match self {
+            Self::Forbidden(violation) => Some(violation.clone()),
+            _ => None,
+        };
+        HttpResponse::build(self.status_code()).json(ErrorResponse { error: self.to_string(), ownership })
+    }
+}
+
+/// Repository details are logged here and never sent back to the client
+impl From<RepositoryError> for ApiError {
+    fn from(e: RepositoryError) -> Self {
+        match e {
+            RepositoryError::NotFound => Self::NotFound,
+            RepositoryError::UniqueViolation { .. } => Self::Conflict(e.to_string()),
+            RepositoryError::ForeignKeyViolation { .. }
+            | RepositoryError::ValueTooLong { .. }
+            | RepositoryError::SelfFollow => Self::BadRequest(e.to_string()),
+            RepositoryError::LimitReached { .. } => Self::TooManyRequests(e.to_string()),
+            RepositoryError::Unavailable(_) => {
+                error!("repository unavailable: {}", e);
+                Self::Unavailable
+            },
+            RepositoryError::Database(_) => {
+                error!("repository error: {}", e);
+                Self::InternalServerError
+            },
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::app_state::AppState;
+use crate::auth::ownership::require_profile_owner;
+use crate::auth::session::AuthenticatedProfile;
+use crate::routes::errors::ApiError;
+use crate::routes::route_utils::OutputId;
+use actix_web::{ web, HttpResponse };
+use repository::repo::follow::follow::{ FollowUserFn, UnfollowUserFn };
+use repository::repo::profile::profile::QueryProfileFn;
+
+/// The path is the follower then the profile to follow, the follower must belong to the caller
+pub async fn follow_user<T: FollowUserFn + QueryProfileFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<(i64, i64)>,
+    auth: AuthenticatedProfile
+) -> Result<web::Json<OutputId>, ApiError> {
+    let (follower_id, following_id) = path.into_inner();
+    require_profile_owner(&app_data.db_repo, &auth, follower_id).await?;
+
+    let id = app_data.db_repo.follow_user(follower_id, following_id).await?;
+
+    Ok(web::Json(OutputId { id }))
+}
+
+pub async fn unfollow_user<T: UnfollowUserFn + QueryProfileFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<(i64, i64)>,
+    auth: AuthenticatedProfile
+) -> Result<HttpResponse, ApiError> {
+    let (follower_id, following_id) = path.into_inner();
+    require_profile_owner(&app_data.db_repo, &auth, follower_id).await?;
+
+    app_data.db_repo.unfollow_user(follower_id, following_id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routes::errors::{ OwnershipViolation, NOT_OWNER_CODE };
+    use crate::test_helpers::fixtures::{ bearer_header, get_access_token, get_app };
+    use actix_web::{ http::StatusCode, test };
+    use repository::repo::follow::follow::QueryIsFollowingFn;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use repository::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    async fn insert_profile(repo: &InMemoryRepo, user_name: &str, owner_address: &str) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: user_name.to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: Some(owner_address.to_string()),
+        }).await.unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_follow_and_unfollow() {
+        let repo = InMemoryRepo::new();
+        let jill_id = insert_profile(&repo, "jill", "0xa1").await;
+        let jill_alt_id = insert_profile(&repo, "jill_alt", "0xa1").await;
+        let bob_id = insert_profile(&repo, "bob", "0xb2").await;
+        let token = get_access_token(&repo, jill_id).await;
+        let app = get_app(repo.clone()).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/v1/profile/{}/following/{}", jill_alt_id, bob_id))
+            .insert_header(bearer_header(&token))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::OK, "profiles of the same wallet belong to the caller");
+        assert!(repo.query_is_following(jill_alt_id, bob_id).await.unwrap());
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/v1/profile/{}/following/{}", jill_alt_id, bob_id))
+            .insert_header(bearer_header(&token))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::NO_CONTENT);
+        assert!(!repo.query_is_following(jill_alt_id, bob_id).await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_follow_as_other_profile_is_forbidden() {
+        let repo = InMemoryRepo::new();
+        let jill_id = insert_profile(&repo, "jill", "0xa1").await;
+        let bob_id = insert_profile(&repo, "bob", "0xb2").await;
+        let token = get_access_token(&repo, jill_id).await;
+        let app = get_app(repo.clone()).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/v1/profile/{}/following/{}", bob_id, jill_id))
+            .insert_header(bearer_header(&token))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::FORBIDDEN);
+        let violation: OwnershipViolation = test::read_body_json(res).await;
+        assert!(violation == OwnershipViolation { code: NOT_OWNER_CODE.to_string(), resource: "profile".to_string(), resource_id: bob_id });
+        assert!(!repo.query_is_following(bob_id, jill_id).await.unwrap());
+    }
+}
@@ -0,0 +1,406 @@
+use crate::app_state::AppState;
+use crate::auth::ownership::require_profile_owner;
+use crate::auth::session::AuthenticatedProfile;
+use crate::routes::errors::ApiError;
+use crate::routes::route_utils::{ local_chain_identity, MultipartFields, OutputId };
+use actix_multipart::Multipart;
+use actix_web::web;
+use chrono::{ DateTime, Utc };
+use repository::repo::chain::chain::QueryChainIdByNameFn;
+use repository::repo::post::model::{ PostCursor, PostWithProfileQueryResult };
+use repository::repo::post::post::{
+    InsertPostFn, InsertResponsePostFn, InsertSharePostFn, QueryPostFn, QueryPostsByUserFn
+};
+use repository::repo::profile::profile::QueryProfileFn;
+use serde::Deserialize;
+
+const DEFAULT_PAGE_SIZE: i16 = 20;
+const MAX_PAGE_SIZE: i16 = 100;
+
+/// Keyset paging parameters, pass the updated_at and id of the last post of the previous page
+#[derive(Deserialize, Debug)]
+pub struct PostsQuery {
+    pub last_updated_at: Option<DateTime<Utc>>,
+    pub last_id: Option<i64>,
+    pub page_size: Option<i16>,
+}
+
+impl PostsQuery {
+    fn cursor(&self) -> Result<Option<PostCursor>, ApiError> {
+        match (self.last_updated_at, self.last_id) {
+            (Some(updated_at), Some(id)) => Ok(Some(PostCursor { updated_at, id })),
+            (None, None) => Ok(None),
+            _ => Err(ApiError::BadRequest("last_updated_at and last_id must be given together".to_string())),
+        }
+    }
+
+    fn page_size(&self) -> Result<i16, ApiError> {
+        match self.page_size {
+            Some(page_size) if !(1..=MAX_PAGE_SIZE).contains(&page_size) => {
+                Err(ApiError::BadRequest(format!("page_size must be between 1 and {}", MAX_PAGE_SIZE)))
+            },
+            Some(page_size) => Ok(page_size),
+            None => Ok(DEFAULT_PAGE_SIZE),
+        }
+    }
+}
+
+pub async fn get_post<T: QueryPostFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<i64>
+) -> Result<Option<web::Json<PostWithProfileQueryResult>>, ApiError> {
+    let post = app_data.db_repo.query_post(path.into_inner()).await?;
+
+    Ok(post.map(web::Json))
+}
+
+pub async fn get_posts_by_user<T: QueryPostsByUserFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<i64>,
+    query: web::Query<PostsQuery>
+) -> Result<web::Json<Vec<PostWithProfileQueryResult>>, ApiError> {
+    let posts = app_data.db_repo
+        .query_posts_by_user(path.into_inner(), query.cursor()?, query.page_size()?)
+        .await?;
+
+    Ok(web::Json(posts))
+}
+
+pub async fn create_post<T: InsertPostFn + QueryProfileFn + QueryChainIdByNameFn>(
+    app_data: web::Data<AppState<T>>,
+    auth: AuthenticatedProfile,
+    payload: Multipart
+) -> Result<web::Json<OutputId>, ApiError> {
+    let fields = MultipartFields::read(payload, &["user_id", "message", "image"]).await?;
+    let user_id = fields.required_i64("user_id")?;
+    require_profile_owner(&app_data.db_repo, &auth, user_id).await?;
+
+    let (chain_id, chain_asset_id) = local_chain_identity(&app_data.db_repo).await?;
+    let post_id = app_data.db_repo.insert_standalone_post(
+        chain_asset_id.as_str(),
+        chain_id,
+        user_id,
+        fields.required_text("message")?.as_str(),
+        fields.bytes("image")
+    ).await?;
+
+    Ok(web::Json(OutputId { id: post_id.id }))
+}
+
+/// The path id is the post being replied to
+pub async fn create_response_post<T: InsertResponsePostFn + QueryProfileFn + QueryChainIdByNameFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<i64>,
+    auth: AuthenticatedProfile,
+    payload: Multipart
+) -> Result<web::Json<OutputId>, ApiError> {
+    let fields = MultipartFields::read(payload, &["user_id", "message", "image"]).await?;
+    let user_id = fields.required_i64("user_id")?;
+    require_profile_owner(&app_data.db_repo, &auth, user_id).await?;
+
+    let (chain_id, chain_asset_id) = local_chain_identity(&app_data.db_repo).await?;
+    let post_id = app_data.db_repo.insert_response_post(
+        chain_asset_id.as_str(),
+        chain_id,
+        user_id,
+        fields.required_text("message")?.as_str(),
+        fields.bytes("image"),
+        path.into_inner()
+    ).await?;
+
+    Ok(web::Json(OutputId { id: post_id.id }))
+}
+
+/// The path id is the post being shared, a share may come without a message of its own but never with an image
+pub async fn create_share_post<T: InsertSharePostFn + QueryProfileFn + QueryChainIdByNameFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<i64>,
+    auth: AuthenticatedProfile,
+    payload: Multipart
+) -> Result<web::Json<OutputId>, ApiError> {
+    let fields = MultipartFields::read(payload, &["user_id", "message", "image"]).await?;
+    if fields.bytes("image").is_some() {
+        return Err(ApiError::BadRequest("shares cannot have an image".to_string()));
+    }
+    let user_id = fields.required_i64("user_id")?;
+    require_profile_owner(&app_data.db_repo, &auth, user_id).await?;
+
+    let (chain_id, chain_asset_id) = local_chain_identity(&app_data.db_repo).await?;
+    let post_id = app_data.db_repo.insert_share_post(
+        chain_asset_id.as_str(),
+        chain_id,
+        user_id,
+        fields.text("message")?.filter(|message| !message.is_empty()),
+        path.into_inner()
+    ).await?;
+
+    Ok(web::Json(OutputId { id: post_id.id }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::errors::OwnershipViolation;
+    use crate::routes::route_utils::{ MAX_PART_BYTES, MAX_PARTS };
+    use crate::test_helpers::fixtures::{ bearer_header, get_access_token, get_app, get_post_create_multipart };
+    use actix_web::{ http::{ header, StatusCode }, test, web::BytesMut };
+    use chrono::SecondsFormat;
+    use repository::repo::chain::chain::LOCAL_CHAIN;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::post::post::QuerySharedPostFn;
+    use repository::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use repository::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    const BOUNDARY: &str = "----WebKitFormBoundary0gYK6PWVgKwyVIvS";
+
+    fn multipart_content_type() -> (header::HeaderName, String) {
+        (header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", BOUNDARY))
+    }
+
+    async fn insert_profile(repo: &InMemoryRepo, user_name: &str) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: user_name.to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: Some(vec![1, 2, 3]),
+            owner_address: None,
+        }).await.unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_create_and_get_post() {
+        let repo = InMemoryRepo::new();
+        let user_id = insert_profile(&repo, "jill").await;
+        let token = get_access_token(&repo, user_id).await;
+        let app = get_app(repo.clone()).await;
+        let image = vec![0xFF, 0xD8, 1, 2, 3, 0xFF, 0xD9];
+
+        let req = test::TestRequest::post()
+            .uri("/v1/post")
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, Some("hello"), Some(&image), BOUNDARY))
+            .to_request();
+        let output_id: OutputId = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get().uri(&format!("/v1/post/{}", output_id.id)).to_request();
+        let post: PostWithProfileQueryResult = test::call_and_read_body_json(&app, req).await;
+        assert!(post.id == output_id.id);
+        assert!(post.message == Some("hello".to_string()));
+        assert!(post.image == Some(image));
+        assert!(post.user_name == "jill");
+        assert!(post.full_name == "Jill Simon");
+        assert!(post.avatar == Some(vec![1, 2, 3]));
+        assert!(post.respondee_post_id.is_none());
+        assert!(repo.query_chain_id_by_name(LOCAL_CHAIN).await.unwrap() == Some(post.chain_id));
+        assert!(post.chain_asset_id.starts_with(LOCAL_CHAIN));
+    }
+
+    #[actix_web::test]
+    async fn test_create_response_and_share_post() {
+        let repo = InMemoryRepo::new();
+        let user_id = insert_profile(&repo, "jill").await;
+        let token = get_access_token(&repo, user_id).await;
+        let app = get_app(repo.clone()).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/post")
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, Some("hello"), None, BOUNDARY))
+            .to_request();
+        let respondee: OutputId = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/v1/post/{}/response", respondee.id))
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, Some("reply"), None, BOUNDARY))
+            .to_request();
+        let responder: OutputId = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get().uri(&format!("/v1/post/{}", responder.id)).to_request();
+        let post: PostWithProfileQueryResult = test::call_and_read_body_json(&app, req).await;
+        assert!(post.respondee_post_id == Some(respondee.id));
+        assert!(post.image.is_none());
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/v1/post/{}/share", respondee.id))
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, None, None, BOUNDARY))
+            .to_request();
+        let share: OutputId = test::call_and_read_body_json(&app, req).await;
+
+        let shared_post = repo.query_shared_post(share.id).await.unwrap().unwrap();
+        assert!(shared_post.message.is_none());
+        assert!(shared_post.sharee_post_id == Some(respondee.id));
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/v1/post/{}/share", respondee.id))
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, None, Some(&vec![1, 2, 3]), BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::BAD_REQUEST, "share images are rejected rather than dropped");
+    }
+
+    #[actix_web::test]
+    async fn test_create_post_errors() {
+        let repo = InMemoryRepo::new();
+        let user_id = insert_profile(&repo, "jill").await;
+        let token = get_access_token(&repo, user_id).await;
+        let app = get_app(repo).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/post")
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, None, None, BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::BAD_REQUEST, "standalone posts need a message");
+
+        let req = test::TestRequest::post()
+            .uri("/v1/post/999/response")
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, Some("reply"), None, BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::BAD_REQUEST, "replies to missing posts are rejected");
+
+        let req = test::TestRequest::get().uri("/v1/post/999").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::post()
+            .uri("/v1/post")
+            .insert_header(multipart_content_type())
+            .set_payload(get_post_create_multipart(user_id, Some("hello"), None, BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED, "posting needs a session");
+
+        let req = test::TestRequest::post()
+            .uri("/v1/post")
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header("unknown"))
+            .set_payload(get_post_create_multipart(user_id, Some("hello"), None, BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+
+        let image = vec![0; MAX_PART_BYTES + 1];
+        let req = test::TestRequest::post()
+            .uri("/v1/post")
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(user_id, Some("hello"), Some(&image), BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::PAYLOAD_TOO_LARGE, "images over the part limit are rejected");
+    }
+
+    #[actix_web::test]
+    async fn test_create_post_rejects_unexpected_parts() {
+        let repo = InMemoryRepo::new();
+        let user_id = insert_profile(&repo, "jill").await;
+        let token = get_access_token(&repo, user_id).await;
+        let app = get_app(repo.clone()).await;
+        let part = |disposition: &str, value: &str| {
+            format!("--{}\r\nContent-Disposition: form-data{}\r\n\r\n{}\r\n", BOUNDARY, disposition, value)
+        };
+
+        for (extra, expected_error) in [
+            (part("; name=\"chain_id\"", "1"), "chain_id is not an expected field".to_string()),
+            (part("; name=\"message\"", "again"), "message was sent more than once".to_string()),
+            (part("", "").repeat(MAX_PARTS), format!("form has more than {} parts", MAX_PARTS)),
+        ] {
+            let mut payload = BytesMut::from(extra.as_bytes());
+            payload.extend(get_post_create_multipart(user_id, Some("hello"), None, BOUNDARY));
+            // mixed rather than form-data, so the parts without a name are read and skipped instead of refused
+            let req = test::TestRequest::post()
+                .uri("/v1/post")
+                .insert_header((header::CONTENT_TYPE, format!("multipart/mixed; boundary={}", BOUNDARY)))
+                .insert_header(bearer_header(&token))
+                .set_payload(payload)
+                .to_request();
+            let res = test::call_service(&app, req).await;
+
+            assert!(res.status() == StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = test::read_body_json(res).await;
+            assert!(body["error"] == expected_error.as_str());
+        }
+        assert!(repo.query_posts_by_user(user_id, None, 10).await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_get_posts_by_user() {
+        let repo = InMemoryRepo::new();
+        let user_id = insert_profile(&repo, "jill").await;
+        let token = get_access_token(&repo, user_id).await;
+        let app = get_app(repo).await;
+
+        let mut expected_ids = vec![];
+        for message in ["one", "two", "three"] {
+            let req = test::TestRequest::post()
+                .uri("/v1/post")
+                .insert_header(multipart_content_type())
+                .insert_header(bearer_header(&token))
+            .insert_header(bearer_header(&token))
+                .set_payload(get_post_create_multipart(user_id, Some(message), None, BOUNDARY))
+                .to_request();
+            let output_id: OutputId = test::call_and_read_body_json(&app, req).await;
+            expected_ids.push(output_id.id);
+        }
+        expected_ids.reverse();
+
+        let req = test::TestRequest::get().uri(&format!("/v1/posts/user/{}?page_size=2", user_id)).to_request();
+        let first_page: Vec<PostWithProfileQueryResult> = test::call_and_read_body_json(&app, req).await;
+        let last = first_page.last().unwrap();
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/v1/posts/user/{}?page_size=2&last_updated_at={}&last_id={}",
+                user_id,
+                last.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                last.id
+            ))
+            .to_request();
+        let second_page: Vec<PostWithProfileQueryResult> = test::call_and_read_body_json(&app, req).await;
+
+        let post_ids: Vec<i64> = first_page.iter().chain(second_page.iter()).map(|post| post.id).collect();
+        assert!(first_page.len() == 2);
+        assert!(post_ids == expected_ids);
+
+        let req = test::TestRequest::get().uri(&format!("/v1/posts/user/{}?last_id=1", user_id)).to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_post_as_other_profile_is_forbidden() {
+        let repo = InMemoryRepo::new();
+        let user_id = insert_profile(&repo, "jill").await;
+        let other_user_id = insert_profile(&repo, "bob").await;
+        let token = get_access_token(&repo, user_id).await;
+        let app = get_app(repo.clone()).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/post")
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_post_create_multipart(other_user_id, Some("hello"), None, BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::FORBIDDEN);
+        let violation: OwnershipViolation = test::read_body_json(res).await;
+        assert!(violation.resource_id == other_user_id);
+        assert!(repo.query_posts_by_user(other_user_id, None, 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::app_state::AppState;
+use crate::auth::ownership::require_profile_owner;
+use crate::auth::session::AuthenticatedProfile;
+use crate::routes::auth::verify_address_challenge;
+use crate::routes::errors::ApiError;
+use crate::routes::route_utils::{ local_chain_identity, MultipartFields, OutputId };
+use actix_multipart::Multipart;
+use actix_web::{ web, HttpResponse };
+use repository::repo::auth::auth::TakeAuthChallengeFn;
+use repository::repo::chain::chain::QueryChainIdByNameFn;
+use repository::repo::profile::model::{ ProfileCreate, ProfileQueryResult, ProfileUpdate };
+use repository::repo::profile::profile::{
+    InsertProfileFn, QueryProfileByUserNameFn, QueryProfileFn, UpdateProfileFn
+};
+
+pub async fn get_profile<T: QueryProfileFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<i64>
+) -> Result<Option<web::Json<ProfileQueryResult>>, ApiError> {
+    let profile = app_data.db_repo.query_profile(path.into_inner()).await?;
+
+    Ok(profile.map(web::Json))
+}
+
+pub async fn get_profile_by_user<T: QueryProfileByUserNameFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<String>
+) -> Result<Option<web::Json<ProfileQueryResult>>, ApiError> {
+    let profile = app_data.db_repo.query_profile_by_user_name(path.into_inner().as_str()).await?;
+
+    Ok(profile.map(web::Json))
+}
+
+/// The new profile belongs to the wallet that signed the address challenge of nonce, so a wallet without a profile
+/// can create its first one. It lives on the local chain under an asset id chosen by the server
+pub async fn create_profile<T: InsertProfileFn + TakeAuthChallengeFn + QueryChainIdByNameFn>(
+    app_data: web::Data<AppState<T>>,
+    payload: Multipart
+) -> Result<web::Json<OutputId>, ApiError> {
+    let fields = MultipartFields::read(
+        payload,
+        &["nonce", "signature", "user_name", "full_name", "description", "main_url", "avatar"]
+    ).await?;
+    let owner_address = verify_address_challenge(
+        &app_data.db_repo,
+        &fields.required_text("nonce")?,
+        &fields.required_text("signature")?
+    ).await?;
+
+    let (chain_id, chain_asset_id) = local_chain_identity(&app_data.db_repo).await?;
+    let params = ProfileCreate {
+        chain_asset_id,
+        chain_id,
+        user_name: fields.required_text("user_name")?,
+        full_name: fields.required_text("full_name")?,
+        description: fields.required_text("description")?,
+        main_url: fields.text("main_url")?,
+        avatar: fields.bytes("avatar"),
+        owner_address: Some(owner_address),
+    };
+
+    let id = app_data.db_repo.insert_profile(params).await?;
+
+    Ok(web::Json(OutputId { id }))
+}
+
+pub async fn update_profile<T: UpdateProfileFn + QueryProfileFn>(
+    app_data: web::Data<AppState<T>>,
+    path: web::Path<i64>,
+    auth: AuthenticatedProfile,
+    payload: Multipart
+) -> Result<HttpResponse, ApiError> {
+    let profile_id = path.into_inner();
+    require_profile_owner(&app_data.db_repo, &auth, profile_id).await?;
+
+    let fields = MultipartFields::read(payload, &["full_name", "description", "main_url", "avatar"]).await?;
+    let params = ProfileUpdate {
+        full_name: fields.required_text("full_name")?,
+        description: fields.required_text("description")?,
+        main_url: fields.text("main_url")?,
+        avatar: fields.bytes("avatar"),
+    };
+
+    app_data.db_repo.update_profile(profile_id, params).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::signature::sui_address;
+    use crate::test_helpers::fixtures::{
+        bearer_header, get_app, get_profile_create_multipart, get_profile_update_multipart, get_access_token,
+        sign_address_challenge, sign_personal_message
+    };
+    use crate::routes::auth::{ address_challenge_message, ChallengeRequest, ChallengeResponse };
+    use crate::routes::errors::OwnershipViolation;
+    use actix_http::Request;
+    use actix_web::{ dev::{ Service, ServiceResponse }, http::{ header, StatusCode }, test, Error };
+    use actix_web::web::BytesMut;
+    use ed25519_consensus::SigningKey;
+    use repository::repo::chain::chain::LOCAL_CHAIN;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+
+    const BOUNDARY: &str = "----WebKitFormBoundary0gYK6PWVgKwyVIvS";
+
+    fn multipart_content_type() -> (header::HeaderName, String) {
+        (header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", BOUNDARY))
+    }
+
+    async fn post_profile(
+        app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+        payload: BytesMut
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri("/v1/profile")
+            .insert_header(multipart_content_type())
+            .set_payload(payload)
+            .to_request();
+        test::call_service(app, req).await
+    }
+
+    /// Creates a profile owned by the key's wallet through a signed address challenge
+    async fn create_profile_as(app: &impl Service<Request, Response = ServiceResponse, Error = Error>, signing_key: &SigningKey) -> OutputId {
+        let (nonce, signature) = sign_address_challenge(app, signing_key).await;
+        let res = post_profile(app, get_profile_create_multipart(&nonce, &signature, &vec![], BOUNDARY, false)).await;
+        test::read_body_json(res).await
+    }
+
+    #[actix_web::test]
+    async fn test_create_and_get_profile() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo.clone()).await;
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let (nonce, signature) = sign_address_challenge(&app, &signing_key).await;
+        let avatar = vec![0xFF, 0xD8, 1, 2, 3, 0xFF, 0xD9];
+
+        let res = post_profile(&app, get_profile_create_multipart(&nonce, &signature, &avatar, BOUNDARY, true)).await;
+        let output_id: OutputId = test::read_body_json(res).await;
+
+        let req = test::TestRequest::get().uri(&format!("/v1/profile/{}", output_id.id)).to_request();
+        let profile: ProfileQueryResult = test::call_and_read_body_json(&app, req).await;
+        assert!(profile.id == output_id.id);
+        assert!(profile.avatar == Some(avatar));
+        assert!(profile.main_url.unwrap().starts_with("https://"));
+        assert!(repo.query_chain_id_by_name(LOCAL_CHAIN).await.unwrap() == Some(profile.chain_id));
+
+        let req = test::TestRequest::get().uri(&format!("/v1/profile/username/{}", profile.user_name)).to_request();
+        let profile_by_user: ProfileQueryResult = test::call_and_read_body_json(&app, req).await;
+        assert!(profile_by_user.id == output_id.id);
+    }
+
+    #[actix_web::test]
+    async fn test_fresh_wallet_creates_its_first_profile() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo.clone()).await;
+        let signing_key = SigningKey::new(rand::thread_rng());
+
+        let output_id = create_profile_as(&app, &signing_key).await;
+
+        let profile = repo.query_profile(output_id.id).await.unwrap().unwrap();
+        assert!(profile.owner_address == Some(sui_address(&signing_key.verification_key().to_bytes())));
+        assert!(profile.avatar.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_create_profile_needs_signed_address_challenge() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo.clone()).await;
+        let signing_key = SigningKey::new(rand::thread_rng());
+
+        let mut payload = BytesMut::new();
+        payload.extend(format!("--{}\r\n", BOUNDARY).as_bytes());
+        payload.extend(b"Content-Disposition: form-data; name=\"user_name\"\r\n\r\njill\r\n");
+        payload.extend(format!("--{}--\r\n", BOUNDARY).as_bytes());
+        assert!(post_profile(&app, payload).await.status() == StatusCode::BAD_REQUEST);
+
+        let (nonce, _) = sign_address_challenge(&app, &signing_key).await;
+        let other_key = SigningKey::new(rand::thread_rng());
+        let address = sui_address(&signing_key.verification_key().to_bytes());
+        let wrong_signature = sign_personal_message(&other_key, address_challenge_message(&address, &nonce).as_bytes());
+        let res = post_profile(&app, get_profile_create_multipart(&nonce, &wrong_signature, &vec![], BOUNDARY, false)).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+
+        let (nonce, signature) = sign_address_challenge(&app, &signing_key).await;
+        let res = post_profile(&app, get_profile_create_multipart(&nonce, &signature, &vec![], BOUNDARY, false)).await;
+        assert!(res.status() == StatusCode::OK);
+        let res = post_profile(&app, get_profile_create_multipart(&nonce, &signature, &vec![], BOUNDARY, false)).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED, "the challenge is used up");
+    }
+
+    #[actix_web::test]
+    async fn test_create_profile_rejects_sign_in_challenge() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo.clone()).await;
+        let signing_key = SigningKey::new(rand::thread_rng());
+        let profile_id = create_profile_as(&app, &signing_key).await.id;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth/challenge")
+            .set_json(ChallengeRequest { profile_id })
+            .to_request();
+        let challenge: ChallengeResponse = test::call_and_read_body_json(&app, req).await;
+        let signature = sign_personal_message(&signing_key, challenge.message.as_bytes());
+
+        let res = post_profile(&app, get_profile_create_multipart(&challenge.nonce, &signature, &vec![], BOUNDARY, false)).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_create_profile_rejects_client_chain_identity() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo.clone()).await;
+        let signing_key = SigningKey::new(rand::thread_rng());
+
+        for (name, value) in [
+            ("chain_asset_id", "123"),
+            ("chain_id", "1"),
+            ("owner_address", "0xdef"),
+        ] {
+            let (nonce, signature) = sign_address_challenge(&app, &signing_key).await;
+            let mut payload = BytesMut::new();
+            payload.extend(format!("--{}\r\n", BOUNDARY).as_bytes());
+            payload.extend(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", name, value).as_bytes());
+            payload.extend(get_profile_create_multipart(&nonce, &signature, &vec![], BOUNDARY, false));
+            let res = post_profile(&app, payload).await;
+
+            assert!(res.status() == StatusCode::BAD_REQUEST, "{} is set by the server", name);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_update_profile() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo.clone()).await;
+        let output_id = create_profile_as(&app, &SigningKey::new(rand::thread_rng())).await;
+        let token = get_access_token(&repo, output_id.id).await;
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/v1/profile/{}", output_id.id))
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&token))
+            .set_payload(get_profile_update_multipart("Updated Name", BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.status() == StatusCode::NO_CONTENT);
+        let profile = repo.query_profile(output_id.id).await.unwrap().unwrap();
+        assert!(profile.full_name == "Updated Name");
+    }
+
+    #[actix_web::test]
+    async fn test_update_profile_needs_owner_session() {
+        let repo = InMemoryRepo::new();
+        let app = get_app(repo.clone()).await;
+        let mut profile_ids = vec![];
+        for _ in 0..2 {
+            profile_ids.push(create_profile_as(&app, &SigningKey::new(rand::thread_rng())).await.id);
+        }
+        let other_token = get_access_token(&repo, profile_ids[1]).await;
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/v1/profile/{}", profile_ids[0]))
+            .insert_header(multipart_content_type())
+            .set_payload(get_profile_update_multipart("Updated Name", BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/v1/profile/{}", profile_ids[0]))
+            .insert_header(multipart_content_type())
+            .insert_header(bearer_header(&other_token))
+            .set_payload(get_profile_update_multipart("Updated Name", BOUNDARY))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::FORBIDDEN);
+        let violation: OwnershipViolation = test::read_body_json(res).await;
+        assert!(violation.resource == "profile" && violation.resource_id == profile_ids[0]);
+
+        let profile = repo.query_profile(profile_ids[0]).await.unwrap().unwrap();
+        assert!(profile.full_name != "Updated Name");
+    }
+
+    #[actix_web::test]
+    async fn test_missing_profile_returns_not_found() {
+        let app = get_app(InMemoryRepo::new()).await;
+
+        let req = test::TestRequest::get().uri("/v1/profile/999").to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status() == StatusCode::NOT_FOUND);
+    }
+}
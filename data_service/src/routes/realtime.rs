@@ -0,0 +1,259 @@
+use crate::auth::session::{ session_is_active, websocket_protocols, AuthenticatedProfile, SessionStore, ACCESS_TOKEN_PROTOCOL };
+use crate::realtime::hub::{ PostHub, Subscription, Topic };
+use actix_web::{ http::header::{ self, HeaderValue }, rt, web, HttpRequest, HttpResponse };
+use actix_ws::{ CloseCode, CloseReason, Message, MessageStream, Session };
+use log::error;
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 50;
+/// How often an open connection looks up its session, a revoked or expired session is closed within this long
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Text frames a client sends, e.g. {"action":"subscribe","topic":{"type":"thread","post_id":1}}
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Subscribe { topic: Topic },
+    Unsubscribe { topic: Topic },
+}
+
+/// Sent before the next post of a subscription that dropped posts because the client read too slowly
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LaggedFrame {
+    pub lagged: Topic,
+    pub dropped: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ErrorFrame {
+    pub error: String,
+}
+
+/// Upgrades to a websocket that pushes new posts of the subscribed topics, each as a json
+/// PostWithProfileQueryResult frame. A post matching several subscriptions is sent once for each.
+/// Browsers pass the access token as a subprotocol, the accepted subprotocol is echoed or they drop the connection.
+/// Once the session is revoked or expires the connection is closed with a policy violation
+pub async fn subscribe_posts(
+    req: HttpRequest,
+    body: web::Payload,
+    auth: AuthenticatedProfile,
+    hub: web::Data<PostHub>,
+    sessions: web::Data<SessionStore>
+) -> Result<HttpResponse, actix_web::Error> {
+    let (mut response, session, stream) = actix_ws::handle(&req, body)?;
+    if websocket_protocols(&req).any(|protocol| protocol == ACCESS_TOKEN_PROTOCOL) {
+        response.headers_mut().insert(header::SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(ACCESS_TOKEN_PROTOCOL));
+    }
+    rt::spawn(run_connection(hub.into_inner(), sessions, auth, session, stream, SESSION_CHECK_INTERVAL));
+
+    Ok(response)
+}
+
+async fn run_connection(
+    hub: Arc<PostHub>,
+    sessions: web::Data<SessionStore>,
+    auth: AuthenticatedProfile,
+    mut session: Session,
+    mut stream: MessageStream,
+    session_check_interval: Duration
+) {
+    let profile_id = auth.profile_id;
+    let mut subscriptions: HashMap<Topic, (u64, rt::task::JoinHandle<()>)> = HashMap::new();
+    let mut close_reason = None;
+    let mut session_check = rt::time::interval_at(rt::time::Instant::now() + session_check_interval, session_check_interval);
+
+    loop {
+        let message = tokio::select! {
+            message = stream.recv() => message,
+            _ = session_check.tick() => match session_is_active(sessions.get_ref(), auth).await {
+                Ok(false) => {
+                    close_reason = Some(CloseReason {
+                        code: CloseCode::Policy,
+                        description: Some("session was revoked or has expired".to_string()),
+                    });
+                    break;
+                },
+                Ok(true) => continue,
+                Err(e) => {
+                    error!("failed to check the session of a websocket connection: {}", e);
+                    continue;
+                },
+            },
+        };
+        let Some(Ok(message)) = message else {
+            break;
+        };
+
+        let sent = match message {
+            Message::Text(text) => match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(ClientMessage::Subscribe { topic }) if subscriptions.contains_key(&topic) => Ok(()),
+                Ok(ClientMessage::Subscribe { .. }) if subscriptions.len() >= MAX_SUBSCRIPTIONS_PER_CONNECTION => {
+                    let error = format!("a connection can have at most {} subscriptions", MAX_SUBSCRIPTIONS_PER_CONNECTION);
+                    send_json(&mut session, &ErrorFrame { error }).await
+                },
+                Ok(ClientMessage::Subscribe { topic }) => {
+                    let subscription = hub.subscribe(profile_id, topic.clone());
+                    let id = subscription.id;
+                    let forwarder = rt::spawn(forward_subscription(subscription, topic.clone(), session.clone()));
+                    subscriptions.insert(topic, (id, forwarder));
+                    Ok(())
+                },
+                Ok(ClientMessage::Unsubscribe { topic }) => {
+                    if let Some((id, forwarder)) = subscriptions.remove(&topic) {
+                        hub.unsubscribe(id);
+                        forwarder.abort();
+                    }
+                    Ok(())
+                },
+                Err(e) => send_json(&mut session, &ErrorFrame { error: format!("invalid message: {}", e) }).await,
+            },
+            Message::Ping(bytes) => session.pong(&bytes).await,
+            Message::Close(reason) => {
+                close_reason = reason;
+                break;
+            },
+            _ => Ok(()),
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+
+    for (id, forwarder) in subscriptions.into_values() {
+        hub.unsubscribe(id);
+        forwarder.abort();
+    }
+    let _ = session.close(close_reason).await;
+}
+
+/// Waiting on the socket here only ever holds up this subscription, the hub drops its posts once the buffer is full
+async fn forward_subscription(mut subscription: Subscription, topic: Topic, mut session: Session) {
+    while let Some(post) = subscription.recv().await {
+        let dropped = subscription.take_dropped();
+        if dropped > 0 && send_json(&mut session, &LaggedFrame { lagged: topic.clone(), dropped }).await.is_err() {
+            return;
+        }
+        if send_json(&mut session, post.as_ref()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn send_json<S: Serialize>(session: &mut Session, frame: &S) -> Result<(), actix_ws::Closed> {
+    match serde_json::to_string(frame) {
+        Ok(json) => session.text(json).await,
+        Err(e) => {
+            error!("failed to serialize websocket frame: {}", e);
+            Ok(())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::session::session_store;
+    use crate::auth::token::AccessTokenSigner;
+    use crate::test_helpers::fixtures::{ bearer_header, get_access_token, get_app, TEST_ACCESS_TOKEN_SECRET };
+    use actix_web::{ body::to_bytes, http::StatusCode, test, FromRequest };
+    use repository::repo::auth::auth::RevokeSessionFn;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use repository::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    async fn insert_profile(repo: &InMemoryRepo) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: "jill".to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: None,
+        }).await.unwrap()
+    }
+
+    fn upgrade_request() -> test::TestRequest {
+        test::TestRequest::get()
+            .uri("/v1/ws")
+            .insert_header((header::CONNECTION, "upgrade"))
+            .insert_header((header::UPGRADE, "websocket"))
+            .insert_header((header::SEC_WEBSOCKET_VERSION, "13"))
+            .insert_header((header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="))
+    }
+
+    #[actix_web::test]
+    async fn test_upgrade_needs_access_token() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_profile(&repo).await;
+        let token = get_access_token(&repo, profile_id).await;
+        let app = get_app(repo).await;
+
+        let res = test::call_service(&app, upgrade_request().to_request()).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+
+        let res = test::call_service(&app, upgrade_request().insert_header(bearer_header(&token)).to_request()).await;
+        assert!(res.status() == StatusCode::SWITCHING_PROTOCOLS);
+    }
+
+    #[actix_web::test]
+    async fn test_upgrade_with_access_token_protocol() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_profile(&repo).await;
+        let token = get_access_token(&repo, profile_id).await;
+        let app = get_app(repo.clone()).await;
+
+        let protocols = |token: &str| (header::SEC_WEBSOCKET_PROTOCOL, format!("{}, {}", ACCESS_TOKEN_PROTOCOL, token));
+
+        let res = test::call_service(&app, upgrade_request().insert_header(protocols(&token)).to_request()).await;
+        assert!(res.status() == StatusCode::SWITCHING_PROTOCOLS);
+        assert!(res.headers().get(header::SEC_WEBSOCKET_PROTOCOL).unwrap() == ACCESS_TOKEN_PROTOCOL);
+
+        let tampered = format!("{}x", token);
+        let res = test::call_service(&app, upgrade_request().insert_header(protocols(&tampered)).to_request()).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+
+        let claims = AccessTokenSigner::new(TEST_ACCESS_TOKEN_SECRET).verify(&token).unwrap();
+        repo.revoke_session(profile_id, claims.session_id).await.unwrap();
+        let res = test::call_service(&app, upgrade_request().insert_header(protocols(&token)).to_request()).await;
+        assert!(res.status() == StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_revoked_session_closes_the_connection() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_profile(&repo).await;
+        let token = get_access_token(&repo, profile_id).await;
+        let claims = AccessTokenSigner::new(TEST_ACCESS_TOKEN_SECRET).verify(&token).unwrap();
+        let auth = AuthenticatedProfile { profile_id, session_id: claims.session_id };
+
+        let req = upgrade_request().to_http_request();
+        // the sender is kept so the client side of the socket stays open
+        let (_client, payload) = actix_http::h1::Payload::create(false);
+        let body = web::Payload::from_request(&req, &mut payload.into()).await.unwrap();
+        let (response, session, stream) = actix_ws::handle(&req, body).unwrap();
+        let hub = Arc::new(PostHub::new());
+        rt::spawn(run_connection(hub, session_store(repo.clone()), auth, session, stream, Duration::from_millis(10)));
+
+        repo.revoke_session(profile_id, auth.session_id).await.unwrap();
+        let frames = rt::time::timeout(Duration::from_secs(5), to_bytes(response.into_body())).await.unwrap().unwrap();
+
+        // a close frame whose status code is 1008, policy violation
+        assert!(frames[0] == 0x88);
+        assert!(frames[2..4] == [0x03, 0xF0]);
+    }
+
+    #[actix_web::test]
+    async fn test_client_message_format() {
+        let message: ClientMessage = serde_json::from_str(r#"{"action":"subscribe","topic":{"type":"thread","post_id":7}}"#).unwrap();
+        assert!(matches!(message, ClientMessage::Subscribe { topic: Topic::Thread { post_id: 7 } }));
+
+        let message: ClientMessage = serde_json::from_str(r#"{"action":"unsubscribe","topic":{"type":"home"}}"#).unwrap();
+        assert!(matches!(message, ClientMessage::Unsubscribe { topic: Topic::Home }));
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"action":"subscribe","topic":{"type":"everything"}}"#).is_err());
+    }
+}
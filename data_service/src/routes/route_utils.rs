@@ -0,0 +1,102 @@
+use crate::auth::session::new_random_token;
+use crate::routes::errors::ApiError;
+use actix_multipart::Multipart;
+use futures_util::TryStreamExt;
+use log::error;
+use repository::repo::chain::chain::{ QueryChainIdByNameFn, LOCAL_CHAIN };
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+
+/// Largest single part, enough for an avatar or post image
+pub const MAX_PART_BYTES: usize = 2 * 1024 * 1024;
+/// Largest sum of all parts of one form
+pub const MAX_FORM_BYTES: usize = 4 * 1024 * 1024;
+/// Most parts of one form, including parts without a name that are skipped
+pub const MAX_PARTS: usize = 16;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct OutputId {
+    pub id: i64,
+}
+
+/// Chain id and chain_asset_id for a profile or post created through the api. Both are chosen here and never
+/// taken from the request, the random asset id cannot collide with the object id of a real chain asset
+pub async fn local_chain_identity<T: QueryChainIdByNameFn>(db_repo: &T) -> Result<(i64, String), ApiError> {
+    let chain_id = db_repo.query_chain_id_by_name(LOCAL_CHAIN).await?.ok_or_else(|| {
+        error!("chain {} is missing, run the repository migrations", LOCAL_CHAIN);
+        ApiError::InternalServerError
+    })?;
+
+    Ok((chain_id, format!("{}:{}", LOCAL_CHAIN, new_random_token())))
+}
+
+/// All parts of a multipart form read into memory, keyed by field name
+pub struct MultipartFields {
+    fields: HashMap<String, Vec<u8>>,
+}
+
+impl MultipartFields {
+    /// Only the named fields are accepted, each at most once. Stops reading as soon as a part or the whole form goes
+    /// over its limit, so an oversized upload is never held in memory
+    pub async fn read(mut payload: Multipart, field_names: &[&str]) -> Result<Self, ApiError> {
+        let mut fields = HashMap::new();
+        let mut form_len = 0;
+        let mut part_count = 0;
+
+        while let Some(mut field) = payload.try_next().await.map_err(|e| ApiError::BadRequest(e.to_string()))? {
+            part_count += 1;
+            if part_count > MAX_PARTS {
+                return Err(ApiError::BadRequest(format!("form has more than {} parts", MAX_PARTS)));
+            }
+            let name = match field.content_disposition().get_name() {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            if !field_names.contains(&name.as_str()) {
+                return Err(ApiError::BadRequest(format!("{} is not an expected field", name)));
+            }
+            if fields.contains_key(&name) {
+                return Err(ApiError::BadRequest(format!("{} was sent more than once", name)));
+            }
+            let mut value = vec![];
+            while let Some(chunk) = field.try_next().await.map_err(|e| ApiError::BadRequest(e.to_string()))? {
+                form_len += chunk.len();
+                if value.len() + chunk.len() > MAX_PART_BYTES {
+                    return Err(ApiError::PayloadTooLarge(format!("{} is larger than {} bytes", name, MAX_PART_BYTES)));
+                }
+                if form_len > MAX_FORM_BYTES {
+                    return Err(ApiError::PayloadTooLarge(format!("form is larger than {} bytes", MAX_FORM_BYTES)));
+                }
+                value.extend_from_slice(&chunk);
+            }
+            fields.insert(name, value);
+        }
+
+        Ok(Self { fields })
+    }
+
+    pub fn text(&self, name: &str) -> Result<Option<String>, ApiError> {
+        match self.fields.get(name) {
+            Some(value) => String::from_utf8(value.clone())
+                .map(Some)
+                .map_err(|_| ApiError::BadRequest(format!("{} must be valid utf-8 text", name))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn required_text(&self, name: &str) -> Result<String, ApiError> {
+        self.text(name)?.ok_or_else(|| ApiError::BadRequest(format!("{} is required", name)))
+    }
+
+    pub fn required_i64(&self, name: &str) -> Result<i64, ApiError> {
+        self.required_text(name)?
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| ApiError::BadRequest(format!("{} must be a number", name)))
+    }
+
+    /// Empty parts are treated as missing, browsers send them for file inputs without a selection
+    pub fn bytes(&self, name: &str) -> Option<Vec<u8>> {
+        self.fields.get(name).filter(|value| !value.is_empty()).cloned()
+    }
+}
@@ -1,10 +1,26 @@
 use std::ops::Range;
 use crate::app_state::AppState;
 use fake::{faker::{internet::en::Username, name::en::{LastName, FirstName}, lorem::en::Sentence}, Fake};
-use repository::repo::base::DbRepo;
+use repository::repo::in_memory::in_memory::InMemoryRepo;
 use repository::test_helpers::fixtures::get_fake_main_url;
-use actix_web::{ App, web::{ self, BytesMut, Bytes }, Error, test, dev::{ Service, ServiceResponse } };
+use crate::auth::session::{hash_token, new_random_token, session_store};
+use crate::auth::token::AccessTokenSigner;
+use crate::auth::signature::{personal_message_digest, sui_address};
+use crate::routes::auth::{AddressChallengeRequest, ChallengeResponse, create_address_challenge, create_challenge, get_sessions, login, refresh, revoke_all_sessions, revoke_session};
+use crate::routes::follow::{follow_user, unfollow_user};
+use crate::realtime::hub::PostHub;
+use crate::routes::realtime::subscribe_posts;
+use crate::routes::profile::{create_profile, get_profile, get_profile_by_user, update_profile};
+use crate::routes::post::{create_post, create_response_post, create_share_post, get_post, get_posts_by_user};
+use actix_web::{ App, web::{ self, BytesMut, Bytes }, Error, test, dev::{ Service, ServiceResponse }, http::header };
 use actix_http::Request;
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use chrono::{ Duration, Utc };
+use ed25519_consensus::SigningKey;
+use repository::repo::auth::auth::InsertSessionFn;
+
+/// Secret the test app signs access tokens with
+pub const TEST_ACCESS_TOKEN_SECRET: &[u8] = b"test access token secret";
 
 #[allow(unused)]
 pub async fn get_app_state<T>(db_repo: T) -> AppState<T> {
@@ -18,31 +34,103 @@ pub async fn get_app_data<T>(db_repo: T) -> web::Data<AppState<T>> {
     web::Data::new(get_app_state(db_repo).await)
 }
 
+/// Test app backed by the given in memory repo, keep a clone of the repo to seed or inspect data
 #[allow(unused)]
-pub async fn get_app() -> impl Service<Request, Response = ServiceResponse, Error = Error> {
-    let app_data = get_app_data(DbRepo::init().await).await;
+pub async fn get_app(db_repo: InMemoryRepo) -> impl Service<Request, Response = ServiceResponse, Error = Error> {
+    let sessions = session_store(db_repo.clone());
+    let app_data = get_app_data(db_repo).await;
     test::init_service(
         App::new()
-            .app_data(app_data.clone())            
+            .app_data(app_data.clone())
+            .app_data(web::Data::new(AccessTokenSigner::new(TEST_ACCESS_TOKEN_SECRET)))
+            .app_data(sessions)
+            .app_data(web::Data::new(PostHub::new()))
             .service(
                 web::scope("/v1")
                     // .service(web::resource("/msg/{id}").route(web::get().to(get_message::<DbRepo>)))
                     // .service(web::resource("/msg").route(web::post().to(create_message::<DbRepo>)))
                     // .service(web::resource("/msgs").route(web::post().to(get_messages::<DbRepo>)))
-                    // .service(web::resource("/profile/{id}").route(web::get().to(get_profile::<DbRepo>)))
-                    // .service(web::resource("/profile/username/{user_name}").route(web::get().to(get_profile_by_user::<DbRepo>)))
-                    // .service(web::resource("/profile").route(web::post().to(create_profile::<DbRepo>)))
+                    .service(
+                        web::resource("/profile/{id}")
+                            .route(web::get().to(get_profile::<InMemoryRepo>))
+                            .route(web::put().to(update_profile::<InMemoryRepo>))
+                    )
+                    .service(web::resource("/profile/username/{user_name}").route(web::get().to(get_profile_by_user::<InMemoryRepo>)))
+                    .service(
+                        web::resource("/profile/{id}/following/{following_id}")
+                            .route(web::post().to(follow_user::<InMemoryRepo>))
+                            .route(web::delete().to(unfollow_user::<InMemoryRepo>))
+                    )
+                    .service(web::resource("/profile").route(web::post().to(create_profile::<InMemoryRepo>)))
+                    .service(web::resource("/post/{id}").route(web::get().to(get_post::<InMemoryRepo>)))
+                    .service(web::resource("/post/{id}/response").route(web::post().to(create_response_post::<InMemoryRepo>)))
+                    .service(web::resource("/post/{id}/share").route(web::post().to(create_share_post::<InMemoryRepo>)))
+                    .service(web::resource("/post").route(web::post().to(create_post::<InMemoryRepo>)))
+                    .service(web::resource("/posts/user/{user_id}").route(web::get().to(get_posts_by_user::<InMemoryRepo>)))
+                    .service(web::resource("/auth/challenge").route(web::post().to(create_challenge::<InMemoryRepo>)))
+                    .service(web::resource("/auth/address_challenge").route(web::post().to(create_address_challenge::<InMemoryRepo>)))
+                    .service(web::resource("/auth/login").route(web::post().to(login::<InMemoryRepo>)))
+                    .service(web::resource("/auth/refresh").route(web::post().to(refresh::<InMemoryRepo>)))
+                    .service(
+                        web::resource("/auth/sessions")
+                            .route(web::get().to(get_sessions::<InMemoryRepo>))
+                            .route(web::delete().to(revoke_all_sessions::<InMemoryRepo>))
+                    )
+                    .service(web::resource("/auth/sessions/{id}").route(web::delete().to(revoke_session::<InMemoryRepo>)))
+                    .service(web::resource("/ws").route(web::get().to(subscribe_posts)))
             )
     ).await
 }
 
+/// Serialized sui signature of a personal message the way wallets return it: flag, signature and public key in base64
+pub fn sign_personal_message(signing_key: &SigningKey, message: &[u8]) -> String {
+    let signature = signing_key.sign(&personal_message_digest(message));
+    let mut bytes = vec![0x00];
+    bytes.extend_from_slice(&signature.to_bytes());
+    bytes.extend_from_slice(&signing_key.verification_key().to_bytes());
+    STANDARD.encode(bytes)
+}
+
+/// Asks the app for an address challenge of the key's wallet and signs it, returns the nonce and signature
+pub async fn sign_address_challenge(
+    app: &impl Service<Request, Response = ServiceResponse, Error = Error>,
+    signing_key: &SigningKey
+) -> (String, String) {
+    let address = sui_address(&signing_key.verification_key().to_bytes());
+    let req = test::TestRequest::post()
+        .uri("/v1/auth/address_challenge")
+        .set_json(AddressChallengeRequest { address })
+        .to_request();
+    let challenge: ChallengeResponse = test::call_and_read_body_json(app, req).await;
+    (challenge.nonce, sign_personal_message(signing_key, challenge.message.as_bytes()))
+}
+
+/// Stores a session for the profile without going through the wallet login, returns an access token of it
+#[allow(unused)]
+pub async fn get_access_token(db_repo: &InMemoryRepo, profile_id: i64) -> String {
+    let expires_at = Utc::now() + Duration::hours(1);
+    let session_id = db_repo.insert_session(profile_id, &hash_token(&new_random_token()), "test", expires_at).await.unwrap();
+    AccessTokenSigner::new(TEST_ACCESS_TOKEN_SECRET).sign(profile_id, session_id, expires_at)
+}
+
+pub fn bearer_header(token: &str) -> (header::HeaderName, String) {
+    (header::AUTHORIZATION, format!("Bearer {}", token))
+}
+
 /// warning: line breaks are very important when ending any line!!!
+/// nonce and signature are of an address challenge, see verify_address_challenge
 pub fn get_profile_create_multipart(
+    nonce: &str,
+    signature: &str,
     avatar: &Vec<u8>,
     boundary: &str,
     with_avatar: bool
 ) -> BytesMut {
     let mut payload = actix_web::web::BytesMut::new();
+    for (name, value) in [("nonce", nonce), ("signature", signature)] {
+        payload.extend(format!("--{}\r\n", boundary).as_bytes());
+        payload.extend(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", name, value).as_bytes());
+    }
     payload.extend(format!("--{}\r\n", boundary).as_bytes());
     payload.extend(
         format!("Content-Disposition: form-data; name=\"user_name\"\r\n\r\n").as_bytes()
@@ -62,13 +150,13 @@ pub fn get_profile_create_multipart(
     payload.extend(
         format!("{}\r\n", Sentence(Range { start: 8, end: 10 }).fake::<String>()).as_bytes()
     );
-    
+    payload.extend(format!("--{}\r\n", boundary).as_bytes());
     payload.extend(format!("Content-Disposition: form-data; name=\"main_url\"\r\n\r\n").as_bytes());    
     payload.extend(get_fake_main_url().as_bytes());
     payload.extend(b"\r\n"); // warning: line breaks are very important!!! 
-    payload.extend(format!("--{}\r\n", boundary).as_bytes());
 
     if with_avatar == true {
+        payload.extend(format!("--{}\r\n", boundary).as_bytes());
         payload.extend(
             b"Content-Disposition: form-data; name=\"avatar\"; filename=\"profile.jpeg\"\r\n"
         );
@@ -79,4 +167,62 @@ pub fn get_profile_create_multipart(
     payload.extend(format!("--{}--\r\n", boundary).as_bytes()); // note the extra -- at the end of the boundary
 
     payload
-}
\ No newline at end of file
+}
+
+/// warning: line breaks are very important when ending any line!!!
+pub fn get_profile_update_multipart(
+    full_name: &str,
+    boundary: &str
+) -> BytesMut {
+    let mut payload = actix_web::web::BytesMut::new();
+    payload.extend(format!("--{}\r\n", boundary).as_bytes());
+    payload.extend(
+        b"Content-Disposition: form-data; name=\"full_name\"\r\n\r\n"
+    );
+    payload.extend(format!("{}\r\n", full_name).as_bytes());
+    payload.extend(format!("--{}\r\n", boundary).as_bytes());
+    payload.extend(
+        b"Content-Disposition: form-data; name=\"description\"\r\n\r\n"
+    );
+    payload.extend(
+        format!("{}\r\n", Sentence(Range { start: 8, end: 10 }).fake::<String>()).as_bytes()
+    );
+    payload.extend(format!("--{}--\r\n", boundary).as_bytes()); // note the extra -- at the end of the boundary
+
+    payload
+}
+
+/// Fields shared by standalone, response and share posts, message and image parts are left out when None
+pub fn get_post_create_multipart(
+    user_id: i64,
+    message: Option<&str>,
+    image: Option<&Vec<u8>>,
+    boundary: &str
+) -> BytesMut {
+    let mut payload = actix_web::web::BytesMut::new();
+    payload.extend(format!("--{}\r\n", boundary).as_bytes());
+    payload.extend(
+        b"Content-Disposition: form-data; name=\"user_id\"\r\n\r\n"
+    );
+    payload.extend(format!("{}\r\n", user_id).as_bytes());
+
+    if let Some(message) = message {
+        payload.extend(format!("--{}\r\n", boundary).as_bytes());
+        payload.extend(
+            b"Content-Disposition: form-data; name=\"message\"\r\n\r\n"
+        );
+        payload.extend(format!("{}\r\n", message).as_bytes());
+    }
+    if let Some(image) = image {
+        payload.extend(format!("--{}\r\n", boundary).as_bytes());
+        payload.extend(
+            b"Content-Disposition: form-data; name=\"image\"; filename=\"post.jpeg\"\r\n"
+        );
+        payload.extend(b"Content-Type: image/jpeg\r\n\r\n");
+        payload.extend(Bytes::from(image.clone()));
+        payload.extend(b"\r\n"); // warning: line breaks are very important!!!
+    }
+    payload.extend(format!("--{}--\r\n", boundary).as_bytes()); // note the extra -- at the end of the boundary
+
+    payload
+}
@@ -0,0 +1,99 @@
+use crate::dead_letter::{replay_failed_event_by_id, RetryPolicy};
+use crate::ingest::ApplyEventRepo;
+use anyhow::anyhow;
+use repository::repo::base::BeginTransactionFn;
+use repository::repo::failed_event::failed_event::{
+    DeleteFailedEventFn, QueryFailedEventFn, QueryFailedEventsFn, UpdateFailedEventRetryFn
+};
+use repository::repo::failed_event::model::FailedEventQueryResult;
+
+pub const FAILED_EVENT_USAGE: &str = "usage: event_handler failed list | failed replay <id> | failed discard <id>";
+const LIST_PAGE_SIZE: i16 = 100;
+
+/// Admin commands for dead lettered events, args are everything after `failed` on the command line
+pub async fn run_failed_event_command<T>(db_repo: &T, args: &[&str]) -> Result<(), anyhow::Error>
+where
+    T: BeginTransactionFn + QueryFailedEventFn + QueryFailedEventsFn + UpdateFailedEventRetryFn + DeleteFailedEventFn,
+    T::Transaction: ApplyEventRepo + DeleteFailedEventFn,
+{
+    match args {
+        ["list"] => {
+            for failed_event in list_failed_events(db_repo).await? {
+                println!("{}", format_failed_event(&failed_event));
+            }
+        },
+        ["replay", id] => {
+            let replayed = replay_failed_event_by_id(db_repo, &RetryPolicy::default(), parse_id(id)?).await?;
+            match replayed.row_id {
+                Some(row_id) => println!("replayed failed event {} as id {}", id, row_id),
+                None => println!("replayed failed event {}", id),
+            }
+        },
+        ["discard", id] => {
+            db_repo.delete_failed_event(parse_id(id)?).await?;
+            println!("discarded failed event {}", id);
+        },
+        _ => return Err(anyhow!(FAILED_EVENT_USAGE)),
+    }
+
+    Ok(())
+}
+
+pub async fn list_failed_events<T: QueryFailedEventsFn>(db_repo: &T) -> Result<Vec<FailedEventQueryResult>, anyhow::Error> {
+    let mut failed_events = vec![];
+    loop {
+        let page = db_repo.query_failed_events(failed_events.last().map(|fe: &FailedEventQueryResult| fe.id), LIST_PAGE_SIZE).await?;
+        let is_last_page = page.len() < LIST_PAGE_SIZE as usize;
+        failed_events.extend(page);
+        if is_last_page {
+            return Ok(failed_events);
+        }
+    }
+}
+
+fn format_failed_event(failed_event: &FailedEventQueryResult) -> String {
+    let next_retry = match failed_event.next_retry_at {
+        Some(next_retry_at) => format!("retry at {}", next_retry_at.to_rfc3339()),
+        None => "parked".to_string(),
+    };
+    format!(
+        "{}\t{}\t{}:{}\tattempts {}\t{}\t{}",
+        failed_event.id,
+        failed_event.subscription,
+        failed_event.tx_digest,
+        failed_event.event_seq,
+        failed_event.attempts,
+        next_retry,
+        failed_event.error
+    )
+}
+
+fn parse_id(id: &str) -> Result<i64, anyhow::Error> {
+    id.parse().map_err(|_| anyhow!("failed event id must be a number, got {}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dead_letter::dead_letter;
+    use crate::test_helpers::fixtures::get_raw_create_profile_event;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::profile::profile::QueryProfileByUserNameFn;
+
+    #[tokio::test]
+    async fn test_failed_event_commands() {
+        let repo = InMemoryRepo::new();
+        let replayed = dead_letter(&repo, "test", &get_raw_create_profile_event("digest", 0, "user"), "error", None).await.unwrap();
+        let discarded = dead_letter(&repo, "test", &get_raw_create_profile_event("digest", 1, "user"), "error", None).await.unwrap();
+        assert!(list_failed_events(&repo).await.unwrap().len() == 2);
+
+        run_failed_event_command(&repo, &["replay", &replayed.to_string()]).await.unwrap();
+        run_failed_event_command(&repo, &["discard", &discarded.to_string()]).await.unwrap();
+
+        assert!(list_failed_events(&repo).await.unwrap().is_empty());
+        assert!(repo.query_profile_by_user_name("user0").await.unwrap().is_some());
+        assert!(repo.query_profile_by_user_name("user1").await.unwrap().is_none());
+        assert!(run_failed_event_command(&repo, &["discard", "abc"]).await.is_err());
+        assert!(run_failed_event_command(&repo, &["retry"]).await.is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::AptosConfig;
+use crate::source::catch_up::EventPage;
+use crate::source::source::{deserialize_u64_from_string_or_number, ChainEventId, RawChainEvent, APTOS_CHAIN};
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Events of the indexer's events table in chain order, filtered by the where clause built in events_request
+const EVENTS_QUERY: &str = "query DechatEvents($where: events_bool_exp!, $limit: Int!) {
+  events(where: $where, order_by: [{ transaction_version: asc }, { event_index: asc }], limit: $limit) {
+    transaction_version
+    event_index
+    type
+    data
+  }
+}";
+
+/// A row of the aptos indexer's events table. data holds the Move event's fields by name like sui's parsed_json,
+/// the table has no transaction sender so aptos profiles are stored without an owner
+#[derive(Deserialize, Debug, Clone)]
+pub struct AptosEvent {
+    #[serde(deserialize_with = "deserialize_u64_from_string_or_number")]
+    pub transaction_version: u64,
+    #[serde(deserialize_with = "deserialize_u64_from_string_or_number")]
+    pub event_index: u64,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: Value,
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse {
+    data: Option<EventsData>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize)]
+struct EventsData {
+    events: Vec<AptosEvent>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+pub fn to_raw_chain_event(event: AptosEvent) -> RawChainEvent {
+    RawChainEvent {
+        chain: APTOS_CHAIN.to_string(),
+        id: ChainEventId { tx_digest: event.transaction_version.to_string(), event_seq: event.event_index },
+        event_type: event.event_type,
+        parsed_json: event.data,
+        sender: None,
+        bcs: vec![],
+    }
+}
+
+/// The indexer stores types with the package address in its long form, 0x followed by 64 lowercase hex digits
+pub fn normalize_address(address: &str) -> Result<String, anyhow::Error> {
+    let hex = address.strip_prefix("0x").unwrap_or(address);
+    if hex.is_empty() || hex.len() > 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("{} is not an aptos address", address));
+    }
+
+    Ok(format!("0x{:0>64}", hex.to_lowercase()))
+}
+
+/// Builds the graphql request for up to limit events of the configured module strictly after the cursor
+pub fn events_request(config: &AptosConfig, cursor: Option<&ChainEventId>, limit: usize) -> Result<Value, anyhow::Error> {
+    let type_prefix = format!("{}::{}::%", normalize_address(&config.package_address)?, config.module);
+    let mut filter = json!({ "indexed_type": { "_like": type_prefix } });
+    if let Some(cursor) = cursor {
+        let version: u64 = cursor.tx_digest.parse()
+            .map_err(|_| anyhow!("{} is not an aptos transaction version", cursor.tx_digest))?;
+        filter["_or"] = json!([
+            { "transaction_version": { "_gt": version } },
+            { "transaction_version": { "_eq": version }, "event_index": { "_gt": cursor.event_seq } },
+        ]);
+    }
+
+    Ok(json!({ "query": EVENTS_QUERY, "variables": { "where": filter, "limit": limit } }))
+}
+
+/// A page is full when the indexer returned as many events as were asked for, there may be more after it
+pub fn parse_events_response(response: Value, limit: usize) -> Result<EventPage, anyhow::Error> {
+    let response = GraphqlResponse::deserialize(response)?;
+    if !response.errors.is_empty() {
+        let messages: Vec<String> = response.errors.into_iter().map(|error| error.message).collect();
+        return Err(anyhow!("aptos indexer query failed: {}", messages.join(", ")));
+    }
+    let events = response.data.ok_or_else(|| anyhow!("aptos indexer response has no data"))?.events;
+
+    let events: Vec<RawChainEvent> = events.into_iter().map(to_raw_chain_event).collect();
+    Ok(EventPage {
+        next_cursor: events.last().map(|raw| raw.id.clone()),
+        has_next_page: events.len() >= limit,
+        events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DechatEvent;
+    use crate::test_helpers::fixtures::{get_aptos_events, APTOS_EVENTS_FIXTURE};
+
+    fn config() -> AptosConfig {
+        AptosConfig {
+            graphql_url: "http://aptos-indexer:8090/v1/graphql".to_string(),
+            package_address: "0xA1".to_string(),
+            module: "dechat".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_recorded_events() {
+        let response: Value = serde_json::from_str(&std::fs::read_to_string(APTOS_EVENTS_FIXTURE).unwrap()).unwrap();
+
+        let page = parse_events_response(response, 4).unwrap();
+
+        assert!(page.events.len() == 4 && page.has_next_page);
+        assert!(page.events.iter().all(|raw| raw.chain == APTOS_CHAIN && raw.bcs.is_empty()));
+        assert!(page.next_cursor == Some(ChainEventId { tx_digest: "1180351907".to_string(), event_seq: 2 }));
+        let decoded: Vec<DechatEvent> = get_aptos_events().into_iter().map(|raw| raw.decode().unwrap().event).collect();
+        assert!(matches!(&decoded[0], DechatEvent::CreateProfile(profile) if profile.user_name == "alice"));
+        assert!(matches!(&decoded[2], DechatEvent::CreatePost(post) if post.message == "hello from aptos"));
+        assert!(matches!(&decoded[3], DechatEvent::Follow(_)));
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let response = json!({ "errors": [{ "message": "field 'events' not found" }] });
+
+        let error = parse_events_response(response, 50).err().unwrap();
+
+        assert!(error.to_string() == "aptos indexer query failed: field 'events' not found");
+        assert!(parse_events_response(json!({ "data": { "events": [] } }), 50).unwrap().events.is_empty());
+    }
+
+    #[test]
+    fn test_events_request_filters_module_after_cursor() {
+        let cursor = ChainEventId { tx_digest: "1180342611".to_string(), event_seq: 1 };
+
+        let request = events_request(&config(), Some(&cursor), 50).unwrap();
+
+        let filter = &request["variables"]["where"];
+        assert!(filter["indexed_type"]["_like"] == format!("0x{}a1::dechat::%", "0".repeat(62)));
+        assert!(filter["_or"][0]["transaction_version"]["_gt"] == 1180342611_u64);
+        assert!(filter["_or"][1]["event_index"]["_gt"] == 1);
+        assert!(request["variables"]["limit"] == 50);
+        assert!(events_request(&config(), None, 50).unwrap()["variables"]["where"].get("_or").is_none());
+        let sui_cursor = ChainEventId { tx_digest: "8ZJ7aV1HzXHnyNSH7TjmyGsQtdCzKpZbMWAaiZcHGpTN".to_string(), event_seq: 0 };
+        assert!(events_request(&config(), Some(&sui_cursor), 50).is_err());
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        assert!(normalize_address("0x1").unwrap() == format!("0x{}1", "0".repeat(63)));
+        assert!(normalize_address(&format!("0x{}", "AB".repeat(32))).unwrap() == format!("0x{}", "ab".repeat(32)));
+        assert!(normalize_address("0x").is_err());
+        assert!(normalize_address("0xzz").is_err());
+        assert!(normalize_address(&format!("0x{}", "1".repeat(65))).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::aptos::aptos::{events_request, parse_events_response};
+use crate::config::AptosConfig;
+use crate::source::catch_up::{EventPage, EventPageFetcher};
+use crate::source::polling::PollingEventSource;
+use crate::source::source::ChainEventId;
+use crate::source::supervisor::EventSourceFactory;
+use async_trait::async_trait;
+use std::time::Duration;
+
+const EVENT_PAGE_SIZE: usize = 50;
+/// Aptos has no event subscriptions, the indexer is polled this often once ingestion has caught up
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct AptosEventPageFetcher {
+    http_client: reqwest::Client,
+    config: AptosConfig,
+}
+
+impl AptosEventPageFetcher {
+    pub fn new(config: AptosConfig) -> Result<Self, anyhow::Error> {
+        let http_client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+        Ok(Self { http_client, config })
+    }
+}
+
+#[async_trait]
+impl EventPageFetcher for AptosEventPageFetcher {
+    async fn fetch_page(&self, cursor: Option<ChainEventId>) -> Result<EventPage, anyhow::Error> {
+        let request = events_request(&self.config, cursor.as_ref(), EVENT_PAGE_SIZE)?;
+        let response = self.http_client.post(&self.config.graphql_url)
+            .json(&request)
+            .send().await?
+            .error_for_status()?
+            .json().await?;
+
+        parse_events_response(response, EVENT_PAGE_SIZE)
+    }
+}
+
+/// Every connect starts a new poller from the cursor, connecting itself does not touch the network
+/// so an unreachable indexer shows up as the first page fetch failing
+pub struct AptosPollerFactory {
+    config: AptosConfig,
+}
+
+impl AptosPollerFactory {
+    pub fn new(config: AptosConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl EventSourceFactory for AptosPollerFactory {
+    type Source = PollingEventSource<AptosEventPageFetcher>;
+
+    async fn connect(&self, cursor: Option<ChainEventId>) -> Result<Self::Source, anyhow::Error> {
+        let fetcher = AptosEventPageFetcher::new(self.config.clone())?;
+        Ok(PollingEventSource::new(fetcher, cursor, POLL_INTERVAL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EventHandlerConfig;
+
+    #[tokio::test]
+    #[ignore = "queries a live aptos indexer set up by the env, parsing is covered by the recorded fixture"]
+    async fn test_fetch_page() {
+        let config = EventHandlerConfig::load().unwrap().aptos.unwrap();
+        let fetcher = AptosEventPageFetcher::new(config).unwrap();
+
+        let page = fetcher.fetch_page(None).await.unwrap();
+
+        println!("{:?}", page.events);
+    }
+}
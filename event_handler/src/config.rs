@@ -0,0 +1,262 @@
+use crate::events::DECHAT_MODULE;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Env var naming a json config file, when it is not set the config is read from the env vars below
+pub const CONFIG_FILE_VAR: &str = "EVENT_HANDLER_CONFIG";
+pub const NETWORK_VAR: &str = "SUI_NETWORK";
+pub const RPC_URL_VAR: &str = "SUI_RPC_URL";
+pub const WS_URL_VAR: &str = "SUI_WS_URL";
+pub const PACKAGE_ADDRESS_VAR: &str = "DECHAT_PACKAGE_ADDRESS";
+pub const MODULE_VAR: &str = "DECHAT_MODULE";
+pub const APTOS_GRAPHQL_URL_VAR: &str = "APTOS_GRAPHQL_URL";
+pub const APTOS_PACKAGE_ADDRESS_VAR: &str = "APTOS_PACKAGE_ADDRESS";
+pub const APTOS_MODULE_VAR: &str = "APTOS_MODULE";
+const APTOS_TESTNET_GRAPHQL_URL: &str = "https://api.testnet.aptoslabs.com/v1/graphql";
+const APTOS_DEFAULT_MODULE: &str = "dechat";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SuiNetwork {
+    Localnet,
+    Devnet,
+    #[default]
+    Testnet,
+    Mainnet,
+}
+
+impl SuiNetwork {
+    pub fn alias(&self) -> &'static str {
+        match self {
+            Self::Localnet => "localnet",
+            Self::Devnet => "devnet",
+            Self::Testnet => "testnet",
+            Self::Mainnet => "mainnet",
+        }
+    }
+
+    pub fn rpc_url(&self) -> &'static str {
+        match self {
+            Self::Localnet => "http://127.0.0.1:9000",
+            Self::Devnet => "https://fullnode.devnet.sui.io:443",
+            Self::Testnet => "https://fullnode.testnet.sui.io:443",
+            Self::Mainnet => "https://fullnode.mainnet.sui.io:443",
+        }
+    }
+
+    pub fn ws_url(&self) -> &'static str {
+        match self {
+            Self::Localnet => "ws://127.0.0.1:9000",
+            Self::Devnet => "wss://rpc.devnet.sui.io:443",
+            Self::Testnet => "wss://rpc.testnet.sui.io:443",
+            Self::Mainnet => "wss://rpc.mainnet.sui.io:443",
+        }
+    }
+}
+
+impl FromStr for SuiNetwork {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "localnet" => Ok(Self::Localnet),
+            "devnet" => Ok(Self::Devnet),
+            "testnet" => Ok(Self::Testnet),
+            "mainnet" => Ok(Self::Mainnet),
+            _ => Err(ConfigError::InvalidNetwork(value.to_string())),
+        }
+    }
+}
+
+/// The dechat package on aptos, its events are read from the aptos indexer's graphql api
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AptosConfig {
+    #[serde(default = "default_aptos_graphql_url")]
+    pub graphql_url: String,
+    pub package_address: String,
+    #[serde(default = "default_aptos_module")]
+    pub module: String,
+}
+
+fn default_aptos_graphql_url() -> String {
+    APTOS_TESTNET_GRAPHQL_URL.to_string()
+}
+
+fn default_aptos_module() -> String {
+    APTOS_DEFAULT_MODULE.to_string()
+}
+
+/// Which sui network and dechat package the event handler follows. The network alias picks the
+/// public fullnode urls, rpc_url and ws_url replace them for custom nodes.
+/// Aptos events are followed as well when an aptos package is configured
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EventHandlerConfig {
+    #[serde(default)]
+    pub network: SuiNetwork,
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    #[serde(default)]
+    pub ws_url: Option<String>,
+    pub package_address: String,
+    #[serde(default = "default_module")]
+    pub module: String,
+    #[serde(default)]
+    pub aptos: Option<AptosConfig>,
+}
+
+fn default_module() -> String {
+    DECHAT_MODULE.to_string()
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Missing(&'static str),
+    InvalidNetwork(String),
+    File { path: String, source: std::io::Error },
+    InvalidFile { path: String, source: serde_json::Error },
+}
+
+impl std::error::Error for ConfigError {}
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(var) => write!(f, "{} is not set", var),
+            Self::InvalidNetwork(network) => {
+                write!(f, "unknown sui network {}, expected localnet, devnet, testnet or mainnet", network)
+            },
+            Self::File { path, source } => write!(f, "failed to read config {}: {}", path, source),
+            Self::InvalidFile { path, source } => write!(f, "invalid config {}: {}", path, source),
+        }
+    }
+}
+
+impl EventHandlerConfig {
+    /// Reads the file named by EVENT_HANDLER_CONFIG if it is set, the env vars otherwise
+    pub fn load() -> Result<Self, ConfigError> {
+        match std::env::var(CONFIG_FILE_VAR) {
+            Ok(path) => Self::from_file(&path),
+            Err(_) => Self::from_vars(|name| std::env::var(name).ok()),
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::File { path: path.to_string(), source })?;
+        serde_json::from_str(&contents).map_err(|source| ConfigError::InvalidFile { path: path.to_string(), source })
+    }
+
+    /// Only the package address is required, the network defaults to testnet.
+    /// Aptos is left out unless APTOS_PACKAGE_ADDRESS is set, its indexer defaults to testnet's
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        Ok(Self {
+            network: var(NETWORK_VAR).map(|network| network.parse()).transpose()?.unwrap_or_default(),
+            rpc_url: var(RPC_URL_VAR),
+            ws_url: var(WS_URL_VAR),
+            package_address: var(PACKAGE_ADDRESS_VAR).ok_or(ConfigError::Missing(PACKAGE_ADDRESS_VAR))?,
+            module: var(MODULE_VAR).unwrap_or_else(default_module),
+            aptos: var(APTOS_PACKAGE_ADDRESS_VAR).map(|package_address| AptosConfig {
+                graphql_url: var(APTOS_GRAPHQL_URL_VAR).unwrap_or_else(default_aptos_graphql_url),
+                package_address,
+                module: var(APTOS_MODULE_VAR).unwrap_or_else(default_aptos_module),
+            }),
+        })
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        self.rpc_url.as_deref().unwrap_or(self.network.rpc_url())
+    }
+
+    pub fn ws_url(&self) -> &str {
+        self.ws_url.as_deref().unwrap_or(self.network.ws_url())
+    }
+
+    /// Name of the sui client env for this config, custom when the urls are overridden
+    pub fn env_alias(&self) -> &str {
+        match self.rpc_url {
+            Some(_) => "custom",
+            None => self.network.alias(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const CONFIG_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/event_handler_config.json");
+
+    fn from_vars(vars: &[(&str, &str)]) -> Result<EventHandlerConfig, ConfigError> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        EventHandlerConfig::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_from_vars_with_network_alias() {
+        let config = from_vars(&[(NETWORK_VAR, "Mainnet"), (PACKAGE_ADDRESS_VAR, "0x2")]).unwrap();
+
+        assert!(config.network == SuiNetwork::Mainnet);
+        assert!(config.rpc_url() == "https://fullnode.mainnet.sui.io:443");
+        assert!(config.ws_url() == "wss://rpc.mainnet.sui.io:443");
+        assert!(config.module == DECHAT_MODULE);
+        assert!(config.env_alias() == "mainnet");
+        assert!(config.aptos.is_none());
+    }
+
+    #[test]
+    fn test_from_vars_with_custom_urls() {
+        let config = from_vars(&[
+            (RPC_URL_VAR, "http://sui-node:9000"),
+            (WS_URL_VAR, "ws://sui-node:9000"),
+            (PACKAGE_ADDRESS_VAR, "0x2"),
+            (MODULE_VAR, "dechat_test"),
+        ]).unwrap();
+
+        assert!(config.network == SuiNetwork::Testnet);
+        assert!(config.rpc_url() == "http://sui-node:9000");
+        assert!(config.ws_url() == "ws://sui-node:9000");
+        assert!(config.module == "dechat_test");
+        assert!(config.env_alias() == "custom");
+    }
+
+    #[test]
+    fn test_from_vars_with_aptos() {
+        let config = from_vars(&[(PACKAGE_ADDRESS_VAR, "0x2"), (APTOS_PACKAGE_ADDRESS_VAR, "0xa1")]).unwrap();
+        assert!(config.aptos == Some(AptosConfig {
+            graphql_url: APTOS_TESTNET_GRAPHQL_URL.to_string(),
+            package_address: "0xa1".to_string(),
+            module: APTOS_DEFAULT_MODULE.to_string(),
+        }));
+
+        let config = from_vars(&[
+            (PACKAGE_ADDRESS_VAR, "0x2"),
+            (APTOS_PACKAGE_ADDRESS_VAR, "0xa1"),
+            (APTOS_GRAPHQL_URL_VAR, "http://aptos-indexer:8090/v1/graphql"),
+            (APTOS_MODULE_VAR, "dechat_test"),
+        ]).unwrap();
+        let aptos = config.aptos.unwrap();
+        assert!(aptos.graphql_url == "http://aptos-indexer:8090/v1/graphql");
+        assert!(aptos.module == "dechat_test");
+    }
+
+    #[test]
+    fn test_from_vars_errors() {
+        assert!(matches!(from_vars(&[]), Err(ConfigError::Missing(PACKAGE_ADDRESS_VAR))));
+        let invalid = from_vars(&[(NETWORK_VAR, "betanet"), (PACKAGE_ADDRESS_VAR, "0x2")]);
+        assert!(matches!(invalid, Err(ConfigError::InvalidNetwork(network)) if network == "betanet"));
+    }
+
+    #[test]
+    fn test_from_file() {
+        let config = EventHandlerConfig::from_file(CONFIG_FIXTURE).unwrap();
+
+        assert!(config.network == SuiNetwork::Localnet);
+        assert!(config.rpc_url() == "http://127.0.0.1:9000");
+        assert!(config.package_address.starts_with("0x7c2a"));
+        assert!(config.module == DECHAT_MODULE);
+        let aptos = config.aptos.unwrap();
+        assert!(aptos.package_address.starts_with("0xecce"));
+        assert!(aptos.graphql_url == APTOS_TESTNET_GRAPHQL_URL && aptos.module == APTOS_DEFAULT_MODULE);
+        assert!(matches!(EventHandlerConfig::from_file("missing.json"), Err(ConfigError::File { .. })));
+    }
+}
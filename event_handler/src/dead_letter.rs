@@ -0,0 +1,294 @@
+use crate::events::{DechatEvent, DecodeError};
+use crate::ingest::{apply_event, ApplyError, ApplyEventRepo};
+use crate::source::source::RawChainEvent;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use repository::repo::base::{BeginTransactionFn, TransactionFn};
+use repository::repo::error::RepositoryError;
+use repository::repo::event_cursor::event_cursor::UpsertEventCursorFn;
+use repository::repo::failed_event::failed_event::{
+    DeleteFailedEventFn, InsertFailedEventFn, QueryDueFailedEventsFn, QueryFailedEventFn, UpdateFailedEventRetryFn
+};
+use repository::repo::failed_event::model::{FailedEventCreate, FailedEventQueryResult};
+
+const RETRY_BATCH_SIZE: i16 = 100;
+
+/// Exponential backoff for events whose write failed. Decode failures are never retried automatically,
+/// decoding the same payload again gives the same result until a new release can read it
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub max_attempts: i32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::seconds(30),
+            max_delay: Duration::hours(1),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// When to retry an event that has failed `attempts` times, None once it is left for an admin
+    pub fn next_retry_at(&self, attempts: i32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let doublings = attempts.clamp(1, 31) as u32 - 1;
+        let delay_ms = self.base_delay.num_milliseconds()
+            .saturating_mul(2_i64.pow(doublings))
+            .min(self.max_delay.num_milliseconds());
+        Some(now + Duration::milliseconds(delay_ms))
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The stored payload is not a raw chain event, it can only be discarded
+    InvalidPayload(serde_json::Error),
+    Decode(DecodeError),
+    MissingReference(String),
+    UnknownChain(String),
+    Repository(RepositoryError),
+}
+
+impl std::error::Error for ReplayError {}
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidPayload(e) => write!(f, "stored payload is not a chain event: {}", e),
+            Self::Decode(e) => write!(f, "{}", e),
+            Self::MissingReference(chain_asset_id) => write!(f, "waiting for chain asset {}", chain_asset_id),
+            Self::UnknownChain(chain) => write!(f, "unknown chain {}", chain),
+            Self::Repository(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<RepositoryError> for ReplayError {
+    fn from(e: RepositoryError) -> Self {
+        Self::Repository(e)
+    }
+}
+
+impl From<ApplyError> for ReplayError {
+    fn from(e: ApplyError) -> Self {
+        match e {
+            ApplyError::MissingReference(chain_asset_id) => Self::MissingReference(chain_asset_id),
+            ApplyError::UnknownChain(chain) => Self::UnknownChain(chain),
+            ApplyError::Repository(e) => Self::Repository(e),
+        }
+    }
+}
+
+/// A failed event that was applied, row_id as returned by apply_event
+#[derive(Debug, Clone)]
+pub struct ReplayedEvent {
+    pub event: DechatEvent,
+    pub row_id: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RetrySummary {
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// Stores the event as failed and moves the cursor past it in one transaction
+pub async fn dead_letter<T>(
+    db_repo: &T,
+    subscription: &str,
+    raw: &RawChainEvent,
+    error: &str,
+    next_retry_at: Option<DateTime<Utc>>
+) -> Result<i64, RepositoryError>
+where
+    T: BeginTransactionFn,
+    T::Transaction: InsertFailedEventFn + UpsertEventCursorFn,
+{
+    let tx = db_repo.begin_transaction().await?;
+    let id = tx.insert_failed_event(FailedEventCreate {
+        subscription: subscription.to_string(),
+        tx_digest: raw.id.tx_digest.clone(),
+        event_seq: raw.id.event_seq as i64,
+        payload: serde_json::to_string(raw).expect("raw chain events serialize to json"),
+        error: error.to_string(),
+        next_retry_at,
+    }).await?;
+    tx.upsert_event_cursor(subscription, &raw.id.tx_digest, raw.id.event_seq as i64).await?;
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+/// Applies a failed event again and deletes it in the same transaction. On failure another attempt is
+/// recorded, scheduled by the policy for write errors and left for an admin otherwise
+pub async fn replay_failed_event<T>(
+    db_repo: &T,
+    policy: &RetryPolicy,
+    failed_event: &FailedEventQueryResult,
+    now: DateTime<Utc>
+) -> Result<ReplayedEvent, ReplayError>
+where
+    T: BeginTransactionFn + UpdateFailedEventRetryFn,
+    T::Transaction: ApplyEventRepo + DeleteFailedEventFn,
+{
+    let result = apply_and_delete(db_repo, failed_event).await;
+
+    if let Err(e) = &result {
+        let next_retry_at = match e {
+            ReplayError::MissingReference(_) | ReplayError::Repository(_) => {
+                policy.next_retry_at(failed_event.attempts + 1, now)
+            },
+            ReplayError::InvalidPayload(_) | ReplayError::Decode(_) | ReplayError::UnknownChain(_) => None,
+        };
+        db_repo.update_failed_event_retry(failed_event.id, &e.to_string(), next_retry_at).await?;
+    }
+    result
+}
+
+async fn apply_and_delete<T>(db_repo: &T, failed_event: &FailedEventQueryResult) -> Result<ReplayedEvent, ReplayError>
+where
+    T: BeginTransactionFn,
+    T::Transaction: ApplyEventRepo + DeleteFailedEventFn,
+{
+    let raw: RawChainEvent = serde_json::from_str(&failed_event.payload).map_err(ReplayError::InvalidPayload)?;
+    let event = DechatEvent::decode(&raw.event_type, &raw.parsed_json, &raw.bcs).map_err(ReplayError::Decode)?;
+
+    let tx = db_repo.begin_transaction().await?;
+    let row_id = match apply_event(&tx, &raw, event.clone()).await {
+        Ok(row_id) => row_id,
+        Err(e) => {
+            tx.rollback().await?;
+            return Err(e.into());
+        },
+    };
+    tx.delete_failed_event(failed_event.id).await?;
+    tx.commit().await?;
+
+    Ok(ReplayedEvent { event, row_id })
+}
+
+/// Replays every failed event that is due, oldest schedule first
+pub async fn retry_due_events<T>(db_repo: &T, policy: &RetryPolicy, now: DateTime<Utc>) -> Result<RetrySummary, RepositoryError>
+where
+    T: BeginTransactionFn + QueryDueFailedEventsFn + UpdateFailedEventRetryFn,
+    T::Transaction: ApplyEventRepo + DeleteFailedEventFn,
+{
+    let mut summary = RetrySummary::default();
+
+    for failed_event in db_repo.query_due_failed_events(now, RETRY_BATCH_SIZE).await? {
+        match replay_failed_event(db_repo, policy, &failed_event, now).await {
+            Ok(replayed) => {
+                info!("retried failed event {} as id {:?}", failed_event.id, replayed.row_id);
+                summary.succeeded += 1;
+            },
+            Err(e) => {
+                error!("retry of failed event {} failed: {}", failed_event.id, e);
+                summary.failed += 1;
+            },
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Retries due events every interval for as long as the process runs
+pub async fn run_retry_worker<T>(db_repo: T, policy: RetryPolicy, interval: std::time::Duration)
+where
+    T: BeginTransactionFn + QueryDueFailedEventsFn + UpdateFailedEventRetryFn + Sync,
+    T::Transaction: ApplyEventRepo + DeleteFailedEventFn,
+{
+    loop {
+        if let Err(e) = retry_due_events(&db_repo, &policy, Utc::now()).await {
+            error!("failed to query due events: {}", e);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Replays a failed event right away regardless of its schedule
+pub async fn replay_failed_event_by_id<T>(db_repo: &T, policy: &RetryPolicy, id: i64) -> Result<ReplayedEvent, ReplayError>
+where
+    T: BeginTransactionFn + QueryFailedEventFn + UpdateFailedEventRetryFn,
+    T::Transaction: ApplyEventRepo + DeleteFailedEventFn,
+{
+    let failed_event = db_repo.query_failed_event(id).await?.ok_or(RepositoryError::NotFound)?;
+    replay_failed_event(db_repo, policy, &failed_event, Utc::now()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::fixtures::get_raw_create_profile_event;
+    use repository::repo::failed_event::failed_event::QueryFailedEventsFn;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::profile::profile::QueryProfileByUserNameFn;
+
+    const SUBSCRIPTION: &str = "test";
+
+    #[test]
+    fn test_next_retry_at_backs_off_exponentially() {
+        let policy = RetryPolicy { base_delay: Duration::seconds(10), max_delay: Duration::seconds(60), max_attempts: 5 };
+        let now = Utc::now();
+
+        let delays: Vec<Option<i64>> = (1..=5)
+            .map(|attempts| policy.next_retry_at(attempts, now).map(|at| (at - now).num_seconds()))
+            .collect();
+
+        assert!(delays == vec![Some(10), Some(20), Some(40), Some(60), None]);
+    }
+
+    #[tokio::test]
+    async fn test_due_event_is_applied_and_deleted() {
+        let repo = InMemoryRepo::new();
+        let now = Utc::now();
+        let raw = get_raw_create_profile_event("digest", 0, "user");
+        let id = dead_letter(&repo, SUBSCRIPTION, &raw, "database unavailable", Some(now)).await.unwrap();
+
+        let summary = retry_due_events(&repo, &RetryPolicy::default(), now).await.unwrap();
+
+        assert!(summary == RetrySummary { succeeded: 1, failed: 0 });
+        assert!(repo.query_profile_by_user_name("user0").await.unwrap().is_some());
+        assert!(repo.query_failed_event(id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_retry_is_rescheduled_then_parked() {
+        let repo = InMemoryRepo::new();
+        let policy = RetryPolicy { max_attempts: 2, ..RetryPolicy::default() };
+        let now = Utc::now();
+        let mut too_long = get_raw_create_profile_event("digest", 0, "user");
+        too_long.parsed_json["user_name"] = "x".repeat(51).into();
+        let id = dead_letter(&repo, SUBSCRIPTION, &too_long, "user_name is too long", Some(now)).await.unwrap();
+
+        let summary = retry_due_events(&repo, &policy, now).await.unwrap();
+
+        assert!(summary == RetrySummary { succeeded: 0, failed: 1 });
+        let failed_event = repo.query_failed_event(id).await.unwrap().unwrap();
+        assert!(failed_event.attempts == 2);
+        assert!(failed_event.next_retry_at.is_none());
+        assert!(retry_due_events(&repo, &policy, now).await.unwrap() == RetrySummary::default());
+    }
+
+    #[tokio::test]
+    async fn test_replay_undecodable_event_stays_parked() {
+        let repo = InMemoryRepo::new();
+        let mut unknown = get_raw_create_profile_event("digest", 0, "user");
+        unknown.event_type = "0x2::dechat_sui::LikeEvent".to_string();
+        let id = dead_letter(&repo, SUBSCRIPTION, &unknown, "unknown event type", None).await.unwrap();
+
+        let result = replay_failed_event_by_id(&repo, &RetryPolicy::default(), id).await;
+
+        assert!(matches!(result, Err(ReplayError::Decode(DecodeError::UnknownEventType(_)))));
+        let failed_events = repo.query_failed_events(None, 10).await.unwrap();
+        assert!(failed_events.len() == 1 && failed_events[0].next_retry_at.is_none());
+        let missing = replay_failed_event_by_id(&repo, &RetryPolicy::default(), id + 1).await;
+        assert!(matches!(missing, Err(ReplayError::Repository(RepositoryError::NotFound))));
+    }
+}
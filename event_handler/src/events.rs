@@ -0,0 +1,415 @@
+use repository::repo::profile::model::ProfileCreate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+pub const DECHAT_MODULE: &str = "dechat_sui";
+pub const CREATE_PROFILE_EVENT: &str = "CreateProfileEvent";
+pub const UPDATE_PROFILE_EVENT: &str = "UpdateProfileEvent";
+pub const CREATE_POST_EVENT: &str = "CreatePostEvent";
+pub const CREATE_RESPONSE_POST_EVENT: &str = "CreateResponsePostEvent";
+pub const CREATE_SHARE_POST_EVENT: &str = "CreateSharePostEvent";
+pub const FOLLOW_EVENT: &str = "FollowEvent";
+pub const UNFOLLOW_EVENT: &str = "UnfollowEvent";
+
+/// Every dechat event the ingestion pipeline knows how to apply, independent of the chain it came from
+#[derive(Debug, Clone)]
+pub enum DechatEvent {
+    CreateProfile(CreateProfileEvent),
+    UpdateProfile(UpdateProfileEvent),
+    CreatePost(CreatePostEvent),
+    CreateResponsePost(CreateResponsePostEvent),
+    CreateSharePost(CreateSharePostEvent),
+    Follow(FollowEvent),
+    Unfollow(UnfollowEvent),
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    UnknownEventType(String),
+    InvalidPayload { event_type: String, source: serde_json::Error },
+    InvalidBcs { event_type: String, source: bcs::Error },
+    /// The payload has the right shape but a value the Move module can never emit
+    InvalidField { event_type: String, field: &'static str, reason: String },
+}
+
+impl std::error::Error for DecodeError {}
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownEventType(event_type) => write!(f, "unknown event type {}", event_type),
+            Self::InvalidPayload { event_type, source } => write!(f, "invalid {} payload: {}", event_type, source),
+            Self::InvalidBcs { event_type, source } => write!(f, "invalid {} bcs: {}", event_type, source),
+            Self::InvalidField { event_type, field, reason } => {
+                write!(f, "invalid {} field {}: {}", event_type, field, reason)
+            },
+        }
+    }
+}
+
+/// A field that failed validation, turned into DecodeError::InvalidField once the event type is known
+#[derive(Debug)]
+pub struct InvalidField {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+/// Decoding shared by the event payloads. Field order matters, it is the order of the Move struct's fields
+/// which is what the bcs bytes follow, while parsed_json is matched by name
+pub trait EventPayload: DeserializeOwned {
+    fn validate(&self) -> Result<(), InvalidField>;
+
+    fn from_json(parsed_json: &Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize(parsed_json)
+    }
+
+    fn from_bcs(bcs: &[u8]) -> Result<Self, bcs::Error> {
+        bcs::from_bytes(bcs)
+    }
+}
+
+impl DechatEvent {
+    /// event_type may be a bare struct name or a fully qualified Move type like 0x2::dechat_sui::CreateProfileEvent.
+    /// The bcs bytes are decoded when the source provides them, parsed_json otherwise
+    pub fn decode(event_type: &str, parsed_json: &Value, bcs: &[u8]) -> Result<Self, DecodeError> {
+        let name = event_type.rsplit("::").next().unwrap_or(event_type);
+
+        match name {
+            CREATE_PROFILE_EVENT => decode_payload(name, parsed_json, bcs).map(Self::CreateProfile),
+            UPDATE_PROFILE_EVENT => decode_payload(name, parsed_json, bcs).map(Self::UpdateProfile),
+            CREATE_POST_EVENT => decode_payload(name, parsed_json, bcs).map(Self::CreatePost),
+            CREATE_RESPONSE_POST_EVENT => decode_payload(name, parsed_json, bcs).map(Self::CreateResponsePost),
+            CREATE_SHARE_POST_EVENT => decode_payload(name, parsed_json, bcs).map(Self::CreateSharePost),
+            FOLLOW_EVENT => decode_payload(name, parsed_json, bcs).map(Self::Follow),
+            UNFOLLOW_EVENT => decode_payload(name, parsed_json, bcs).map(Self::Unfollow),
+            _ => Err(DecodeError::UnknownEventType(event_type.to_string())),
+        }
+    }
+
+    /// The on-chain object this event brings into existence, events referring to it may be waiting for it
+    pub fn created_asset(&self) -> Option<&str> {
+        match self {
+            Self::CreateProfile(profile) => Some(&profile.profile_id),
+            Self::CreatePost(post) => Some(&post.post_id),
+            Self::CreateResponsePost(response) => Some(&response.post_id),
+            Self::CreateSharePost(share) => Some(&share.post_id),
+            Self::UpdateProfile(_) | Self::Follow(_) | Self::Unfollow(_) => None,
+        }
+    }
+}
+
+fn decode_payload<E: EventPayload>(name: &str, parsed_json: &Value, bcs: &[u8]) -> Result<E, DecodeError> {
+    let event = match bcs {
+        [] => E::from_json(parsed_json)
+            .map_err(|source| DecodeError::InvalidPayload { event_type: name.to_string(), source })?,
+        bcs => E::from_bcs(bcs)
+            .map_err(|source| DecodeError::InvalidBcs { event_type: name.to_string(), source })?,
+    };
+    event.validate()
+        .map_err(|InvalidField { field, reason }| DecodeError::InvalidField { event_type: name.to_string(), field, reason })?;
+
+    Ok(event)
+}
+
+/// Object ids are hex strings in parsed_json and 32 raw bytes in bcs, both become the 0x prefixed hex string
+fn deserialize_object_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    if deserializer.is_human_readable() {
+        return String::deserialize(deserializer);
+    }
+
+    let bytes = <[u8; 32]>::deserialize(deserializer)?;
+    Ok(format!("0x{}", bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()))
+}
+
+fn check_object_id(field: &'static str, value: &str) -> Result<(), InvalidField> {
+    let is_valid = match value.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.len() <= 64 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    };
+    match is_valid {
+        true => Ok(()),
+        false => Err(InvalidField { field, reason: format!("{} is not an object id", value) }),
+    }
+}
+
+fn check_not_empty(field: &'static str, value: &str) -> Result<(), InvalidField> {
+    match value.is_empty() {
+        true => Err(InvalidField { field, reason: "must not be empty".to_string() }),
+        false => Ok(()),
+    }
+}
+
+/// Move has no optional strings in these events, an empty string means none was set
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// Payload of dechat_sui::CreateProfileEvent as rendered in a SuiEvent's parsed_json.
+/// profile_id is the object id of the on-chain Profile and becomes the chain_asset_id
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateProfileEvent {
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub profile_id: String,
+    pub user_name: String,
+    pub full_name: String,
+    pub description: String,
+    #[serde(default)]
+    pub main_url: String,
+}
+
+impl EventPayload for CreateProfileEvent {
+    fn validate(&self) -> Result<(), InvalidField> {
+        check_object_id("profile_id", &self.profile_id)?;
+        check_not_empty("user_name", &self.user_name)
+    }
+}
+
+impl CreateProfileEvent {
+    /// Avatars are not stored on chain, the owner is the wallet that sent the creating transaction
+    pub fn into_profile_create(self, chain_id: i64, owner_address: Option<String>) -> ProfileCreate {
+        ProfileCreate {
+            chain_asset_id: self.profile_id,
+            chain_id,
+            user_name: self.user_name,
+            full_name: self.full_name,
+            description: self.description,
+            main_url: non_empty(self.main_url),
+            avatar: None,
+            owner_address,
+        }
+    }
+}
+
+/// The user name of a profile is fixed at creation, everything else can be updated
+#[derive(Deserialize, Debug, Clone)]
+pub struct UpdateProfileEvent {
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub profile_id: String,
+    pub full_name: String,
+    pub description: String,
+    #[serde(default)]
+    pub main_url: String,
+}
+
+impl EventPayload for UpdateProfileEvent {
+    fn validate(&self) -> Result<(), InvalidField> {
+        check_object_id("profile_id", &self.profile_id)
+    }
+}
+
+impl UpdateProfileEvent {
+    pub fn main_url(&self) -> Option<String> {
+        non_empty(self.main_url.clone())
+    }
+}
+
+/// post_id is the object id of the on-chain Post, profile_id the Profile of its author
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreatePostEvent {
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub post_id: String,
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub profile_id: String,
+    pub message: String,
+    #[serde(default)]
+    pub image: String,
+}
+
+impl EventPayload for CreatePostEvent {
+    fn validate(&self) -> Result<(), InvalidField> {
+        check_object_id("post_id", &self.post_id)?;
+        check_object_id("profile_id", &self.profile_id)?;
+        check_not_empty("message", &self.message)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateResponsePostEvent {
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub post_id: String,
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub profile_id: String,
+    pub message: String,
+    #[serde(default)]
+    pub image: String,
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub respondee_post_id: String,
+}
+
+impl EventPayload for CreateResponsePostEvent {
+    fn validate(&self) -> Result<(), InvalidField> {
+        check_object_id("post_id", &self.post_id)?;
+        check_object_id("profile_id", &self.profile_id)?;
+        check_not_empty("message", &self.message)?;
+        check_object_id("respondee_post_id", &self.respondee_post_id)
+    }
+}
+
+/// A share may quote the shared post with a message of its own or repost it without one
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreateSharePostEvent {
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub post_id: String,
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub profile_id: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub sharee_post_id: String,
+}
+
+impl EventPayload for CreateSharePostEvent {
+    fn validate(&self) -> Result<(), InvalidField> {
+        check_object_id("post_id", &self.post_id)?;
+        check_object_id("profile_id", &self.profile_id)?;
+        check_object_id("sharee_post_id", &self.sharee_post_id)
+    }
+}
+
+impl CreateSharePostEvent {
+    pub fn message(&self) -> Option<String> {
+        non_empty(self.message.clone())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FollowEvent {
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub follower_profile_id: String,
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub following_profile_id: String,
+}
+
+impl EventPayload for FollowEvent {
+    fn validate(&self) -> Result<(), InvalidField> {
+        check_object_id("follower_profile_id", &self.follower_profile_id)?;
+        check_object_id("following_profile_id", &self.following_profile_id)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UnfollowEvent {
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub follower_profile_id: String,
+    #[serde(deserialize_with = "deserialize_object_id")]
+    pub following_profile_id: String,
+}
+
+impl EventPayload for UnfollowEvent {
+    fn validate(&self) -> Result<(), InvalidField> {
+        check_object_id("follower_profile_id", &self.follower_profile_id)?;
+        check_object_id("following_profile_id", &self.following_profile_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use repository::test_helpers::fixtures::SUI_CHAIN_ID;
+    use serde_json::json;
+
+    /// bcs of a Move string, a uleb128 length followed by the utf8 bytes, all strings here are shorter than 128
+    fn bcs_string(value: &str) -> Vec<u8> {
+        [vec![value.len() as u8], value.as_bytes().to_vec()].concat()
+    }
+
+    #[test]
+    fn test_create_profile_event_into_profile_create() {
+        let parsed_json = json!({
+            "profile_id": "0x5c3e4b2a1f",
+            "user_name": "jill",
+            "full_name": "Jill Simon",
+            "description": "I am a developer",
+            "main_url": ""
+        });
+
+        let profile_create = CreateProfileEvent::from_json(&parsed_json).unwrap().into_profile_create(SUI_CHAIN_ID, Some("0x1f3e".to_string()));
+
+        assert!(profile_create.chain_asset_id == "0x5c3e4b2a1f");
+        assert!(profile_create.chain_id == SUI_CHAIN_ID);
+        assert!(profile_create.user_name == "jill");
+        assert!(profile_create.main_url.is_none());
+        assert!(profile_create.avatar.is_none());
+        assert!(profile_create.owner_address == Some("0x1f3e".to_string()));
+    }
+
+    #[test]
+    fn test_create_profile_event_missing_field() {
+        let parsed_json = json!({ "profile_id": "0x5c3e4b2a1f", "user_name": "jill" });
+
+        assert!(CreateProfileEvent::from_json(&parsed_json).is_err());
+    }
+
+    #[test]
+    fn test_decode_by_type_name() {
+        let parsed_json = json!({
+            "profile_id": "0x5c3e4b2a1f",
+            "user_name": "jill",
+            "full_name": "Jill Simon",
+            "description": "I am a developer"
+        });
+
+        let event = DechatEvent::decode("0x2::dechat_sui::CreateProfileEvent", &parsed_json, &[]).unwrap();
+        assert!(matches!(event, DechatEvent::CreateProfile(profile) if profile.user_name == "jill"));
+
+        let unknown = DechatEvent::decode("0x2::dechat_sui::LikeEvent", &parsed_json, &[]);
+        assert!(matches!(unknown, Err(DecodeError::UnknownEventType(event_type)) if event_type == "0x2::dechat_sui::LikeEvent"));
+
+        let invalid = DechatEvent::decode(CREATE_PROFILE_EVENT, &json!({}), &[]);
+        assert!(matches!(invalid, Err(DecodeError::InvalidPayload { .. })));
+    }
+
+    #[test]
+    fn test_decode_every_event_type() {
+        let post = json!({ "post_id": "0xb1", "profile_id": "0xa1", "message": "hello", "image": "" });
+        let response = json!({ "post_id": "0xb2", "profile_id": "0xa2", "message": "hi", "respondee_post_id": "0xb1" });
+        let share = json!({ "post_id": "0xb3", "profile_id": "0xa2", "message": "", "sharee_post_id": "0xb1" });
+        let follow = json!({ "follower_profile_id": "0xa2", "following_profile_id": "0xa1" });
+        let update = json!({ "profile_id": "0xa1", "full_name": "Jill Simon", "description": "Still a developer" });
+
+        let event = DechatEvent::decode(CREATE_POST_EVENT, &post, &[]).unwrap();
+        assert!(matches!(event, DechatEvent::CreatePost(post) if post.message == "hello" && post.image.is_empty()));
+        let event = DechatEvent::decode(CREATE_RESPONSE_POST_EVENT, &response, &[]).unwrap();
+        assert!(matches!(event, DechatEvent::CreateResponsePost(response) if response.respondee_post_id == "0xb1"));
+        let event = DechatEvent::decode(CREATE_SHARE_POST_EVENT, &share, &[]).unwrap();
+        assert!(matches!(event, DechatEvent::CreateSharePost(share) if share.message().is_none()));
+        let event = DechatEvent::decode(FOLLOW_EVENT, &follow, &[]).unwrap();
+        assert!(matches!(event, DechatEvent::Follow(follow) if follow.following_profile_id == "0xa1"));
+        let event = DechatEvent::decode(UNFOLLOW_EVENT, &follow, &[]).unwrap();
+        assert!(matches!(event, DechatEvent::Unfollow(unfollow) if unfollow.follower_profile_id == "0xa2"));
+        let event = DechatEvent::decode(UPDATE_PROFILE_EVENT, &update, &[]).unwrap();
+        assert!(matches!(event, DechatEvent::UpdateProfile(update) if update.main_url().is_none()));
+    }
+
+    #[test]
+    fn test_decode_bcs() {
+        let follower = [0xa2_u8; 32];
+        let following = [0xa1_u8; 32];
+        let bcs = [follower.to_vec(), following.to_vec()].concat();
+
+        let event = DechatEvent::decode(FOLLOW_EVENT, &Value::Null, &bcs).unwrap();
+        assert!(matches!(
+            event,
+            DechatEvent::Follow(follow) if follow.follower_profile_id == format!("0x{}", "a2".repeat(32))
+                && follow.following_profile_id == format!("0x{}", "a1".repeat(32))
+        ));
+
+        let bcs = [vec![1; 32], vec![2; 32], bcs_string("hello"), bcs_string("")].concat();
+        let event = DechatEvent::decode(CREATE_POST_EVENT, &Value::Null, &bcs).unwrap();
+        assert!(matches!(event, DechatEvent::CreatePost(post) if post.message == "hello" && post.image.is_empty()));
+
+        let truncated = DechatEvent::decode(CREATE_POST_EVENT, &Value::Null, &bcs[..40]);
+        assert!(matches!(truncated, Err(DecodeError::InvalidBcs { .. })));
+    }
+
+    #[test]
+    fn test_decode_validates_fields() {
+        let bad_id = json!({ "follower_profile_id": "a2", "following_profile_id": "0xa1" });
+        let empty_message = json!({ "post_id": "0xb1", "profile_id": "0xa1", "message": "" });
+
+        let invalid = DechatEvent::decode(FOLLOW_EVENT, &bad_id, &[]);
+        assert!(matches!(invalid, Err(DecodeError::InvalidField { field: "follower_profile_id", .. })));
+        let invalid = DechatEvent::decode(CREATE_POST_EVENT, &empty_message, &[]);
+        assert!(matches!(invalid, Err(DecodeError::InvalidField { field: "message", .. })));
+    }
+}
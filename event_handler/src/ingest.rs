@@ -0,0 +1,540 @@
+use crate::dead_letter::{dead_letter, replay_failed_event_by_id, ReplayError, RetryPolicy};
+use crate::events::DechatEvent;
+use crate::source::source::{ChainEvent, ChainEventId, ChainEventSource, EventSourceError, RawChainEvent};
+use chrono::Utc;
+use log::{error, info};
+use repository::repo::base::{BeginTransactionFn, TransactionFn};
+use repository::repo::chain::chain::QueryChainIdByNameFn;
+use repository::repo::error::RepositoryError;
+use repository::repo::event_cursor::event_cursor::{QueryEventCursorFn, UpsertEventCursorFn};
+use repository::repo::failed_event::failed_event::{
+    DeleteFailedEventFn, InsertFailedEventFn, QueryFailedEventFn, UpdateFailedEventRetryFn
+};
+use repository::repo::follow::follow::{UnfollowUserFn, UpsertFollowFn};
+use repository::repo::post::post::{QueryPostIdByChainAssetFn, UpsertPostFn, UpsertResponsePostFn, UpsertSharePostFn};
+use repository::repo::profile::model::ProfileUpdate;
+use repository::repo::profile::profile::{QueryProfileByChainAssetFn, UpdateProfileFn, UpsertProfileFn};
+use std::collections::HashMap;
+
+/// held counts events still waiting for a chain asset when the source ended, once released they count as applied
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IngestSummary {
+    pub applied: usize,
+    pub failed: usize,
+    pub held: usize,
+}
+
+/// Every repository function apply_event needs, implemented by any repo or transaction that has them all
+pub trait ApplyEventRepo:
+    QueryChainIdByNameFn + UpsertProfileFn + UpdateProfileFn + QueryProfileByChainAssetFn + UpsertPostFn + UpsertResponsePostFn
+    + UpsertSharePostFn + QueryPostIdByChainAssetFn + UpsertFollowFn + UnfollowUserFn {}
+
+impl<T> ApplyEventRepo for T where
+    T: QueryChainIdByNameFn + UpsertProfileFn + UpdateProfileFn + QueryProfileByChainAssetFn + UpsertPostFn + UpsertResponsePostFn
+        + UpsertSharePostFn + QueryPostIdByChainAssetFn + UpsertFollowFn + UnfollowUserFn {}
+
+#[derive(Debug)]
+pub enum ApplyError {
+    /// The event refers to a profile or post whose chain asset has not been written yet
+    MissingReference(String),
+    /// The event's chain has no row in the chain table, it can only be applied once a migration adds one
+    UnknownChain(String),
+    Repository(RepositoryError),
+}
+
+impl std::error::Error for ApplyError {}
+impl std::fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingReference(chain_asset_id) => write!(f, "waiting for chain asset {}", chain_asset_id),
+            Self::UnknownChain(chain) => write!(f, "unknown chain {}", chain),
+            Self::Repository(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<RepositoryError> for ApplyError {
+    fn from(e: RepositoryError) -> Self {
+        Self::Repository(e)
+    }
+}
+
+/// Stops ingestion, the cursor stays before the event so that a restart receives it again
+#[derive(Debug)]
+pub enum IngestError {
+    DeadLetter { id: ChainEventId, error: RepositoryError },
+}
+
+impl std::error::Error for IngestError {}
+impl std::fmt::Display for IngestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DeadLetter { id, error } => write!(f, "failed to dead letter event {:?}: {}", id, error),
+        }
+    }
+}
+
+async fn resolve_chain<T: QueryChainIdByNameFn>(db_repo: &T, chain: &str) -> Result<i64, ApplyError> {
+    db_repo.query_chain_id_by_name(chain).await?
+        .ok_or_else(|| ApplyError::UnknownChain(chain.to_string()))
+}
+
+async fn resolve_profile<T: QueryProfileByChainAssetFn>(db_repo: &T, chain_id: i64, profile_id: &str) -> Result<i64, ApplyError> {
+    match db_repo.query_profile_by_chain_asset(chain_id, profile_id).await? {
+        Some(profile) => Ok(profile.id),
+        None => Err(ApplyError::MissingReference(profile_id.to_string())),
+    }
+}
+
+async fn resolve_post<T: QueryPostIdByChainAssetFn>(db_repo: &T, chain_id: i64, post_id: &str) -> Result<i64, ApplyError> {
+    db_repo.query_post_id_by_chain_asset(chain_id, post_id).await?
+        .ok_or_else(|| ApplyError::MissingReference(post_id.to_string()))
+}
+
+/// Returns the id of the row the event was written to, None for unfollows which only delete.
+/// Creations are applied as upserts keyed by their chain asset, so applying an event that was already applied
+/// is a no-op returning the existing row's id. Chain assets the event refers to are resolved to local ids first,
+/// on the raw event's chain, so assets of different chains never resolve to each other.
+/// The image of a chain post is a url or content id, not image data, so it is not stored in the image column
+pub async fn apply_event<T: ApplyEventRepo>(db_repo: &T, raw: &RawChainEvent, event: DechatEvent) -> Result<Option<i64>, ApplyError> {
+    let chain_id = resolve_chain(db_repo, &raw.chain).await?;
+    let row_id = match event {
+        DechatEvent::CreateProfile(profile) => {
+            db_repo.upsert_profile(profile.into_profile_create(chain_id, raw.sender.clone())).await?
+        },
+        DechatEvent::UpdateProfile(update) => {
+            let main_url = update.main_url();
+            let profile = db_repo.query_profile_by_chain_asset(chain_id, &update.profile_id).await?
+                .ok_or_else(|| ApplyError::MissingReference(update.profile_id.clone()))?;
+            db_repo.update_profile(profile.id, ProfileUpdate {
+                full_name: update.full_name,
+                description: update.description,
+                main_url,
+                avatar: profile.avatar,
+            }).await?;
+            profile.id
+        },
+        DechatEvent::CreatePost(post) => {
+            let user_id = resolve_profile(db_repo, chain_id, &post.profile_id).await?;
+            db_repo.upsert_standalone_post(&post.post_id, chain_id, user_id, &post.message, None).await?.id
+        },
+        DechatEvent::CreateResponsePost(response) => {
+            let user_id = resolve_profile(db_repo, chain_id, &response.profile_id).await?;
+            let respondee_post_id = resolve_post(db_repo, chain_id, &response.respondee_post_id).await?;
+            db_repo.upsert_response_post(
+                &response.post_id,
+                chain_id,
+                user_id,
+                &response.message,
+                None,
+                respondee_post_id
+            ).await?.id
+        },
+        DechatEvent::CreateSharePost(share) => {
+            let user_id = resolve_profile(db_repo, chain_id, &share.profile_id).await?;
+            let sharee_post_id = resolve_post(db_repo, chain_id, &share.sharee_post_id).await?;
+            db_repo.upsert_share_post(&share.post_id, chain_id, user_id, share.message(), sharee_post_id).await?.id
+        },
+        DechatEvent::Follow(follow) => {
+            let follower_id = resolve_profile(db_repo, chain_id, &follow.follower_profile_id).await?;
+            let following_id = resolve_profile(db_repo, chain_id, &follow.following_profile_id).await?;
+            db_repo.upsert_follow(follower_id, following_id).await?
+        },
+        DechatEvent::Unfollow(unfollow) => {
+            let follower_id = resolve_profile(db_repo, chain_id, &unfollow.follower_profile_id).await?;
+            let following_id = resolve_profile(db_repo, chain_id, &unfollow.following_profile_id).await?;
+            db_repo.unfollow_user(follower_id, following_id).await?;
+            return Ok(None);
+        },
+    };
+
+    Ok(Some(row_id))
+}
+
+/// Where the subscription stopped last time, None when it has never processed an event
+pub async fn load_cursor<T: QueryEventCursorFn>(db_repo: &T, subscription: &str) -> Result<Option<ChainEventId>, RepositoryError> {
+    Ok(db_repo.query_event_cursor(subscription).await?.map(|cursor| ChainEventId {
+        tx_digest: cursor.tx_digest,
+        event_seq: cursor.event_seq as u64,
+    }))
+}
+
+/// The event's writes and the cursor move commit together, so after a crash the event is either
+/// fully applied and skipped on restart, or not applied at all and received again
+async fn apply_and_advance<T>(db_repo: &T, subscription: &str, chain_event: ChainEvent) -> Result<Option<i64>, ApplyError>
+where
+    T: BeginTransactionFn,
+    T::Transaction: ApplyEventRepo + UpsertEventCursorFn,
+{
+    let ChainEvent { raw, event } = chain_event;
+    let tx = db_repo.begin_transaction().await?;
+    match apply_event(&tx, &raw, event).await {
+        Ok(row_id) => {
+            tx.upsert_event_cursor(subscription, &raw.id.tx_digest, raw.id.event_seq as i64).await?;
+            tx.commit().await?;
+            Ok(row_id)
+        },
+        Err(e) => {
+            tx.rollback().await?;
+            Err(e)
+        },
+    }
+}
+
+/// Events that arrived before a chain asset they refer to, by that chain asset. The events themselves are
+/// dead lettered so they survive a restart and the retry worker picks them up, this only lets ingestion
+/// replay them as soon as the asset is written instead of waiting for their retry
+#[derive(Debug, Default)]
+pub struct HeldEvents {
+    failed_event_ids: HashMap<String, Vec<i64>>,
+}
+
+impl HeldEvents {
+    pub fn hold(&mut self, chain_asset_id: String, failed_event_id: i64) {
+        self.failed_event_ids.entry(chain_asset_id).or_default().push(failed_event_id);
+    }
+
+    /// Replays the events waiting for the created asset, then the events waiting for whatever those created.
+    /// An event that still misses another asset is held again for that one
+    pub async fn release<T>(&mut self, db_repo: &T, policy: &RetryPolicy, created_asset: &str) -> usize
+    where
+        T: BeginTransactionFn + QueryFailedEventFn + UpdateFailedEventRetryFn,
+        T::Transaction: ApplyEventRepo + DeleteFailedEventFn,
+    {
+        let mut released = 0;
+        let mut created_assets = vec![created_asset.to_string()];
+
+        while let Some(created_asset) = created_assets.pop() {
+            for failed_event_id in self.failed_event_ids.remove(&created_asset).unwrap_or_default() {
+                match replay_failed_event_by_id(db_repo, policy, failed_event_id).await {
+                    Ok(replayed) => {
+                        info!("released failed event {} waiting for {}", failed_event_id, created_asset);
+                        released += 1;
+                        created_assets.extend(replayed.event.created_asset().map(str::to_string));
+                    },
+                    Err(ReplayError::MissingReference(chain_asset_id)) => self.hold(chain_asset_id, failed_event_id),
+                    Err(e) => error!("failed to release failed event {}: {}", failed_event_id, e),
+                }
+            }
+        }
+
+        released
+    }
+}
+
+/// Applies every event of the source until it is exhausted and keeps the subscription's cursor on the last one.
+/// Events that fail to decode or write are dead lettered so one bad event does not stop ingestion,
+/// write failures are scheduled for retry by the policy while decode failures and unknown chains wait for an admin.
+/// Events referring to a chain asset that does not exist yet are held until it is written.
+/// When an event can neither be applied nor dead lettered ingestion stops there rather than lose it
+pub async fn run_ingestion<S, T>(
+    source: &mut S,
+    db_repo: &T,
+    subscription: &str,
+    policy: &RetryPolicy
+) -> Result<IngestSummary, IngestError>
+where
+    S: ChainEventSource + Send,
+    T: BeginTransactionFn + QueryFailedEventFn + UpdateFailedEventRetryFn + Sync,
+    T::Transaction: ApplyEventRepo + UpsertEventCursorFn + InsertFailedEventFn + DeleteFailedEventFn,
+{
+    let mut summary = IngestSummary::default();
+    let mut held_events = HeldEvents::default();
+
+    while let Some(next) = source.next_event().await {
+        let (raw, error, next_retry_at, missing_reference) = match next {
+            Ok(chain_event) => {
+                let raw = chain_event.raw.clone();
+                let created_asset = chain_event.event.created_asset().map(str::to_string);
+                match apply_and_advance(db_repo, subscription, chain_event).await {
+                    Ok(row_id) => {
+                        info!("applied event {:?} as id {:?}", raw.id, row_id);
+                        summary.applied += 1;
+                        if let Some(created_asset) = created_asset {
+                            let released = held_events.release(db_repo, policy, &created_asset).await;
+                            summary.applied += released;
+                            summary.held -= released;
+                        }
+                        continue;
+                    },
+                    Err(ApplyError::MissingReference(chain_asset_id)) => {
+                        info!("holding event {:?} until {} exists", raw.id, chain_asset_id);
+                        let error = ApplyError::MissingReference(chain_asset_id.clone()).to_string();
+                        (raw, error, policy.next_retry_at(1, Utc::now()), Some(chain_asset_id))
+                    },
+                    Err(e) => {
+                        error!("failed to apply event {:?}: {}", raw.id, e);
+                        let next_retry_at = match e {
+                            ApplyError::UnknownChain(_) => None,
+                            _ => policy.next_retry_at(1, Utc::now()),
+                        };
+                        (raw, e.to_string(), next_retry_at, None)
+                    },
+                }
+            },
+            Err(EventSourceError::Decode { raw, error }) => {
+                error!("failed to decode event {:?}: {}", raw.id, error);
+                (*raw, error.to_string(), None, None)
+            },
+            Err(e @ EventSourceError::Transport(_)) => {
+                error!("{}", e);
+                continue;
+            },
+        };
+
+        let failed_event_id = dead_letter(db_repo, subscription, &raw, &error, next_retry_at).await
+            .map_err(|error| IngestError::DeadLetter { id: raw.id.clone(), error })?;
+        match missing_reference {
+            Some(chain_asset_id) => {
+                held_events.hold(chain_asset_id, failed_event_id);
+                summary.held += 1;
+            },
+            None => summary.failed += 1,
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::replay::ReplayEventSource;
+    use crate::events::{
+        CREATE_POST_EVENT, CREATE_RESPONSE_POST_EVENT, CREATE_SHARE_POST_EVENT, FOLLOW_EVENT, UNFOLLOW_EVENT,
+        UPDATE_PROFILE_EVENT
+    };
+    use crate::test_helpers::fixtures::{
+        get_aptos_events, get_object_id, get_raw_create_profile_event, get_raw_event, VecEventSource, SUI_EVENTS_FIXTURE
+    };
+    use crate::source::source::{APTOS_CHAIN, SUI_CHAIN};
+    use repository::repo::chain::chain::QueryChainIdByNameFn;
+    use repository::repo::failed_event::failed_event::QueryFailedEventsFn;
+    use repository::repo::follow::follow::QueryIsFollowingFn;
+    use repository::repo::in_memory::in_memory::InMemoryRepo;
+    use repository::repo::post::post::{QueryPostFn, QuerySharedPostFn};
+    use repository::repo::profile::profile::QueryProfileByUserNameFn;
+    use repository::test_helpers::fixtures::SUI_CHAIN_ID;
+    use serde_json::json;
+
+    const SUBSCRIPTION: &str = "test";
+
+    #[tokio::test]
+    async fn test_run_ingestion_from_replay() {
+        let repo = InMemoryRepo::new();
+        let mut source = ReplayEventSource::open(SUI_EVENTS_FIXTURE).await.unwrap();
+
+        let summary = run_ingestion(&mut source, &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+
+        assert!(summary == IngestSummary { applied: 2, failed: 2, held: 0 });
+        let jill = repo.query_profile_by_user_name("jill").await.unwrap().unwrap();
+        assert!(jill.chain_asset_id == "0x3a9f1c5e7b2d4f6a8c0e1b3d5f7a9c2e4b6d8f0a1c3e5b7d9f2a4c6e8b0d1f3a");
+        assert!(jill.chain_id == SUI_CHAIN_ID);
+        assert!(jill.main_url == Some("https://jill.dev".to_string()));
+        assert!(jill.owner_address == Some("0x1f3e5d7c9b2a4c6e8f0a1b3c5d7e9f2a4b6c8d0e1f3a5b7c9d2e4f6a8b0c1d3e".to_string()));
+        let dave = repo.query_profile_by_user_name("dave").await.unwrap().unwrap();
+        assert!(dave.main_url.is_none());
+        assert!(repo.query_profile_by_user_name("tom").await.unwrap().is_none());
+        let failed_events = repo.query_failed_events(None, 10).await.unwrap();
+        assert!(failed_events.len() == 2);
+        assert!(failed_events.iter().all(|failed_event| failed_event.next_retry_at.is_none()));
+
+        let cursor = load_cursor(&repo, SUBSCRIPTION).await.unwrap().unwrap();
+        assert!(cursor == ChainEventId { tx_digest: "9xYzA1bC2dE3fG4hJ5kL6mN7oP8qR9sT1uV2wX3yZ4aB".to_string(), event_seq: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_replayed_events_are_no_ops() {
+        let repo = InMemoryRepo::new();
+        let mut source = ReplayEventSource::open(SUI_EVENTS_FIXTURE).await.unwrap();
+        _ = run_ingestion(&mut source, &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+        let jill = repo.query_profile_by_user_name("jill").await.unwrap().unwrap();
+
+        let mut source = ReplayEventSource::open(SUI_EVENTS_FIXTURE).await.unwrap();
+        let summary = run_ingestion(&mut source, &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+        assert!(summary == IngestSummary { applied: 2, failed: 2, held: 0 });
+        assert!(repo.query_profile_by_user_name("jill").await.unwrap().unwrap().id == jill.id);
+
+        let raw = get_raw_create_profile_event("digest", 0, "user");
+        let event = raw.clone().decode().unwrap().event;
+        let profile_id = apply_event(&repo, &raw, event.clone()).await.unwrap().unwrap();
+        assert!(apply_event(&repo, &raw, event).await.unwrap() == Some(profile_id));
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_is_rolled_back_and_dead_lettered() {
+        let repo = InMemoryRepo::new();
+        let mut too_long = get_raw_create_profile_event("digest", 1, "user");
+        too_long.parsed_json["user_name"] = "x".repeat(51).into();
+        let mut source = VecEventSource::new(vec![get_raw_create_profile_event("digest", 0, "user"), too_long]);
+
+        let summary = run_ingestion(&mut source, &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+
+        assert!(summary == IngestSummary { applied: 1, failed: 1, held: 0 });
+        assert!(repo.query_profile_by_user_name(&"x".repeat(51)).await.unwrap().is_none());
+        let cursor = load_cursor(&repo, SUBSCRIPTION).await.unwrap().unwrap();
+        assert!(cursor.event_seq == 1);
+        let failed_events = repo.query_failed_events(None, 10).await.unwrap();
+        assert!(failed_events.len() == 1);
+        assert!(failed_events[0].event_seq == 1 && failed_events[0].subscription == SUBSCRIPTION);
+        assert!(failed_events[0].next_retry_at.is_some());
+        let payload: RawChainEvent = serde_json::from_str(&failed_events[0].payload).unwrap();
+        assert!(payload.parsed_json["user_name"] == "x".repeat(51));
+    }
+
+    #[tokio::test]
+    async fn test_ingestion_stops_when_dead_letter_fails() {
+        let repo = InMemoryRepo::new();
+        // The repository rejects the digest of the second event, for its cursor as well as its failed event row
+        let undeliverable = get_raw_create_profile_event(&"x".repeat(101), 0, "user");
+        let mut source = VecEventSource::new(vec![
+            get_raw_create_profile_event("digest", 0, "user"),
+            undeliverable.clone(),
+            get_raw_create_profile_event("digest", 2, "user"),
+        ]);
+
+        let result = run_ingestion(&mut source, &repo, SUBSCRIPTION, &RetryPolicy::default()).await;
+
+        assert!(matches!(result, Err(IngestError::DeadLetter { id, .. }) if id == undeliverable.id));
+        let cursor = load_cursor(&repo, SUBSCRIPTION).await.unwrap().unwrap();
+        assert!(cursor == ChainEventId { tx_digest: "digest".to_string(), event_seq: 0 }, "a restart receives the event again");
+        assert!(repo.query_profile_by_user_name("user2").await.unwrap().is_none());
+        assert!(repo.query_failed_events(None, 10).await.unwrap().is_empty());
+    }
+
+    /// dave posts, jill responds to and shares the post, then follows and unfollows dave
+    fn get_social_events() -> Vec<RawChainEvent> {
+        let dave = get_object_id("dave0");
+        let jill = get_object_id("jill1");
+        let post = get_object_id("post");
+        vec![
+            get_raw_create_profile_event("dave", 0, "dave"),
+            get_raw_create_profile_event("jill", 1, "jill"),
+            get_raw_event("post", 0, CREATE_POST_EVENT, json!({
+                "post_id": post, "profile_id": dave, "message": "hello", "image": "https://images.dechat.dev/post.png"
+            })),
+            get_raw_event("reply", 0, CREATE_RESPONSE_POST_EVENT, json!({
+                "post_id": get_object_id("reply"), "profile_id": jill, "message": "hi dave", "respondee_post_id": post
+            })),
+            get_raw_event("share", 0, CREATE_SHARE_POST_EVENT, json!({
+                "post_id": get_object_id("share"), "profile_id": jill, "message": "", "sharee_post_id": post
+            })),
+            get_raw_event("follow", 0, FOLLOW_EVENT, json!({ "follower_profile_id": jill, "following_profile_id": dave })),
+            get_raw_event("update", 0, UPDATE_PROFILE_EVENT, json!({
+                "profile_id": dave, "full_name": "Dave Choi", "description": "I cook", "main_url": "https://dave.dev"
+            })),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_social_events_resolve_chain_assets() {
+        let repo = InMemoryRepo::new();
+        let mut source = VecEventSource::new(get_social_events());
+
+        let summary = run_ingestion(&mut source, &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+
+        assert!(summary == IngestSummary { applied: 7, failed: 0, held: 0 });
+        let dave = repo.query_profile_by_user_name("dave0").await.unwrap().unwrap();
+        let jill = repo.query_profile_by_user_name("jill1").await.unwrap().unwrap();
+        assert!(dave.description == "I cook" && dave.main_url == Some("https://dave.dev".to_string()));
+        assert!(repo.query_is_following(jill.id, dave.id).await.unwrap());
+        let post_id = repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &get_object_id("post")).await.unwrap().unwrap();
+        let post = repo.query_post(post_id).await.unwrap().unwrap();
+        assert!(post.user_id == dave.id);
+        assert!(post.image.is_none(), "image urls are not image data");
+        let reply_id = repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &get_object_id("reply")).await.unwrap().unwrap();
+        assert!(repo.query_post(reply_id).await.unwrap().unwrap().respondee_post_id == Some(post_id));
+        let share_id = repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &get_object_id("share")).await.unwrap().unwrap();
+        assert!(repo.query_shared_post(share_id).await.unwrap().unwrap().sharee_post_id == Some(post_id));
+
+        let unfollow = get_raw_event("unfollow", 0, UNFOLLOW_EVENT, json!({
+            "follower_profile_id": get_object_id("jill1"), "following_profile_id": get_object_id("dave0")
+        }));
+        let summary = run_ingestion(&mut VecEventSource::new(vec![unfollow]), &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+        assert!(summary.applied == 1);
+        assert!(!repo.query_is_following(jill.id, dave.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_events_are_held_until_their_references_exist() {
+        let repo = InMemoryRepo::new();
+        let mut events = get_social_events();
+        events.reverse();
+        let mut source = VecEventSource::new(events);
+
+        let summary = run_ingestion(&mut source, &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+
+        assert!(summary == IngestSummary { applied: 7, failed: 0, held: 0 });
+        assert!(repo.query_failed_events(None, 10).await.unwrap().is_empty());
+        let dave = repo.query_profile_by_user_name("dave0").await.unwrap().unwrap();
+        let jill = repo.query_profile_by_user_name("jill1").await.unwrap().unwrap();
+        assert!(dave.description == "I cook");
+        assert!(repo.query_is_following(jill.id, dave.id).await.unwrap());
+        assert!(repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &get_object_id("reply")).await.unwrap().is_some());
+        let cursor = load_cursor(&repo, SUBSCRIPTION).await.unwrap().unwrap();
+        assert!(cursor.tx_digest == "dave");
+    }
+
+    #[tokio::test]
+    async fn test_event_without_reference_stays_held() {
+        let repo = InMemoryRepo::new();
+        let post = get_raw_event("post", 0, CREATE_POST_EVENT, json!({
+            "post_id": get_object_id("post"), "profile_id": get_object_id("nobody"), "message": "hello"
+        }));
+
+        let summary = run_ingestion(&mut VecEventSource::new(vec![post]), &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+
+        assert!(summary == IngestSummary { applied: 0, failed: 0, held: 1 });
+        let failed_events = repo.query_failed_events(None, 10).await.unwrap();
+        assert!(failed_events.len() == 1 && failed_events[0].next_retry_at.is_some());
+        assert!(failed_events[0].error == format!("waiting for chain asset {}", get_object_id("nobody")));
+    }
+
+    #[tokio::test]
+    async fn test_aptos_and_sui_events_coexist() {
+        let repo = InMemoryRepo::new();
+        let aptos_chain_id = repo.query_chain_id_by_name(APTOS_CHAIN).await.unwrap().unwrap();
+        let mut source = VecEventSource::new(get_aptos_events());
+
+        let summary = run_ingestion(&mut source, &repo, "aptos", &RetryPolicy::default()).await.unwrap();
+        assert!(summary == IngestSummary { applied: 4, failed: 0, held: 0 });
+        let mut source = ReplayEventSource::open(SUI_EVENTS_FIXTURE).await.unwrap();
+        _ = run_ingestion(&mut source, &repo, "sui", &RetryPolicy::default()).await.unwrap();
+
+        let alice = repo.query_profile_by_user_name("alice").await.unwrap().unwrap();
+        let bob = repo.query_profile_by_user_name("bob").await.unwrap().unwrap();
+        let jill = repo.query_profile_by_user_name("jill").await.unwrap().unwrap();
+        assert!(alice.chain_id == aptos_chain_id && bob.chain_id == aptos_chain_id);
+        assert!(alice.owner_address.is_none());
+        assert!(jill.chain_id == SUI_CHAIN_ID);
+        assert!(repo.query_is_following(bob.id, alice.id).await.unwrap());
+        let alice_post_id = get_aptos_events()[2].parsed_json["post_id"].as_str().unwrap().to_string();
+        let post_id = repo.query_post_id_by_chain_asset(aptos_chain_id, &alice_post_id).await.unwrap().unwrap();
+        assert!(repo.query_post(post_id).await.unwrap().unwrap().user_id == alice.id);
+        assert!(repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &alice_post_id).await.unwrap().is_none());
+        let cursor = load_cursor(&repo, "aptos").await.unwrap().unwrap();
+        assert!(cursor == ChainEventId { tx_digest: "1180351907".to_string(), event_seq: 2 });
+
+        // the same chain asset id on another chain is another profile
+        let mut sui_alice = get_aptos_events().remove(0);
+        sui_alice.chain = SUI_CHAIN.to_string();
+        sui_alice.parsed_json["user_name"] = "alice_sui".into();
+        let sui_alice_id = apply_event(&repo, &sui_alice, sui_alice.clone().decode().unwrap().event).await.unwrap().unwrap();
+        assert!(sui_alice_id != alice.id);
+    }
+
+    #[tokio::test]
+    async fn test_event_of_unknown_chain_is_parked() {
+        let repo = InMemoryRepo::new();
+        let mut event = get_raw_create_profile_event("digest", 0, "user");
+        event.chain = "solana".to_string();
+
+        let summary = run_ingestion(&mut VecEventSource::new(vec![event]), &repo, SUBSCRIPTION, &RetryPolicy::default()).await.unwrap();
+
+        assert!(summary == IngestSummary { applied: 0, failed: 1, held: 0 });
+        assert!(repo.query_profile_by_user_name("user0").await.unwrap().is_none());
+        let failed_events = repo.query_failed_events(None, 10).await.unwrap();
+        assert!(failed_events.len() == 1 && failed_events[0].next_retry_at.is_none());
+        assert!(failed_events[0].error == "unknown chain solana");
+    }
+}
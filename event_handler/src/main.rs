@@ -1,8 +1,133 @@
+pub mod admin;
+pub mod aptos {
+    pub mod aptos;
+    pub mod client;
+}
+pub mod config;
+pub mod dead_letter;
+pub mod events;
+pub mod ingest;
+pub mod source {
+    pub mod catch_up;
+    pub mod polling;
+    pub mod replay;
+    pub mod source;
+    pub mod supervisor;
+}
 pub mod sui {
     pub mod sui;
     pub mod wallet;
 }
+pub mod test_helpers {
+    pub mod fixtures;
+}
+
+use std::env;
+use std::time::Duration;
+use admin::{run_failed_event_command, FAILED_EVENT_USAGE};
+use anyhow::anyhow;
+use aptos::aptos::normalize_address;
+use aptos::client::AptosPollerFactory;
+use config::{AptosConfig, EventHandlerConfig};
+use dead_letter::{run_retry_worker, RetryPolicy};
+use dotenv::dotenv;
+use ingest::{load_cursor, run_ingestion};
+use log::{error, info};
+use repository::repo::base::DbRepo;
+use source::replay::ReplayEventSource;
+use source::supervisor::{ReconnectPolicy, SupervisedEventSource};
+use sui::sui::SuiSubscriptionFactory;
+use sui::wallet::{run_wallet_command, WALLET_USAGE};
+use sui_sdk::types::base_types::ObjectID;
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+const CONNECTION_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Without arguments subscribes to the sui network from EventHandlerConfig, polls aptos too when it is configured,
+/// and retries failed events in the background.
+/// Pass a jsonl file of recorded events as the only argument to replay it instead,
+/// `failed list|replay <id>|discard <id>` to manage dead lettered events,
+/// or `wallet generate|show` to create or check a keystore key, the service itself never writes to a wallet
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    dotenv().ok();
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let ["wallet", command @ ..] = args.as_slice() {
+        return run_wallet_command(command);
+    }
+
+    let db_repo = DbRepo::init().await;
+    match args.as_slice() {
+        ["failed", command @ ..] => return run_failed_event_command(&db_repo, command).await,
+        [replay_path] => {
+            let mut source = ReplayEventSource::open(replay_path).await?;
+            let subscription = format!("replay:{}", replay_path);
+            let summary = run_ingestion(&mut source, &db_repo, &subscription, &RetryPolicy::default()).await?;
+            info!("replay finished, {} applied, {} failed, {} held", summary.applied, summary.failed, summary.held);
+            return Ok(());
+        },
+        [] => (),
+        _ => return Err(anyhow!("usage: event_handler [replay_path]\n{}\n{}", FAILED_EVENT_USAGE, WALLET_USAGE)),
+    }
+
+    let config = EventHandlerConfig::load()?;
+    let package = ObjectID::from_hex_literal(&config.package_address)?;
+    let subscription = format!("sui:{}::{}", package, config.module);
+    let cursor = load_cursor(&db_repo, &subscription).await?;
+    info!("starting {} on {} ({}) after {:?}", subscription, config.env_alias(), config.rpc_url(), cursor);
+
+    tokio::spawn(run_retry_worker(db_repo.clone(), RetryPolicy::default(), RETRY_INTERVAL));
+    if let Some(aptos_config) = config.aptos.clone() {
+        let aptos_subscription = format!("aptos:{}::{}", normalize_address(&aptos_config.package_address)?, aptos_config.module);
+        tokio::spawn(run_aptos_ingestion(db_repo.clone(), aptos_config, aptos_subscription));
+    }
+    let factory = SuiSubscriptionFactory::new(config)?;
+    let mut source = SupervisedEventSource::new(factory, ReconnectPolicy::default(), cursor);
+    let metrics = source.metrics();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(CONNECTION_REPORT_INTERVAL).await;
+            info!("sui connection: {}", metrics);
+        }
+    });
+    run_ingestion(&mut source, &db_repo, &subscription, &RetryPolicy::default()).await?;
+
+    Err(anyhow!("supervised sui event subscription ended"))
+}
+
+/// Follows the aptos package alongside sui, its events go through the same pipeline under their own cursor.
+/// Unlike sui it runs beside the main task, so instead of ending the process it starts over from the persisted
+/// cursor with backoff whenever the cursor cannot be loaded or ingestion stops
+async fn run_aptos_ingestion(db_repo: DbRepo, config: AptosConfig, subscription: String) {
+    let policy = ReconnectPolicy::default();
+    let mut failures = 0;
+    loop {
+        match load_cursor(&db_repo, &subscription).await {
+            Ok(cursor) => {
+                info!("starting {} on {} after {:?}", subscription, config.graphql_url, cursor);
+                let mut source = SupervisedEventSource::new(AptosPollerFactory::new(config.clone()), policy.clone(), cursor);
+                let metrics = source.metrics();
+                let report = tokio::spawn(async move {
+                    loop {
+                        tokio::time::sleep(CONNECTION_REPORT_INTERVAL).await;
+                        info!("aptos connection: {}", metrics);
+                    }
+                });
+                match run_ingestion(&mut source, &db_repo, &subscription, &RetryPolicy::default()).await {
+                    Ok(_) => error!("supervised aptos event polling ended"),
+                    Err(e) => error!("aptos ingestion stopped: {}", e),
+                }
+                report.abort();
+            },
+            Err(e) => error!("failed to load the cursor of {}: {}", subscription, e),
+        }
 
-fn main() {
-    println!("Hello, world!");
+        failures += 1;
+        let delay = policy.delay(failures);
+        info!("restarting {} in {:?}", subscription, delay);
+        tokio::time::sleep(delay).await;
+    }
 }
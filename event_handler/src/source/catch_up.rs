@@ -0,0 +1,167 @@
+use crate::source::source::{ChainEvent, ChainEventId, ChainEventSource, EventSourceError, RawChainEvent};
+use async_trait::async_trait;
+use std::collections::{HashSet, VecDeque};
+
+pub struct EventPage {
+    pub events: Vec<RawChainEvent>,
+    pub next_cursor: Option<ChainEventId>,
+    pub has_next_page: bool,
+}
+
+/// Paginated history of the same events a live source delivers
+#[async_trait]
+pub trait EventPageFetcher {
+    /// Events strictly after the cursor in chain order, starting at the first event when the cursor is None
+    async fn fetch_page(&self, cursor: Option<ChainEventId>) -> Result<EventPage, anyhow::Error>;
+}
+
+/// Backfills everything after a cursor page by page, then switches to the live source.
+/// The live source must be subscribed before the backfill starts so nothing emitted in between is missed,
+/// live events that the backfill already returned are dropped until the first new one arrives.
+/// A failed page fetch ends the source, restarting from the persisted cursor picks up where it stopped
+pub struct CatchUpEventSource<F, L> {
+    fetcher: F,
+    live: L,
+    cursor: Option<ChainEventId>,
+    pending: VecDeque<RawChainEvent>,
+    backfilling: bool,
+    failed: bool,
+    backfilled_ids: HashSet<ChainEventId>,
+}
+
+impl<F: EventPageFetcher, L: ChainEventSource> CatchUpEventSource<F, L> {
+    pub fn new(fetcher: F, live: L, cursor: Option<ChainEventId>) -> Self {
+        Self {
+            fetcher,
+            live,
+            cursor,
+            pending: VecDeque::new(),
+            backfilling: true,
+            failed: false,
+            backfilled_ids: HashSet::new(),
+        }
+    }
+
+    fn is_backfilled(&self, next: &Result<ChainEvent, EventSourceError>) -> Option<bool> {
+        let id = match next {
+            Ok(event) => &event.raw.id,
+            Err(EventSourceError::Decode { raw, .. }) => &raw.id,
+            Err(EventSourceError::Transport(_)) => return None,
+        };
+        Some(self.backfilled_ids.contains(id))
+    }
+}
+
+#[async_trait]
+impl<F: EventPageFetcher + Send + Sync, L: ChainEventSource + Send> ChainEventSource for CatchUpEventSource<F, L> {
+    async fn next_event(&mut self) -> Option<Result<ChainEvent, EventSourceError>> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if let Some(raw) = self.pending.pop_front() {
+                self.backfilled_ids.insert(raw.id.clone());
+                return Some(raw.decode());
+            }
+            if !self.backfilling {
+                break;
+            }
+
+            match self.fetcher.fetch_page(self.cursor.clone()).await {
+                Ok(page) => {
+                    if let Some(cursor) = page.next_cursor.or_else(|| page.events.last().map(|raw| raw.id.clone())) {
+                        self.cursor = Some(cursor);
+                    }
+                    self.backfilling = page.has_next_page;
+                    self.pending.extend(page.events);
+                },
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(EventSourceError::Transport(e)));
+                },
+            }
+        }
+
+        loop {
+            let next = self.live.next_event().await?;
+            match self.is_backfilled(&next) {
+                Some(true) => continue,
+                Some(false) => self.backfilled_ids.clear(),
+                None => (),
+            }
+            return Some(next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::fixtures::{get_raw_create_profile_event, VecEventSource};
+    use std::sync::Mutex;
+
+    /// Serves the given events two per page
+    struct FakePageFetcher {
+        events: Vec<RawChainEvent>,
+        requested_cursors: Mutex<Vec<Option<ChainEventId>>>,
+    }
+
+    #[async_trait]
+    impl EventPageFetcher for FakePageFetcher {
+        async fn fetch_page(&self, cursor: Option<ChainEventId>) -> Result<EventPage, anyhow::Error> {
+            self.requested_cursors.lock().unwrap().push(cursor.clone());
+            let start = match cursor {
+                Some(cursor) => self.events.iter().position(|raw| raw.id == cursor).unwrap() + 1,
+                None => 0,
+            };
+            let events: Vec<RawChainEvent> = self.events.iter().skip(start).take(2).cloned().collect();
+
+            Ok(EventPage {
+                next_cursor: events.last().map(|raw| raw.id.clone()),
+                has_next_page: start + events.len() < self.events.len(),
+                events,
+            })
+        }
+    }
+
+    struct FailingPageFetcher;
+
+    #[async_trait]
+    impl EventPageFetcher for FailingPageFetcher {
+        async fn fetch_page(&self, _cursor: Option<ChainEventId>) -> Result<EventPage, anyhow::Error> {
+            Err(anyhow::anyhow!("rpc unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backfill_then_live_without_gaps_or_duplicates() {
+        let history: Vec<RawChainEvent> = (0..5).map(|seq| get_raw_create_profile_event("digest", seq, "user")).collect();
+        let fetcher = FakePageFetcher { events: history.clone(), requested_cursors: Mutex::new(vec![]) };
+        // the live subscription started while events 3 and 4 were emitted, so they show up in both
+        let live = VecEventSource::new(vec![
+            history[3].clone(),
+            history[4].clone(),
+            get_raw_create_profile_event("digest", 5, "user"),
+        ]);
+        let mut source = CatchUpEventSource::new(fetcher, live, Some(history[0].id.clone()));
+
+        let mut event_seqs = vec![];
+        while let Some(event) = source.next_event().await {
+            event_seqs.push(event.unwrap().raw.id.event_seq);
+        }
+
+        assert!(event_seqs == vec![1, 2, 3, 4, 5]);
+        let requested_cursors = source.fetcher.requested_cursors.lock().unwrap().clone();
+        assert!(requested_cursors == vec![Some(history[0].id.clone()), Some(history[2].id.clone())]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_backfill_ends_source() {
+        let live = VecEventSource::new(vec![get_raw_create_profile_event("digest", 0, "user")]);
+        let mut source = CatchUpEventSource::new(FailingPageFetcher, live, None);
+
+        assert!(matches!(source.next_event().await, Some(Err(EventSourceError::Transport(_)))));
+        assert!(source.next_event().await.is_none());
+    }
+}
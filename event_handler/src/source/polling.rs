@@ -0,0 +1,105 @@
+use crate::source::catch_up::EventPageFetcher;
+use crate::source::source::{ChainEvent, ChainEventId, ChainEventSource, EventSourceError, RawChainEvent};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// A live source for chains without event subscriptions. Pages after the cursor are fetched until one comes
+/// back empty, then the next fetch waits for the interval. A failed page fetch ends the source like a dropped
+/// subscription, so a supervisor reconnects from the last event handed out
+pub struct PollingEventSource<F> {
+    fetcher: F,
+    cursor: Option<ChainEventId>,
+    pending: VecDeque<RawChainEvent>,
+    interval: Duration,
+    failed: bool,
+}
+
+impl<F: EventPageFetcher> PollingEventSource<F> {
+    pub fn new(fetcher: F, cursor: Option<ChainEventId>, interval: Duration) -> Self {
+        Self { fetcher, cursor, pending: VecDeque::new(), interval, failed: false }
+    }
+}
+
+#[async_trait]
+impl<F: EventPageFetcher + Send + Sync> ChainEventSource for PollingEventSource<F> {
+    async fn next_event(&mut self) -> Option<Result<ChainEvent, EventSourceError>> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if let Some(raw) = self.pending.pop_front() {
+                return Some(raw.decode());
+            }
+
+            match self.fetcher.fetch_page(self.cursor.clone()).await {
+                Ok(page) => {
+                    if let Some(cursor) = page.next_cursor.or_else(|| page.events.last().map(|raw| raw.id.clone())) {
+                        self.cursor = Some(cursor);
+                    }
+                    if page.events.is_empty() {
+                        tokio::time::sleep(self.interval).await;
+                    }
+                    self.pending.extend(page.events);
+                },
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(EventSourceError::Transport(e)));
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::catch_up::EventPage;
+    use crate::test_helpers::fixtures::get_raw_create_profile_event;
+    use std::sync::Mutex;
+
+    /// Each fetch pops the next scripted page, Err for a failed request
+    struct ScriptedPageFetcher {
+        pages: Mutex<VecDeque<Result<Vec<RawChainEvent>, String>>>,
+        requested_cursors: Mutex<Vec<Option<ChainEventId>>>,
+    }
+
+    #[async_trait]
+    impl EventPageFetcher for ScriptedPageFetcher {
+        async fn fetch_page(&self, cursor: Option<ChainEventId>) -> Result<EventPage, anyhow::Error> {
+            self.requested_cursors.lock().unwrap().push(cursor);
+            match self.pages.lock().unwrap().pop_front() {
+                Some(Ok(events)) => Ok(EventPage { next_cursor: None, has_next_page: false, events }),
+                Some(Err(e)) => Err(anyhow::anyhow!(e)),
+                None => Err(anyhow::anyhow!("no more pages")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_polls_after_cursor_until_fetch_fails() {
+        let event = |seq| get_raw_create_profile_event("digest", seq, "user");
+        let fetcher = ScriptedPageFetcher {
+            pages: Mutex::new(VecDeque::from(vec![
+                Ok(vec![event(1), event(2)]),
+                Ok(vec![]),
+                Ok(vec![event(3)]),
+                Err("indexer unavailable".to_string()),
+            ])),
+            requested_cursors: Mutex::new(vec![]),
+        };
+        let mut source = PollingEventSource::new(fetcher, Some(event(0).id), Duration::from_millis(1));
+
+        let mut event_seqs = vec![];
+        for _ in 0..3 {
+            event_seqs.push(source.next_event().await.unwrap().unwrap().raw.id.event_seq);
+        }
+
+        assert!(event_seqs == vec![1, 2, 3]);
+        assert!(matches!(source.next_event().await, Some(Err(EventSourceError::Transport(_)))));
+        assert!(source.next_event().await.is_none());
+        let requested_cursors = source.fetcher.requested_cursors.lock().unwrap().clone();
+        assert!(requested_cursors == vec![Some(event(0).id), Some(event(2).id), Some(event(2).id), Some(event(3).id)]);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::source::source::{ChainEvent, ChainEventSource, EventSourceError, RawChainEvent};
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+
+/// Replays recorded events from a jsonl file, one RawChainEvent per line. Blank lines are skipped
+pub struct ReplayEventSource {
+    lines: Lines<BufReader<File>>,
+    line_number: usize,
+}
+
+impl ReplayEventSource {
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let file = File::open(path).await?;
+
+        Ok(Self { lines: BufReader::new(file).lines(), line_number: 0 })
+    }
+}
+
+#[async_trait]
+impl ChainEventSource for ReplayEventSource {
+    async fn next_event(&mut self) -> Option<Result<ChainEvent, EventSourceError>> {
+        loop {
+            let line = match self.lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(EventSourceError::Transport(e.into()))),
+            };
+            self.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            return Some(match serde_json::from_str::<RawChainEvent>(&line) {
+                Ok(raw) => raw.decode(),
+                Err(e) => Err(EventSourceError::Transport(
+                    anyhow::anyhow!("line {} is not a recorded event: {}", self.line_number, e)
+                )),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DechatEvent;
+    use crate::source::source::ChainEventId;
+    use crate::test_helpers::fixtures::SUI_EVENTS_FIXTURE;
+
+    #[tokio::test]
+    async fn test_replay_recorded_events() {
+        let mut source = ReplayEventSource::open(SUI_EVENTS_FIXTURE).await.unwrap();
+
+        let first = source.next_event().await.unwrap().unwrap();
+        assert!(first.raw.id == ChainEventId {
+            tx_digest: "8ZJ7aV1HzXHnyNSH7TjmyGsQtdCzKpZbMWAaiZcHGpTN".to_string(),
+            event_seq: 0
+        });
+        assert!(matches!(first.event, DechatEvent::CreateProfile(profile) if profile.user_name == "jill"));
+
+        let mut decoded = 1;
+        let mut failed = 0;
+        while let Some(event) = source.next_event().await {
+            match event {
+                Ok(_) => decoded += 1,
+                Err(EventSourceError::Decode { .. }) => failed += 1,
+                Err(e) => panic!("{}", e),
+            }
+        }
+        assert!(decoded == 2);
+        assert!(failed == 2);
+    }
+
+    #[tokio::test]
+    async fn test_replay_missing_file() {
+        assert!(ReplayEventSource::open("does/not/exist.jsonl").await.is_err());
+    }
+}
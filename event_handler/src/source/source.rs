@@ -0,0 +1,110 @@
+use crate::events::{DechatEvent, DecodeError};
+use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+pub const SUI_CHAIN: &str = "sui";
+pub const APTOS_CHAIN: &str = "aptos";
+
+/// Position of an event on its chain, for sui this is the transaction digest and the event's index in it,
+/// for aptos the transaction version and the event's index in that transaction
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainEventId {
+    pub tx_digest: String,
+    #[serde(serialize_with = "serialize_u64_as_string", deserialize_with = "deserialize_u64_from_string_or_number")]
+    pub event_seq: u64,
+}
+
+/// An event as received from the chain before decoding. The field names follow the sui json rpc
+/// event format so recorded sui events can be replayed as they are, any other fields are ignored.
+/// bcs is empty when the source only has parsed_json. chain is the name of the chain's row in the chain table,
+/// recorded sui events have none and default to sui. sender is the address that signed the transaction, when known
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RawChainEvent {
+    #[serde(default = "default_chain")]
+    pub chain: String,
+    pub id: ChainEventId,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub parsed_json: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    #[serde(default, serialize_with = "serialize_base58", deserialize_with = "deserialize_base58")]
+    pub bcs: Vec<u8>,
+}
+
+impl RawChainEvent {
+    #[allow(clippy::result_large_err)]
+    pub fn decode(self) -> Result<ChainEvent, EventSourceError> {
+        match DechatEvent::decode(&self.event_type, &self.parsed_json, &self.bcs) {
+            Ok(event) => Ok(ChainEvent { raw: self, event }),
+            Err(error) => Err(EventSourceError::Decode { raw: Box::new(self), error }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainEvent {
+    pub raw: RawChainEvent,
+    pub event: DechatEvent,
+}
+
+#[derive(Debug)]
+pub enum EventSourceError {
+    /// The event was received but is not a dechat event we can decode, the raw event is kept for inspection
+    Decode { raw: Box<RawChainEvent>, error: DecodeError },
+    /// Connection, rpc or file errors where no event could be read
+    Transport(anyhow::Error),
+}
+
+impl std::error::Error for EventSourceError {}
+impl std::fmt::Display for EventSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Decode { raw, error } => write!(f, "failed to decode event {:?}: {}", raw.id, error),
+            Self::Transport(e) => write!(f, "event source error: {}", e),
+        }
+    }
+}
+
+/// Yields decoded dechat events one at a time in chain order
+#[async_trait]
+pub trait ChainEventSource {
+    /// None once the source is exhausted, live sources only end when their connection does
+    async fn next_event(&mut self) -> Option<Result<ChainEvent, EventSourceError>>;
+}
+
+fn default_chain() -> String {
+    SUI_CHAIN.to_string()
+}
+
+fn serialize_u64_as_string<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&value.to_string())
+}
+
+/// Sui and the aptos indexer render u64 values as json strings so they survive javascript clients
+pub(crate) fn deserialize_u64_from_string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(u64),
+    }
+
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(value) => value.parse().map_err(serde::de::Error::custom),
+        StringOrNumber::Number(value) => Ok(value),
+    }
+}
+
+fn serialize_base58<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&bs58::encode(value).into_string())
+}
+
+/// Sui renders event bcs as base58
+fn deserialize_base58<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    bs58::decode(value).into_vec().map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,270 @@
+use crate::source::source::{ChainEvent, ChainEventId, ChainEventSource, EventSourceError};
+use async_trait::async_trait;
+use log::{info, warn};
+use rand::Rng;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Opens a new connection to the chain that delivers events strictly after the cursor
+#[async_trait]
+pub trait EventSourceFactory {
+    type Source: ChainEventSource + Send;
+
+    async fn connect(&self, cursor: Option<ChainEventId>) -> Result<Self::Source, anyhow::Error>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self { base_delay: Duration::from_millis(500), max_delay: Duration::from_secs(60) }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Doubles with every consecutive failure up to max_delay, then picks a random point in the upper
+    /// half so many handlers losing the same node do not reconnect in lockstep
+    pub fn delay(&self, failures: u32) -> Duration {
+        let doubled = self.base_delay.saturating_mul(2_u32.saturating_pow(failures.saturating_sub(1)));
+        let delay = doubled.min(self.max_delay);
+        delay / 2 + delay.mul_f64(rand::thread_rng().gen_range(0.0..=0.5))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConnectionState {
+    Connecting = 0,
+    Connected = 1,
+    WaitingToReconnect = 2,
+}
+
+/// Counters describing the supervised connection, share the Arc to read them from elsewhere
+#[derive(Debug)]
+pub struct ConnectionMetrics {
+    state: AtomicU8,
+    pub connects: AtomicU64,
+    pub failed_connects: AtomicU64,
+    pub disconnects: AtomicU64,
+    pub events: AtomicU64,
+}
+
+impl Default for ConnectionMetrics {
+    fn default() -> Self {
+        Self {
+            state: AtomicU8::new(ConnectionState::Connecting as u8),
+            connects: AtomicU64::new(0),
+            failed_connects: AtomicU64::new(0),
+            disconnects: AtomicU64::new(0),
+            events: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ConnectionMetrics {
+    pub fn state(&self) -> ConnectionState {
+        match self.state.load(Ordering::Relaxed) {
+            1 => ConnectionState::Connected,
+            2 => ConnectionState::WaitingToReconnect,
+            _ => ConnectionState::Connecting,
+        }
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+}
+
+impl std::fmt::Display for ConnectionMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}, {} connects, {} failed connects, {} disconnects, {} events",
+            self.state(),
+            self.connects.load(Ordering::Relaxed),
+            self.failed_connects.load(Ordering::Relaxed),
+            self.disconnects.load(Ordering::Relaxed),
+            self.events.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// Keeps a live source running. When the connection fails or its stream ends a new one is opened after a
+/// backoff, resuming after the last event handed out, so the supervised source itself never ends.
+/// Decode errors are events and are passed on, transport errors only trigger a reconnect
+pub struct SupervisedEventSource<F: EventSourceFactory> {
+    factory: F,
+    policy: ReconnectPolicy,
+    metrics: Arc<ConnectionMetrics>,
+    current: Option<F::Source>,
+    cursor: Option<ChainEventId>,
+    failures: u32,
+}
+
+impl<F: EventSourceFactory> SupervisedEventSource<F> {
+    pub fn new(factory: F, policy: ReconnectPolicy, cursor: Option<ChainEventId>) -> Self {
+        Self {
+            factory,
+            policy,
+            metrics: Arc::new(ConnectionMetrics::default()),
+            current: None,
+            cursor,
+            failures: 0,
+        }
+    }
+
+    pub fn metrics(&self) -> Arc<ConnectionMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Opens a connection if there is none, false when connecting failed and the backoff has been waited out
+    async fn ensure_connected(&mut self) -> bool {
+        if self.current.is_some() {
+            return true;
+        }
+
+        self.metrics.set_state(ConnectionState::Connecting);
+        match self.factory.connect(self.cursor.clone()).await {
+            Ok(source) => {
+                self.current = Some(source);
+                self.metrics.connects.fetch_add(1, Ordering::Relaxed);
+                self.metrics.set_state(ConnectionState::Connected);
+                info!("connected, resuming after {:?}", self.cursor);
+                true
+            },
+            Err(e) => {
+                self.metrics.failed_connects.fetch_add(1, Ordering::Relaxed);
+                warn!("failed to connect: {}", e);
+                self.back_off().await;
+                false
+            },
+        }
+    }
+
+    async fn disconnect(&mut self) {
+        self.current = None;
+        self.metrics.disconnects.fetch_add(1, Ordering::Relaxed);
+        self.back_off().await;
+    }
+
+    async fn back_off(&mut self) {
+        self.failures += 1;
+        let delay = self.policy.delay(self.failures);
+        self.metrics.set_state(ConnectionState::WaitingToReconnect);
+        info!("reconnecting in {:?} after {} consecutive failures", delay, self.failures);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[async_trait]
+impl<F: EventSourceFactory + Send + Sync> ChainEventSource for SupervisedEventSource<F> {
+    async fn next_event(&mut self) -> Option<Result<ChainEvent, EventSourceError>> {
+        loop {
+            if !self.ensure_connected().await {
+                continue;
+            }
+            let next = match self.current.as_mut() {
+                Some(source) => source.next_event().await,
+                None => continue,
+            };
+
+            let id = match &next {
+                Some(Ok(event)) => event.raw.id.clone(),
+                Some(Err(EventSourceError::Decode { raw, .. })) => raw.id.clone(),
+                Some(Err(EventSourceError::Transport(e))) => {
+                    warn!("connection failed: {}", e);
+                    self.disconnect().await;
+                    continue;
+                },
+                None => {
+                    warn!("event stream ended");
+                    self.disconnect().await;
+                    continue;
+                },
+            };
+
+            self.failures = 0;
+            self.cursor = Some(id);
+            self.metrics.events.fetch_add(1, Ordering::Relaxed);
+            return next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::source::RawChainEvent;
+    use crate::test_helpers::fixtures::{get_raw_create_profile_event, VecEventSource};
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Each connect pops the next scripted connection, Err for a refused connect or the events it delivers
+    struct FakeSourceFactory {
+        connections: Mutex<VecDeque<Result<Vec<RawChainEvent>, String>>>,
+        cursors: Mutex<Vec<Option<ChainEventId>>>,
+    }
+
+    #[async_trait]
+    impl EventSourceFactory for FakeSourceFactory {
+        type Source = VecEventSource;
+
+        async fn connect(&self, cursor: Option<ChainEventId>) -> Result<Self::Source, anyhow::Error> {
+            self.cursors.lock().unwrap().push(cursor);
+            match self.connections.lock().unwrap().pop_front() {
+                Some(Ok(events)) => Ok(VecEventSource::new(events)),
+                Some(Err(e)) => Err(anyhow::anyhow!(e)),
+                None => Err(anyhow::anyhow!("connection refused")),
+            }
+        }
+    }
+
+    fn fast_policy() -> ReconnectPolicy {
+        ReconnectPolicy { base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(4) }
+    }
+
+    #[test]
+    fn test_delay_backs_off_with_jitter() {
+        let policy = ReconnectPolicy { base_delay: Duration::from_secs(1), max_delay: Duration::from_secs(8) };
+
+        for (failures, full_delay) in [(1, 1), (2, 2), (3, 4), (4, 8), (10, 8)] {
+            let delay = policy.delay(failures);
+            let full_delay = Duration::from_secs(full_delay);
+            assert!(delay >= full_delay / 2 && delay <= full_delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_and_resumes_after_last_event() {
+        let event = |seq| get_raw_create_profile_event("digest", seq, "user");
+        let factory = FakeSourceFactory {
+            connections: Mutex::new(VecDeque::from(vec![
+                Ok(vec![event(0), event(1)]),
+                Err("node unavailable".to_string()),
+                Ok(vec![event(2)]),
+            ])),
+            cursors: Mutex::new(vec![]),
+        };
+        let mut source = SupervisedEventSource::new(factory, fast_policy(), None);
+        let metrics = source.metrics();
+
+        let mut event_seqs = vec![];
+        for _ in 0..3 {
+            event_seqs.push(source.next_event().await.unwrap().unwrap().raw.id.event_seq);
+        }
+
+        assert!(event_seqs == vec![0, 1, 2]);
+        let cursors = source.factory.cursors.lock().unwrap().clone();
+        assert!(cursors == vec![None, Some(event(1).id), Some(event(1).id)]);
+        assert!(metrics.connects.load(Ordering::Relaxed) == 2);
+        assert!(metrics.failed_connects.load(Ordering::Relaxed) == 1);
+        assert!(metrics.disconnects.load(Ordering::Relaxed) == 1);
+        assert!(metrics.events.load(Ordering::Relaxed) == 3);
+        assert!(metrics.state() == ConnectionState::Connected);
+    }
+}
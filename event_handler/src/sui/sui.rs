@@ -1,54 +1,172 @@
+use crate::config::EventHandlerConfig;
+use crate::source::catch_up::{CatchUpEventSource, EventPage, EventPageFetcher};
+use crate::source::source::{ChainEvent, ChainEventId, ChainEventSource, EventSourceError, RawChainEvent, SUI_CHAIN};
+use crate::source::supervisor::EventSourceFactory;
+use async_trait::async_trait;
 use futures::stream::StreamExt;
-use move_core_types::account_address::AccountAddress;
+use log::info;
 use move_core_types::identifier::Identifier;
-use sui_sdk::rpc_types::EventFilter;
+use std::str::FromStr;
+use sui_sdk::rpc_types::{EventFilter, SuiEvent};
+use sui_sdk::types::base_types::ObjectID;
+use sui_sdk::types::digests::TransactionDigest;
+use sui_sdk::types::event::EventID;
 use sui_sdk::{SuiClient, SuiClientBuilder};
-use move_core_types::language_storage::{StructTag, TypeTag};
-
-#[allow(unused)]
-const DEVNET_URL: &str = "https://fullnode.devnet.sui.io:443";
-const TESTNET_URL: &str = "https://fullnode.testnet.sui.io:443";
-const TESTNET_WS_URL: &str = "wss://rpc.testnet.sui.io:443";
-
-#[allow(unused)]
-pub struct SuiEventHandler {
-    sui_client: SuiClient
-}
-
-#[allow(unused)]
-impl SuiEventHandler {
-    async fn init() -> Self {
-        SuiEventHandler { 
-            sui_client: SuiClientBuilder::default()
-                .ws_url(TESTNET_WS_URL)
-                .build(TESTNET_URL)
-                .await
-                .unwrap() 
-        }
+use tokio::sync::{mpsc, oneshot};
+
+/// How many received events may wait for the ingestion loop before the subscription task waits
+const EVENT_BUFFER_SIZE: usize = 256;
+const BACKFILL_PAGE_SIZE: usize = 50;
+
+pub async fn build_sui_client(config: &EventHandlerConfig) -> Result<SuiClient, anyhow::Error> {
+    Ok(SuiClientBuilder::default()
+        .ws_url(config.ws_url())
+        .build(config.rpc_url())
+        .await?)
+}
+
+/// Every event emitted by the configured dechat module
+pub fn build_event_filter(config: &EventHandlerConfig) -> Result<EventFilter, anyhow::Error> {
+    Ok(EventFilter::MoveModule {
+        package: ObjectID::from_hex_literal(&config.package_address)?,
+        module: Identifier::new(config.module.as_str())?,
+    })
+}
+
+/// Live sui events from a websocket subscription. The subscription runs on its own task and hands
+/// events over through a channel, the source ends when the subscription does
+pub struct SuiEventSource {
+    receiver: mpsc::Receiver<Result<SuiEvent, anyhow::Error>>,
+}
+
+impl SuiEventSource {
+    /// Returns once the subscription is established, so events emitted afterwards are never missed
+    pub async fn subscribe(sui_client: SuiClient, filter: EventFilter) -> Result<Self, anyhow::Error> {
+        let (sender, receiver) = mpsc::channel(EVENT_BUFFER_SIZE);
+        let (subscribed_sender, subscribed_receiver) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let subscription = match sui_client.event_api().subscribe_event(filter).await {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    _ = subscribed_sender.send(Err(anyhow::Error::from(e)));
+                    return;
+                }
+            };
+            futures::pin_mut!(subscription);
+            _ = subscribed_sender.send(Ok(()));
+
+            while let Some(event) = subscription.next().await {
+                if sender.send(event.map_err(anyhow::Error::from)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        subscribed_receiver.await??;
+        info!("subscribed to sui events");
+        Ok(Self { receiver })
+    }
+}
+
+pub fn to_raw_chain_event(event: SuiEvent) -> RawChainEvent {
+    RawChainEvent {
+        chain: SUI_CHAIN.to_string(),
+        id: to_chain_event_id(event.id),
+        event_type: event.type_.to_string(),
+        parsed_json: event.parsed_json,
+        sender: Some(event.sender.to_string()),
+        bcs: event.bcs,
+    }
+}
+
+pub fn to_chain_event_id(id: EventID) -> ChainEventId {
+    ChainEventId { tx_digest: id.tx_digest.to_string(), event_seq: id.event_seq }
+}
+
+pub fn to_event_id(id: ChainEventId) -> Result<EventID, anyhow::Error> {
+    Ok(EventID { tx_digest: TransactionDigest::from_str(&id.tx_digest)?, event_seq: id.event_seq })
+}
+
+/// Subscribes first and backfills everything after the cursor second, see CatchUpEventSource
+pub async fn subscribe_from(
+    sui_client: SuiClient,
+    filter: EventFilter,
+    cursor: Option<ChainEventId>
+) -> Result<CatchUpEventSource<SuiEventPageFetcher, SuiEventSource>, anyhow::Error> {
+    let live = SuiEventSource::subscribe(sui_client.clone(), filter.clone()).await?;
+
+    Ok(CatchUpEventSource::new(SuiEventPageFetcher { sui_client, filter }, live, cursor))
+}
+
+/// Opens a fresh client and subscription for every connect, a client whose websocket dropped is not reused
+pub struct SuiSubscriptionFactory {
+    config: EventHandlerConfig,
+    filter: EventFilter,
+}
+
+impl SuiSubscriptionFactory {
+    pub fn new(config: EventHandlerConfig) -> Result<Self, anyhow::Error> {
+        let filter = build_event_filter(&config)?;
+        Ok(Self { config, filter })
+    }
+}
+
+#[async_trait]
+impl EventSourceFactory for SuiSubscriptionFactory {
+    type Source = CatchUpEventSource<SuiEventPageFetcher, SuiEventSource>;
+
+    async fn connect(&self, cursor: Option<ChainEventId>) -> Result<Self::Source, anyhow::Error> {
+        let sui_client = build_sui_client(&self.config).await?;
+        subscribe_from(sui_client, self.filter.clone(), cursor).await
     }
+}
+
+pub struct SuiEventPageFetcher {
+    sui_client: SuiClient,
+    filter: EventFilter,
+}
+
+#[async_trait]
+impl EventPageFetcher for SuiEventPageFetcher {
+    async fn fetch_page(&self, cursor: Option<ChainEventId>) -> Result<EventPage, anyhow::Error> {
+        let cursor = cursor.map(to_event_id).transpose()?;
+        let page = self.sui_client.event_api()
+            .query_events(self.filter.clone(), cursor, Some(BACKFILL_PAGE_SIZE), false)
+            .await?;
+
+        Ok(EventPage {
+            events: page.data.into_iter().map(to_raw_chain_event).collect(),
+            next_cursor: page.next_cursor.map(to_chain_event_id),
+            has_next_page: page.has_next_page,
+        })
+    }
+}
+
+#[async_trait]
+impl ChainEventSource for SuiEventSource {
+    async fn next_event(&mut self) -> Option<Result<ChainEvent, EventSourceError>> {
+        let event = self.receiver.recv().await?;
 
-    async fn subscribe_event(&self, address: AccountAddress, module: Identifier, name: Identifier, type_params: Vec<TypeTag>) {       
-        let mut subscribe = self.sui_client.event_api().subscribe_event(EventFilter::MoveEventType(StructTag { address, module, name, type_params })).await.unwrap();
-        loop {
-            println!("{:?}", subscribe.next().await);
-        }
+        Some(match event {
+            Ok(event) => to_raw_chain_event(event).decode(),
+            Err(e) => Err(EventSourceError::Transport(e)),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::sui::wallet::retrieve_wallet;
     use super::*;
 
     #[tokio::test]
+    #[ignore = "subscribes to a live sui network set up by the env, the pipeline itself is covered by replay tests"]
     async fn test_subscribe_event() {
-        let client = SuiEventHandler::init().await;
-        let module = Identifier::new("dechat_sui").unwrap();
-        let name = Identifier::new("CreateProfileEvent").unwrap();
-        let type_params = vec![];
-        let mut wallet = retrieve_wallet().await.unwrap();
-        let active_address = wallet.active_address().unwrap();
-
-        client.subscribe_event(active_address.into(), module, name, type_params).await;
+        let config = EventHandlerConfig::load().unwrap();
+        let sui_client = build_sui_client(&config).await.unwrap();
+        let filter = build_event_filter(&config).unwrap();
+        let mut source = SuiEventSource::subscribe(sui_client, filter).await.unwrap();
+
+        println!("{:?}", source.next_event().await);
     }
-}
\ No newline at end of file
+}
@@ -1,57 +1,134 @@
-use sui_config::{
-    sui_config_dir, Config, PersistedConfig, SUI_CLIENT_CONFIG, SUI_KEYSTORE_FILENAME,
-};
+use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use sui_keys::keystore::{AccountKeystore, FileBasedKeystore};
-use sui_sdk::{
-    sui_client_config::{SuiClientConfig, SuiEnv},
-    types::crypto::SignatureScheme::ED25519,
-    wallet_context::WalletContext,
-};
+use sui_sdk::types::base_types::SuiAddress;
+use sui_sdk::types::crypto::{EncodeDecodeBase64, SignatureScheme::ED25519, SuiKeyPair};
 
-pub async fn retrieve_wallet() -> Result<WalletContext, anyhow::Error> {
-    let wallet_conf = sui_config_dir()?.join(SUI_CLIENT_CONFIG);
-    let keystore_path = sui_config_dir()?.join(SUI_KEYSTORE_FILENAME);
+pub const WALLET_USAGE: &str = "usage: event_handler wallet generate <keystore_path> | wallet show <keystore_path> <address>";
 
-    // check if a wallet exists and if not, create a wallet and a sui client config
-    if !keystore_path.exists() {
-        let keystore = FileBasedKeystore::new(&keystore_path)?;
-        keystore.save()?;
+/// One key of an operator's keystore, found without ever writing to the keystore or sui client config.
+/// Only its address is kept, the service never signs with it
+pub struct Wallet {
+    address: SuiAddress,
+}
+
+impl Wallet {
+    pub fn address(&self) -> SuiAddress {
+        self.address
+    }
+}
+
+#[derive(Debug)]
+pub enum WalletError {
+    KeystoreMissing(PathBuf),
+    InvalidKeystore { path: PathBuf, reason: String },
+    InvalidAddress(String),
+    AddressNotInKeystore { address: SuiAddress, path: PathBuf },
+}
+
+impl std::error::Error for WalletError {}
+impl std::fmt::Display for WalletError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KeystoreMissing(path) => write!(f, "no keystore at {}", path.display()),
+            Self::InvalidKeystore { path, reason } => write!(f, "keystore {} is invalid: {}", path.display(), reason),
+            Self::InvalidAddress(address) => write!(f, "{} is not a sui address", address),
+            Self::AddressNotInKeystore { address, path } => {
+                write!(f, "keystore {} has no key for {}", path.display(), address)
+            },
+        }
     }
+}
 
-    if !wallet_conf.exists() {
-        let keystore = FileBasedKeystore::new(&keystore_path)?;
-        let mut client_config = SuiClientConfig::new(keystore.into());
+/// Reads the key for address from a sui keystore file, a json array of base64 encoded keys.
+/// The file is parsed directly because opening it as a FileBasedKeystore may write an aliases file next to it
+pub fn load_wallet(keystore_path: &Path, address: &str) -> Result<Wallet, WalletError> {
+    let address = SuiAddress::from_str(address).map_err(|_| WalletError::InvalidAddress(address.to_string()))?;
+    let invalid_keystore = |reason: String| WalletError::InvalidKeystore { path: keystore_path.to_path_buf(), reason };
 
-        client_config.add_env(SuiEnv::testnet());
-        client_config.add_env(SuiEnv::devnet());
-        client_config.add_env(SuiEnv::localnet());
+    let contents = std::fs::read_to_string(keystore_path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => WalletError::KeystoreMissing(keystore_path.to_path_buf()),
+        _ => invalid_keystore(e.to_string()),
+    })?;
+    let encoded_keys: Vec<String> = serde_json::from_str(&contents).map_err(|e| invalid_keystore(e.to_string()))?;
 
-        if client_config.active_env.is_none() {
-            client_config.active_env = client_config.envs.first().map(|env| env.alias.clone());
+    for encoded_key in encoded_keys {
+        let keypair = SuiKeyPair::decode_base64(&encoded_key).map_err(|e| invalid_keystore(e.to_string()))?;
+        if SuiAddress::from(&keypair.public()) == address {
+            return Ok(Wallet { address });
         }
+    }
+
+    Err(WalletError::AddressNotInKeystore { address, path: keystore_path.to_path_buf() })
+}
+
+/// Adds a new ED25519 key to the keystore, creating the keystore if there is none. Only run on an explicit command
+pub fn generate_key(keystore_path: &Path) -> Result<SuiAddress, anyhow::Error> {
+    let mut keystore = FileBasedKeystore::new(&keystore_path.to_path_buf())?;
+    let address = keystore.generate_and_add_new_key(ED25519, None, None)?.0;
+    keystore.save()?;
 
-        client_config.save(&wallet_conf)?;
-        println!("Client config file is stored in {:?}.", &wallet_conf);
+    Ok(address)
+}
+
+/// Wallet commands, args are everything after `wallet` on the command line
+pub fn run_wallet_command(args: &[&str]) -> Result<(), anyhow::Error> {
+    match args {
+        ["generate", keystore_path] => {
+            let address = generate_key(Path::new(keystore_path))?;
+            println!("added key for {} to {}", address, keystore_path);
+        },
+        ["show", keystore_path, address] => {
+            let wallet = load_wallet(Path::new(keystore_path), address)?;
+            println!("loaded key for {} from {}", wallet.address(), keystore_path);
+        },
+        _ => return Err(anyhow!(WALLET_USAGE)),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_keystore_path(name: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+        std::env::temp_dir().join(format!("dechat_{}_{}.keystore", name, nanos))
+    }
+
+    fn remove_keystore(keystore_path: &Path) {
+        std::fs::remove_file(keystore_path).ok();
+        std::fs::remove_file(keystore_path.with_extension("aliases")).ok();
     }
 
-    let mut keystore = FileBasedKeystore::new(&keystore_path)?;
-    let mut client_config: SuiClientConfig = PersistedConfig::read(&wallet_conf)?;
+    #[test]
+    fn test_load_generated_key_without_writing() {
+        let keystore_path = temp_keystore_path("load");
+        let address = generate_key(&keystore_path).unwrap();
+        let contents = std::fs::read_to_string(&keystore_path).unwrap();
 
-    let default_active_address = if let Some(address) = keystore.addresses().first() {
-        *address
-    } else {
-        keystore.generate_and_add_new_key(ED25519, None, None)?.0
-    };
+        let wallet = load_wallet(&keystore_path, &address.to_string()).unwrap();
 
-    if keystore.addresses().len() < 2 {
-        keystore.generate_and_add_new_key(ED25519, None, None)?;
+        assert!(wallet.address() == address);
+        assert!(std::fs::read_to_string(&keystore_path).unwrap() == contents);
+        remove_keystore(&keystore_path);
     }
 
-    client_config.active_address = Some(default_active_address);
-    client_config.save(&wallet_conf)?;
+    #[test]
+    fn test_load_wallet_errors() {
+        let keystore_path = temp_keystore_path("errors");
+        let address = SuiAddress::random_for_testing_only().to_string();
 
-    let wallet =
-        WalletContext::new(&wallet_conf, Some(std::time::Duration::from_secs(60)), None).await?;
+        assert!(matches!(load_wallet(&keystore_path, &address), Err(WalletError::KeystoreMissing(_))));
+        assert!(!keystore_path.exists());
+        assert!(matches!(load_wallet(&keystore_path, "not an address"), Err(WalletError::InvalidAddress(_))));
 
-    Ok(wallet)
-}
\ No newline at end of file
+        std::fs::write(&keystore_path, "{}").unwrap();
+        assert!(matches!(load_wallet(&keystore_path, &address), Err(WalletError::InvalidKeystore { .. })));
+        std::fs::write(&keystore_path, "[]").unwrap();
+        assert!(matches!(load_wallet(&keystore_path, &address), Err(WalletError::AddressNotInKeystore { .. })));
+        remove_keystore(&keystore_path);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::aptos::aptos::parse_events_response;
+use crate::events::CREATE_PROFILE_EVENT;
+use crate::source::source::{ChainEvent, ChainEventId, ChainEventSource, EventSourceError, RawChainEvent, SUI_CHAIN};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+/// Two valid CreateProfileEvents followed by one with a missing field and one of an unknown type
+#[allow(unused)]
+pub const SUI_EVENTS_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/sui_events.jsonl");
+
+/// A recorded aptos indexer response, alice and bob create profiles, alice posts and bob follows her
+#[allow(unused)]
+pub const APTOS_EVENTS_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/aptos_events.json");
+
+#[allow(unused)]
+pub fn get_aptos_events() -> Vec<RawChainEvent> {
+    let response = serde_json::from_str(&std::fs::read_to_string(APTOS_EVENTS_FIXTURE).unwrap()).unwrap();
+    parse_events_response(response, usize::MAX).unwrap().events
+}
+
+#[allow(unused)]
+pub fn get_raw_event(tx_digest: &str, event_seq: u64, event_name: &str, parsed_json: Value) -> RawChainEvent {
+    RawChainEvent {
+        chain: SUI_CHAIN.to_string(),
+        id: ChainEventId { tx_digest: tx_digest.to_string(), event_seq },
+        event_type: format!("0x2::dechat_sui::{}", event_name),
+        parsed_json,
+        sender: None,
+        bcs: vec![],
+    }
+}
+
+#[allow(unused)]
+pub fn get_raw_create_profile_event(tx_digest: &str, event_seq: u64, user_name: &str) -> RawChainEvent {
+    get_raw_event(tx_digest, event_seq, CREATE_PROFILE_EVENT, json!({
+        "profile_id": get_object_id(&format!("{}{}", tx_digest, event_seq)),
+        "user_name": format!("{}{}", user_name, event_seq),
+        "full_name": "Jill Simon",
+        "description": "I am a developer",
+    }))
+}
+
+/// A valid object id derived from seed, so events can refer to each other's objects
+#[allow(unused)]
+pub fn get_object_id(seed: &str) -> String {
+    format!("0x{}", seed.bytes().take(32).map(|byte| format!("{:02x}", byte)).collect::<String>())
+}
+
+/// Hands out the given events in order, then ends like a closed subscription
+#[allow(unused)]
+pub struct VecEventSource {
+    events: VecDeque<RawChainEvent>,
+}
+
+#[allow(unused)]
+impl VecEventSource {
+    pub fn new(events: Vec<RawChainEvent>) -> Self {
+        Self { events: events.into() }
+    }
+}
+
+#[async_trait]
+impl ChainEventSource for VecEventSource {
+    async fn next_event(&mut self) -> Option<Result<ChainEvent, EventSourceError>> {
+        self.events.pop_front().map(RawChainEvent::decode)
+    }
+}
@@ -0,0 +1,7 @@
+alter table follow
+    add constraint uq_follow_follower_following unique (follower_id, following_id);
+
+alter table follow
+    add constraint ck_follow_not_self check (follower_id <> following_id);
+
+create index idx_follow_following_id on follow(following_id);
@@ -0,0 +1 @@
+create index idx_post_user_id_updated_at_id on post(user_id, updated_at desc, id desc);
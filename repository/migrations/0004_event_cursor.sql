@@ -0,0 +1,10 @@
+create table event_cursor (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "subscription" varchar(250) NOT NULL,
+    "tx_digest" varchar(100) NOT NULL,
+    "event_seq" bigint NOT NULL,
+
+    constraint uq_event_cursor_subscription unique (subscription)
+);
@@ -0,0 +1,21 @@
+-- Replayed chain events could insert the same asset more than once. The oldest row keeps the
+-- chain_asset_id and later copies get their id appended, so nothing referencing them is lost
+update profile p
+    set chain_asset_id = p.chain_asset_id || '#' || p.id
+    where exists (
+        select 1 from profile o
+        where o.chain_id = p.chain_id and o.chain_asset_id = p.chain_asset_id and o.id < p.id
+    );
+
+update post p
+    set chain_asset_id = p.chain_asset_id || '#' || p.id
+    where exists (
+        select 1 from post o
+        where o.chain_id = p.chain_id and o.chain_asset_id = p.chain_asset_id and o.id < p.id
+    );
+
+alter table profile
+    add constraint uq_profile_chain_asset unique (chain_id, chain_asset_id);
+
+alter table post
+    add constraint uq_post_chain_asset unique (chain_id, chain_asset_id);
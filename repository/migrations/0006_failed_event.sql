@@ -0,0 +1,16 @@
+create table failed_event (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "subscription" varchar(250) NOT NULL,
+    "tx_digest" varchar(100) NOT NULL,
+    "event_seq" bigint NOT NULL,
+    "payload" text NOT NULL,
+    "error" text NOT NULL,
+    "attempts" int NOT NULL DEFAULT 1,
+    "next_retry_at" timestamptz(3),
+
+    constraint uq_failed_event_event unique (subscription, tx_digest, event_seq)
+);
+
+create index idx_failed_event_next_retry_at on failed_event(next_retry_at) where next_retry_at is not null;
@@ -0,0 +1,26 @@
+-- The wallet that created the profile on chain, profiles created before it was recorded have none and cannot log in
+alter table profile add column "owner_address" varchar(66);
+
+create table auth_challenge (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "profile_id" bigint NOT NULL,
+    "nonce" varchar(64) NOT NULL,
+    "expires_at" timestamptz(3) NOT NULL,
+
+    constraint fk_auth_challenge_profile foreign key(profile_id) references profile(id),
+    constraint uq_auth_challenge_nonce unique (nonce)
+);
+
+create table session (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "profile_id" bigint NOT NULL,
+    "token_hash" varchar(64) NOT NULL,
+    "expires_at" timestamptz(3) NOT NULL,
+
+    constraint fk_session_profile foreign key(profile_id) references profile(id),
+    constraint uq_session_token_hash unique (token_hash)
+);
@@ -0,0 +1,5 @@
+-- A session is one signed in device, token_hash holds the hash of its current refresh token and changes on every refresh
+alter table session add column "device_name" varchar(100) NOT NULL DEFAULT '';
+alter table session add column "refreshed_at" timestamptz(3);
+
+create index idx_session_profile on session (profile_id, created_at);
@@ -0,0 +1,12 @@
+-- Tells listeners in other processes, such as data_service, about rows written by any of them. Postgres only
+-- delivers the notification once the inserting transaction commits, so the row and its links are readable by then.
+-- Only posts notify, nothing listening keeps profiles or follows that an insert would have to invalidate
+create function notify_entity_inserted() returns trigger as $$
+begin
+    perform pg_notify('entity_inserted', json_build_object('entity', TG_TABLE_NAME, 'id', new.id)::text);
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_post_inserted after insert on post
+    for each row execute function notify_entity_inserted();
@@ -0,0 +1,3 @@
+-- Profiles and posts created through the api rather than on a chain, their chain_asset_id is generated by the
+-- server so they can never occupy the chain asset of a real chain object
+insert into chain (name) values ('local');
@@ -0,0 +1,5 @@
+-- A wallet without a profile signs a challenge for its address to create its first profile through the api,
+-- every challenge is either for a profile signing in or for an address
+alter table auth_challenge alter column "profile_id" drop not null;
+alter table auth_challenge add column "address" varchar(66);
+alter table auth_challenge add constraint ck_auth_challenge_subject check ((profile_id is null) <> (address is null));
@@ -1,4 +1,7 @@
 pub mod repo {
+    pub mod chain {
+        pub mod chain;
+    }
     pub mod profile {
         pub mod profile;
         pub mod model;
@@ -7,7 +10,37 @@ pub mod repo {
         pub mod post;
         pub mod model;
     }
+    pub mod follow {
+        pub mod follow;
+    }
+    pub mod event_cursor {
+        pub mod event_cursor;
+        pub mod model;
+    }
+    pub mod failed_event {
+        pub mod failed_event;
+        pub mod model;
+    }
+    pub mod auth {
+        pub mod auth;
+        pub mod model;
+    }
+    pub mod notify {
+        pub mod notify;
+        pub mod model;
+    }
+    pub mod in_memory {
+        pub mod in_memory;
+        pub mod profile;
+        pub mod post;
+        pub mod follow;
+        pub mod event_cursor;
+        pub mod failed_event;
+        pub mod chain;
+        pub mod auth;
+    }
     pub mod base;
+    pub mod error;
 }
 pub mod test_helpers {
     pub mod fixtures;
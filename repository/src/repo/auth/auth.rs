@@ -0,0 +1,623 @@
+use crate::repo::auth::model::{ AuthChallengeQueryResult, SessionQueryResult };
+use crate::repo::base::{ DbRepo, DbConnGetter, EntityId };
+use crate::repo::error::{ RepositoryError, check_value_len };
+use async_trait::async_trait;
+use chrono::{ DateTime, Utc };
+use sqlx::{ Pool, Postgres };
+use mockall::automock;
+
+mod private_members {
+    use super::*;
+
+    /// Expired challenges of every profile and address are deleted by the same statement. Requests racing each
+    /// other may each see fewer than max_outstanding and go slightly over it
+    pub async fn insert_challenge_inner(
+        conn: &Pool<Postgres>,
+        profile_id: Option<i64>,
+        address: Option<&str>,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("nonce", nonce, 64)?;
+        if let Some(address) = address {
+            check_value_len("address", address, 66)?;
+        }
+
+        let result = sqlx::query_as::<_, EntityId>(
+            r"
+                with expired as (
+                    delete from auth_challenge where expires_at <= current_timestamp
+                )
+                insert into auth_challenge (profile_id, address, nonce, expires_at)
+                select $1, $2, $3, $4
+                where (
+                    select count(*) from auth_challenge
+                    where (profile_id = $1 or address = $2) and expires_at > current_timestamp
+                ) < $5
+                returning id
+            "
+        )
+        .bind(profile_id)
+        .bind(address)
+        .bind(nonce)
+        .bind(expires_at)
+        .bind(max_outstanding)
+        .fetch_optional(conn).await?;
+
+        match result {
+            Some(result) => Ok(result.id),
+            None => {
+                let field = if profile_id.is_some() { "profile_id" } else { "address" };
+                Err(RepositoryError::LimitReached { field: field.to_string(), max: max_outstanding })
+            },
+        }
+    }
+
+    /// Deletes the challenge as it is read so a signed nonce can never be used twice, expired or not
+    pub async fn take_auth_challenge_inner(
+        conn: &Pool<Postgres>,
+        nonce: &str
+    ) -> Result<Option<AuthChallengeQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, AuthChallengeQueryResult>("delete from auth_challenge where nonce = $1 returning *")
+            .bind(nonce)
+            .fetch_optional(conn).await?)
+    }
+
+    pub async fn insert_session_inner(
+        conn: &Pool<Postgres>,
+        profile_id: i64,
+        token_hash: &str,
+        device_name: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("token_hash", token_hash, 64)?;
+        check_value_len("device_name", device_name, 100)?;
+
+        let result = sqlx::query_as::<_, EntityId>(
+            "insert into session (profile_id, token_hash, device_name, expires_at) values ($1, $2, $3, $4) returning id"
+        )
+        .bind(profile_id)
+        .bind(token_hash)
+        .bind(device_name)
+        .bind(expires_at)
+        .fetch_one(conn).await?;
+
+        Ok(result.id)
+    }
+
+    pub async fn query_session_by_token_hash_inner(
+        conn: &Pool<Postgres>,
+        token_hash: &str
+    ) -> Result<Option<SessionQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, SessionQueryResult>("select * from session where token_hash = $1")
+            .bind(token_hash)
+            .fetch_optional(conn).await?)
+    }
+
+    pub async fn query_session_inner(
+        conn: &Pool<Postgres>,
+        id: i64
+    ) -> Result<Option<SessionQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, SessionQueryResult>("select * from session where id = $1")
+            .bind(id)
+            .fetch_optional(conn).await?)
+    }
+
+    /// Only swaps the hash while it is still the one the caller presented, of two refreshes racing with
+    /// the same token the second finds nothing to update
+    pub async fn rotate_session_token_inner(
+        conn: &Pool<Postgres>,
+        id: i64,
+        token_hash: &str,
+        new_token_hash: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<(), RepositoryError> {
+        check_value_len("token_hash", new_token_hash, 64)?;
+
+        let result = sqlx::query::<_>(
+            r"
+                update session
+                set token_hash = $1, expires_at = $2, refreshed_at = current_timestamp, updated_at = current_timestamp
+                where id = $3 and token_hash = $4
+            "
+        )
+        .bind(new_token_hash)
+        .bind(expires_at)
+        .bind(id)
+        .bind(token_hash)
+        .execute(conn).await?;
+
+        match result.rows_affected() {
+            0 => Err(RepositoryError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn query_sessions_by_profile_inner(
+        conn: &Pool<Postgres>,
+        profile_id: i64
+    ) -> Result<Vec<SessionQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, SessionQueryResult>(
+            r"
+                select * from session
+                where profile_id = $1 and expires_at > current_timestamp
+                order by created_at desc, id desc
+            "
+        )
+        .bind(profile_id)
+        .fetch_all(conn).await?)
+    }
+
+    pub async fn revoke_session_inner(
+        conn: &Pool<Postgres>,
+        profile_id: i64,
+        id: i64
+    ) -> Result<(), RepositoryError> {
+        let result = sqlx::query::<_>("delete from session where id = $1 and profile_id = $2")
+            .bind(id)
+            .bind(profile_id)
+            .execute(conn).await?;
+
+        match result.rows_affected() {
+            0 => Err(RepositoryError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn revoke_all_sessions_inner(
+        conn: &Pool<Postgres>,
+        profile_id: i64
+    ) -> Result<u64, RepositoryError> {
+        let result = sqlx::query::<_>("delete from session where profile_id = $1")
+            .bind(profile_id)
+            .execute(conn).await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Purges expired challenges, LimitReached when the profile already has max_outstanding unexpired ones
+#[automock]
+#[async_trait]
+pub trait InsertAuthChallengeFn {
+    async fn insert_auth_challenge(
+        &self,
+        profile_id: i64,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError>;
+}
+
+#[async_trait]
+impl InsertAuthChallengeFn for DbRepo {
+    async fn insert_auth_challenge(
+        &self,
+        profile_id: i64,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError> {
+        private_members::insert_challenge_inner(self.get_conn(), Some(profile_id), None, nonce, expires_at, max_outstanding).await
+    }
+}
+
+/// Challenge for a wallet that may have no profile yet, capped per address like InsertAuthChallengeFn per profile
+#[automock]
+#[async_trait]
+pub trait InsertAddressChallengeFn {
+    async fn insert_address_challenge(
+        &self,
+        address: &str,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError>;
+}
+
+#[async_trait]
+impl InsertAddressChallengeFn for DbRepo {
+    async fn insert_address_challenge(
+        &self,
+        address: &str,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError> {
+        private_members::insert_challenge_inner(self.get_conn(), None, Some(address), nonce, expires_at, max_outstanding).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait TakeAuthChallengeFn {
+    async fn take_auth_challenge(
+        &self,
+        nonce: &str
+    ) -> Result<Option<AuthChallengeQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl TakeAuthChallengeFn for DbRepo {
+    async fn take_auth_challenge(
+        &self,
+        nonce: &str
+    ) -> Result<Option<AuthChallengeQueryResult>, RepositoryError> {
+        private_members::take_auth_challenge_inner(self.get_conn(), nonce).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait InsertSessionFn {
+    async fn insert_session(
+        &self,
+        profile_id: i64,
+        token_hash: &str,
+        device_name: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<i64, RepositoryError>;
+}
+
+#[async_trait]
+impl InsertSessionFn for DbRepo {
+    async fn insert_session(
+        &self,
+        profile_id: i64,
+        token_hash: &str,
+        device_name: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<i64, RepositoryError> {
+        private_members::insert_session_inner(self.get_conn(), profile_id, token_hash, device_name, expires_at).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait QuerySessionByTokenHashFn {
+    async fn query_session_by_token_hash(
+        &self,
+        token_hash: &str
+    ) -> Result<Option<SessionQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QuerySessionByTokenHashFn for DbRepo {
+    async fn query_session_by_token_hash(
+        &self,
+        token_hash: &str
+    ) -> Result<Option<SessionQueryResult>, RepositoryError> {
+        private_members::query_session_by_token_hash_inner(self.get_conn(), token_hash).await
+    }
+}
+
+/// Returns revoked sessions as None, expired ones are returned as they are
+#[automock]
+#[async_trait]
+pub trait QuerySessionFn {
+    async fn query_session(
+        &self,
+        id: i64
+    ) -> Result<Option<SessionQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QuerySessionFn for DbRepo {
+    async fn query_session(
+        &self,
+        id: i64
+    ) -> Result<Option<SessionQueryResult>, RepositoryError> {
+        private_members::query_session_inner(self.get_conn(), id).await
+    }
+}
+
+/// Replaces the refresh token of a session, NotFound when the presented token was already rotated or revoked
+#[automock]
+#[async_trait]
+pub trait RotateSessionTokenFn {
+    async fn rotate_session_token(
+        &self,
+        id: i64,
+        token_hash: &str,
+        new_token_hash: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl RotateSessionTokenFn for DbRepo {
+    async fn rotate_session_token(
+        &self,
+        id: i64,
+        token_hash: &str,
+        new_token_hash: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<(), RepositoryError> {
+        private_members::rotate_session_token_inner(self.get_conn(), id, token_hash, new_token_hash, expires_at).await
+    }
+}
+
+/// The unexpired sessions of a profile, newest first
+#[automock]
+#[async_trait]
+pub trait QuerySessionsByProfileFn {
+    async fn query_sessions_by_profile(
+        &self,
+        profile_id: i64
+    ) -> Result<Vec<SessionQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QuerySessionsByProfileFn for DbRepo {
+    async fn query_sessions_by_profile(
+        &self,
+        profile_id: i64
+    ) -> Result<Vec<SessionQueryResult>, RepositoryError> {
+        private_members::query_sessions_by_profile_inner(self.get_conn(), profile_id).await
+    }
+}
+
+/// Deletes a session of the profile, NotFound when it does not exist or belongs to another profile
+#[automock]
+#[async_trait]
+pub trait RevokeSessionFn {
+    async fn revoke_session(
+        &self,
+        profile_id: i64,
+        id: i64
+    ) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl RevokeSessionFn for DbRepo {
+    async fn revoke_session(
+        &self,
+        profile_id: i64,
+        id: i64
+    ) -> Result<(), RepositoryError> {
+        private_members::revoke_session_inner(self.get_conn(), profile_id, id).await
+    }
+}
+
+/// Deletes every session of the profile and returns how many there were
+#[automock]
+#[async_trait]
+pub trait RevokeAllSessionsFn {
+    async fn revoke_all_sessions(
+        &self,
+        profile_id: i64
+    ) -> Result<u64, RepositoryError>;
+}
+
+#[async_trait]
+impl RevokeAllSessionsFn for DbRepo {
+    async fn revoke_all_sessions(
+        &self,
+        profile_id: i64
+    ) -> Result<u64, RepositoryError> {
+        private_members::revoke_all_sessions_inner(self.get_conn(), profile_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repo::profile::{ profile::InsertProfileFn, model::ProfileCreate };
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+    use super::*;
+    use chrono::Duration;
+    use lazy_static::lazy_static;
+    use std::sync::{ Arc, RwLock };
+
+    #[derive(Clone)]
+    #[allow(unused)]
+    struct Fixtures {
+        db_repo: DbRepo
+    }
+
+    /// Helps prevent clashes between other running tests, by adding unique prefix values for data
+    const PREFIX: &str = "TestAuth";
+
+    lazy_static! {
+        static ref FIXTURES: Arc<RwLock<Option<Fixtures>>> = Arc::new(RwLock::new(None));
+    }
+
+    async fn setup_fixtures() {
+        let fixtures = Arc::clone(&FIXTURES);
+        let mut fx = fixtures.write().unwrap();
+        match fx.clone() {
+            Some(_) => (),
+            None => {
+                let db_repo = DbRepo::init().await;
+
+                *fx = Some(Fixtures { db_repo });
+            }
+        }
+    }
+
+    lazy_static! {
+        static ref RT: tokio::runtime::Runtime = {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+            rt.block_on(async {
+                setup_fixtures().await;
+            });
+
+            rt
+        };
+    }
+
+    #[allow(unused)]
+    fn fixtures() -> Fixtures {
+        Arc::clone(&FIXTURES).read().unwrap().clone().unwrap()
+    }
+
+    async fn insert_test_profile(db_repo: &DbRepo) -> i64 {
+        let chain_asset_id = get_fake_chain_asset_id();
+        db_repo
+            .insert_profile(ProfileCreate {
+                user_name: format!("{}{}", PREFIX, &chain_asset_id[2..14]),
+                chain_asset_id,
+                chain_id: SUI_CHAIN_ID,
+                full_name: format!("{} Tester", PREFIX),
+                description: format!("{} a description", PREFIX),
+                main_url: None,
+                avatar: None,
+                owner_address: Some(format!("0x{}", "ab".repeat(32))),
+            }).await
+            .unwrap()
+    }
+
+    fn unique_value(name: &str) -> String {
+        format!("{}{}{:032x}", PREFIX, name, rand::random::<u128>())
+    }
+
+    mod test_mod_take_auth_challenge {
+        use super::*;
+
+        async fn test_take_auth_challenge_body() {
+            let fixtures = fixtures();
+            let profile_id = insert_test_profile(&fixtures.db_repo).await;
+            let nonce = unique_value("nonce");
+            let expires_at = Utc::now() + Duration::minutes(5);
+
+            fixtures.db_repo.insert_auth_challenge(profile_id, &nonce, expires_at, 5).await.unwrap();
+            let challenge = fixtures.db_repo.take_auth_challenge(&nonce).await.unwrap().unwrap();
+
+            assert!(challenge.profile_id == Some(profile_id) && challenge.address.is_none());
+            assert!(challenge.nonce == nonce);
+            assert!(fixtures.db_repo.take_auth_challenge(&nonce).await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_take_auth_challenge() {
+            RT.block_on(test_take_auth_challenge_body())
+        }
+
+        async fn test_insert_auth_challenge_purges_and_caps_body() {
+            let fixtures = fixtures();
+            let profile_id = insert_test_profile(&fixtures.db_repo).await;
+            let expired_nonce = unique_value("nonce");
+            let expires_at = Utc::now() + Duration::minutes(5);
+
+            fixtures.db_repo.insert_auth_challenge(profile_id, &expired_nonce, Utc::now() - Duration::minutes(1), 2).await.unwrap();
+            fixtures.db_repo.insert_auth_challenge(profile_id, &unique_value("nonce"), expires_at, 2).await.unwrap();
+            fixtures.db_repo.insert_auth_challenge(profile_id, &unique_value("nonce"), expires_at, 2).await.unwrap();
+            let over_limit = fixtures.db_repo.insert_auth_challenge(profile_id, &unique_value("nonce"), expires_at, 2).await;
+
+            assert!(matches!(over_limit, Err(RepositoryError::LimitReached { max: 2, .. })));
+            assert!(fixtures.db_repo.take_auth_challenge(&expired_nonce).await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_insert_auth_challenge_purges_and_caps() {
+            RT.block_on(test_insert_auth_challenge_purges_and_caps_body())
+        }
+
+        async fn test_insert_address_challenge_body() {
+            let fixtures = fixtures();
+            let address = format!("0x{:064x}", rand::random::<u128>());
+            let nonce = unique_value("nonce");
+            let expires_at = Utc::now() + Duration::minutes(5);
+
+            fixtures.db_repo.insert_address_challenge(&address, &nonce, expires_at, 1).await.unwrap();
+            let over_limit = fixtures.db_repo.insert_address_challenge(&address, &unique_value("nonce"), expires_at, 1).await;
+            let challenge = fixtures.db_repo.take_auth_challenge(&nonce).await.unwrap().unwrap();
+
+            assert!(matches!(over_limit, Err(RepositoryError::LimitReached { .. })));
+            assert!(challenge.address == Some(address) && challenge.profile_id.is_none());
+        }
+
+        #[test]
+        fn test_insert_address_challenge() {
+            RT.block_on(test_insert_address_challenge_body())
+        }
+    }
+
+    mod test_mod_query_session_by_token_hash {
+        use super::*;
+
+        async fn test_query_session_by_token_hash_body() {
+            let fixtures = fixtures();
+            let profile_id = insert_test_profile(&fixtures.db_repo).await;
+            let token_hash = unique_value("token");
+            let expires_at = Utc::now() + Duration::days(1);
+
+            let id = fixtures.db_repo.insert_session(profile_id, &token_hash, "laptop", expires_at).await.unwrap();
+            let session = fixtures.db_repo.query_session_by_token_hash(&token_hash).await.unwrap().unwrap();
+
+            assert!(session.id == id && session.profile_id == profile_id);
+            assert!(session.device_name == "laptop" && session.refreshed_at.is_none());
+            assert!(fixtures.db_repo.query_session_by_token_hash(&unique_value("token")).await.unwrap().is_none());
+            let duplicate = fixtures.db_repo.insert_session(profile_id, &token_hash, "laptop", expires_at).await;
+            assert!(matches!(duplicate, Err(RepositoryError::UniqueViolation { .. })));
+        }
+
+        #[test]
+        fn test_query_session_by_token_hash() {
+            RT.block_on(test_query_session_by_token_hash_body())
+        }
+    }
+
+    mod test_mod_rotate_session_token {
+        use super::*;
+
+        async fn test_rotate_session_token_body() {
+            let fixtures = fixtures();
+            let profile_id = insert_test_profile(&fixtures.db_repo).await;
+            let token_hash = unique_value("token");
+            let new_token_hash = unique_value("token");
+            let expires_at = Utc::now() + Duration::days(30);
+            let id = fixtures.db_repo.insert_session(profile_id, &token_hash, "phone", Utc::now() + Duration::days(1)).await.unwrap();
+
+            fixtures.db_repo.rotate_session_token(id, &token_hash, &new_token_hash, expires_at).await.unwrap();
+
+            assert!(fixtures.db_repo.query_session_by_token_hash(&token_hash).await.unwrap().is_none());
+            let session = fixtures.db_repo.query_session_by_token_hash(&new_token_hash).await.unwrap().unwrap();
+            assert!(session.id == id && session.refreshed_at.is_some());
+            assert!((session.expires_at - expires_at).num_milliseconds().abs() <= 1);
+            let reused = fixtures.db_repo.rotate_session_token(id, &token_hash, &unique_value("token"), expires_at).await;
+            assert!(matches!(reused, Err(RepositoryError::NotFound)));
+        }
+
+        #[test]
+        fn test_rotate_session_token() {
+            RT.block_on(test_rotate_session_token_body())
+        }
+    }
+
+    mod test_mod_revoke_sessions {
+        use super::*;
+
+        async fn test_revoke_sessions_body() {
+            let fixtures = fixtures();
+            let profile_id = insert_test_profile(&fixtures.db_repo).await;
+            let other_profile_id = insert_test_profile(&fixtures.db_repo).await;
+            let expires_at = Utc::now() + Duration::days(1);
+            let laptop_id = fixtures.db_repo.insert_session(profile_id, &unique_value("token"), "laptop", expires_at).await.unwrap();
+            let phone_id = fixtures.db_repo.insert_session(profile_id, &unique_value("token"), "phone", expires_at).await.unwrap();
+            fixtures.db_repo.insert_session(profile_id, &unique_value("token"), "old", Utc::now() - Duration::days(1)).await.unwrap();
+            let other_id = fixtures.db_repo.insert_session(other_profile_id, &unique_value("token"), "tablet", expires_at).await.unwrap();
+
+            let sessions = fixtures.db_repo.query_sessions_by_profile(profile_id).await.unwrap();
+            let session_ids: Vec<i64> = sessions.iter().map(|session| session.id).collect();
+            assert!(session_ids == vec![phone_id, laptop_id], "expired sessions are not listed");
+
+            let not_owned = fixtures.db_repo.revoke_session(profile_id, other_id).await;
+            assert!(matches!(not_owned, Err(RepositoryError::NotFound)));
+            assert!(fixtures.db_repo.query_session(phone_id).await.unwrap().unwrap().device_name == "phone");
+            fixtures.db_repo.revoke_session(profile_id, phone_id).await.unwrap();
+            assert!(fixtures.db_repo.query_session(phone_id).await.unwrap().is_none());
+            assert!(fixtures.db_repo.query_sessions_by_profile(profile_id).await.unwrap().len() == 1);
+
+            assert!(fixtures.db_repo.revoke_all_sessions(profile_id).await.unwrap() == 2);
+            assert!(fixtures.db_repo.query_sessions_by_profile(profile_id).await.unwrap().is_empty());
+            assert!(fixtures.db_repo.query_sessions_by_profile(other_profile_id).await.unwrap().len() == 1);
+        }
+
+        #[test]
+        fn test_revoke_sessions() {
+            RT.block_on(test_revoke_sessions_body())
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+/// A nonce handed out for a profile to sign in or for a wallet address to create a profile, exactly one of
+/// profile_id and address is set. It can be taken once and only before expires_at
+#[derive(Deserialize, Serialize, FromRow, Clone, Debug)]
+pub struct AuthChallengeQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub profile_id: Option<i64>,
+    pub address: Option<String>,
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Sessions are looked up by the hash of their refresh token, the token itself is only known to the client.
+/// refreshed_at is None until the first refresh
+#[derive(Deserialize, Serialize, FromRow, Clone, Debug)]
+pub struct SessionQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub profile_id: i64,
+    pub token_hash: String,
+    pub device_name: String,
+    pub refreshed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
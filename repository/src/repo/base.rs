@@ -1,8 +1,12 @@
 use serde::Deserialize;
-use sqlx::{FromRow, Postgres, Pool};
+use sqlx::{FromRow, Postgres, Pool, Transaction};
 use std::env;
 use dotenv::dotenv;
 use sqlx::migrate;
+use async_trait::async_trait;
+use mockall::automock;
+use tokio::sync::{Mutex, MutexGuard};
+use crate::repo::error::RepositoryError;
 
 #[allow(unused)]
 #[derive(FromRow, Deserialize)]
@@ -10,6 +14,13 @@ pub struct EntityId {
     pub id: i64
 }
 
+/// Id returned by upserts, inserted is false when the row already existed
+#[derive(FromRow)]
+pub struct UpsertedId {
+    pub id: i64,
+    pub inserted: bool
+}
+
 pub trait DbConnGetter {
     type Output;
     fn get_conn(&self) -> &Self::Output;
@@ -34,6 +45,56 @@ impl DbConnGetter for DbRepo {
     }
 }
 
+/// A unit of work, every repo trait implemented for it runs its statements on the same
+/// database transaction so that calls across different traits commit or roll back together
+pub struct DbTransaction {
+    tx: Mutex<Transaction<'static, Postgres>>
+}
+
+impl DbTransaction {
+    /// Statements take the transaction connection one at a time, so calls on the same
+    /// transaction from concurrent tasks are run one after another
+    pub async fn lock_conn(&self) -> MutexGuard<'_, Transaction<'static, Postgres>> {
+        self.tx.lock().await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait TransactionFn {
+    async fn commit(self) -> Result<(), RepositoryError>;
+    async fn rollback(self) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl TransactionFn for DbTransaction {
+    async fn commit(self) -> Result<(), RepositoryError> {
+        Ok(self.tx.into_inner().commit().await?)
+    }
+
+    async fn rollback(self) -> Result<(), RepositoryError> {
+        Ok(self.tx.into_inner().rollback().await?)
+    }
+}
+
+#[automock(type Transaction = MockTransactionFn;)]
+#[async_trait]
+pub trait BeginTransactionFn {
+    type Transaction: TransactionFn + Send + Sync;
+
+    async fn begin_transaction(&self) -> Result<Self::Transaction, RepositoryError>;
+}
+
+#[async_trait]
+impl BeginTransactionFn for DbRepo {
+    type Transaction = DbTransaction;
+
+    async fn begin_transaction(&self) -> Result<Self::Transaction, RepositoryError> {
+        let tx = self.conn.begin().await?;
+        Ok(DbTransaction { tx: Mutex::new(tx) })
+    }
+}
+
 pub async fn get_db_conn() -> Pool<Postgres> {
     dotenv().ok();
     let postgres_host = env::var("POSTGRES_HOST").unwrap();
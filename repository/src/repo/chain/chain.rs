@@ -0,0 +1,121 @@
+use crate::repo::base::{ DbRepo, DbConnGetter, DbTransaction, EntityId };
+use crate::repo::error::RepositoryError;
+use async_trait::async_trait;
+use sqlx::PgExecutor;
+use mockall::automock;
+
+/// Chain of the profiles and posts created through the api, seeded by migration 0010
+pub const LOCAL_CHAIN: &str = "local";
+
+mod private_members {
+    use super::*;
+
+    pub async fn query_chain_id_by_name_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        name: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        let result = sqlx::query_as::<_, EntityId>("select id from chain where name = $1")
+            .bind(name)
+            .fetch_optional(conn).await?;
+
+        Ok(result.map(|chain| chain.id))
+    }
+}
+
+/// Chains are seeded by the migrations, their ids are looked up by name rather than assumed
+#[automock]
+#[async_trait]
+pub trait QueryChainIdByNameFn {
+    async fn query_chain_id_by_name(
+        &self,
+        name: &str
+    ) -> Result<Option<i64>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryChainIdByNameFn for DbRepo {
+    async fn query_chain_id_by_name(
+        &self,
+        name: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        private_members::query_chain_id_by_name_inner(self.get_conn(), name).await
+    }
+}
+
+#[async_trait]
+impl QueryChainIdByNameFn for DbTransaction {
+    async fn query_chain_id_by_name(
+        &self,
+        name: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        private_members::query_chain_id_by_name_inner(&mut **self.lock_conn().await, name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_helpers::fixtures::SUI_CHAIN_ID;
+    use super::*;
+    use lazy_static::lazy_static;
+    use std::sync::{ Arc, RwLock };
+
+    #[derive(Clone)]
+    #[allow(unused)]
+    struct Fixtures {
+        db_repo: DbRepo
+    }
+
+    lazy_static! {
+        static ref FIXTURES: Arc<RwLock<Option<Fixtures>>> = Arc::new(RwLock::new(None));
+    }
+
+    async fn setup_fixtures() {
+        let fixtures = Arc::clone(&FIXTURES);
+        let mut fx = fixtures.write().unwrap();
+        match fx.clone() {
+            Some(_) => (),
+            None => {
+                let db_repo = DbRepo::init().await;
+
+                *fx = Some(Fixtures { db_repo });
+            }
+        }
+    }
+
+    lazy_static! {
+        static ref RT: tokio::runtime::Runtime = {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+            rt.block_on(async {
+                setup_fixtures().await;
+            });
+
+            rt
+        };
+    }
+
+    #[allow(unused)]
+    fn fixtures() -> Fixtures {
+        Arc::clone(&FIXTURES).read().unwrap().clone().unwrap()
+    }
+
+    mod test_mod_query_chain_id_by_name {
+        use super::*;
+
+        async fn test_query_chain_id_by_name_body() {
+            let fixtures = fixtures();
+
+            let sui_id = fixtures.db_repo.query_chain_id_by_name("sui").await.unwrap();
+            let aptos_id = fixtures.db_repo.query_chain_id_by_name("aptos").await.unwrap();
+
+            assert!(sui_id == Some(SUI_CHAIN_ID));
+            assert!(aptos_id.is_some() && aptos_id != sui_id);
+            assert!(fixtures.db_repo.query_chain_id_by_name("solana").await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_query_chain_id_by_name() {
+            RT.block_on(test_query_chain_id_by_name_body())
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use sqlx::error::DatabaseError;
+use sqlx::postgres::PgDatabaseError;
+
+const UNIQUE_VIOLATION_CODE: &str = "23505";
+const FOREIGN_KEY_VIOLATION_CODE: &str = "23503";
+const CHECK_VIOLATION_CODE: &str = "23514";
+const STRING_DATA_RIGHT_TRUNCATION_CODE: &str = "22001";
+const FOLLOW_NOT_SELF_CONSTRAINT: &str = "ck_follow_not_self";
+
+/// Underlying driver error, kept boxed so that no caller depends on the storage library
+pub type StorageError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Storage independent error returned by every repo trait
+#[derive(Debug)]
+pub enum RepositoryError {
+    NotFound,
+    UniqueViolation { field: String },
+    ForeignKeyViolation { field: String },
+    ValueTooLong { field: String, max: usize },
+    SelfFollow,
+    LimitReached { field: String, max: i64 },
+    Unavailable(StorageError),
+    Database(StorageError),
+}
+
+impl std::error::Error for RepositoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Unavailable(e) | Self::Database(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "record not found"),
+            Self::UniqueViolation { field } => write!(f, "{} already exists", field),
+            Self::ForeignKeyViolation { field } => write!(f, "{} references a record that does not exist", field),
+            Self::ValueTooLong { field, max } => write!(f, "{} is longer than {} characters", field, max),
+            Self::SelfFollow => write!(f, "a profile cannot follow itself"),
+            Self::LimitReached { field, max } => write!(f, "{} already has the most records allowed, {}", field, max),
+            Self::Unavailable(e) => write!(f, "database unavailable: {}", e),
+            Self::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// All Postgres error codes are mapped here so that no caller needs to know about them
+impl From<sqlx::Error> for RepositoryError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => Self::NotFound,
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_) => Self::Unavailable(Box::new(e)),
+            sqlx::Error::Database(ref db_err) => {
+                match db_err.code().as_deref() {
+                    Some(UNIQUE_VIOLATION_CODE) => Self::UniqueViolation { field: constraint_field(db_err.as_ref()) },
+                    Some(FOREIGN_KEY_VIOLATION_CODE) => Self::ForeignKeyViolation { field: constraint_field(db_err.as_ref()) },
+                    Some(CHECK_VIOLATION_CODE) if db_err.constraint() == Some(FOLLOW_NOT_SELF_CONSTRAINT) => Self::SelfFollow,
+                    Some(STRING_DATA_RIGHT_TRUNCATION_CODE) => Self::ValueTooLong {
+                        field: truncated_column(db_err.as_ref()),
+                        max: varchar_max_len(db_err.message()).unwrap_or_default()
+                    },
+                    _ => Self::Database(Box::new(e)),
+                }
+            },
+            _ => Self::Database(Box::new(e)),
+        }
+    }
+}
+
+/// Postgres only reports the constraint name, so known constraints are translated into the columns they cover
+fn constraint_field(db_err: &dyn DatabaseError) -> String {
+    match db_err.constraint() {
+        Some("uq_follow_follower_following") => "follower_id, following_id".to_string(),
+        Some("uq_profile_chain_asset") | Some("uq_post_chain_asset") => "chain_id, chain_asset_id".to_string(),
+        Some("fk_profile_follower") => "follower_id".to_string(),
+        Some("fk_profile_following") => "following_id".to_string(),
+        Some("fk_profile_chain") | Some("fk_post_chain") => "chain_id".to_string(),
+        Some("fk_post_profile") => "user_id".to_string(),
+        Some("fk_respondee_post") => "respondee_post_id".to_string(),
+        Some("fk_responder_post") => "responder_post_id".to_string(),
+        Some("fk_sharee_post") => "sharee_post_id".to_string(),
+        Some("fk_sharer_post") => "sharer_post_id".to_string(),
+        Some(constraint) => constraint.to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+/// Postgres rarely names the column of a too long value, in that case the field stays unknown rather than
+/// being guessed from the table
+fn truncated_column(db_err: &dyn DatabaseError) -> String {
+    db_err.try_downcast_ref::<PgDatabaseError>()
+        .and_then(|pg_err| pg_err.column())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Parses the length out of messages like "value too long for type character varying(50)"
+fn varchar_max_len(message: &str) -> Option<usize> {
+    let start = message.rfind('(')? + 1;
+    let end = message.rfind(')')?;
+    message.get(start..end)?.parse().ok()
+}
+
+/// Postgres does not say which column overflowed, so inserts check known varchar limits up front
+pub fn check_value_len(field: &str, value: &str, max: usize) -> Result<(), RepositoryError> {
+    if value.chars().count() > max {
+        return Err(RepositoryError::ValueTooLong { field: field.to_string(), max });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varchar_max_len() {
+        assert!(varchar_max_len("value too long for type character varying(50)") == Some(50));
+        assert!(varchar_max_len("some other message").is_none());
+    }
+
+    #[test]
+    fn test_check_value_len() {
+        assert!(check_value_len("user_name", "dave", 4).is_ok());
+        let result = check_value_len("user_name", "david", 4);
+        assert!(matches!(result, Err(RepositoryError::ValueTooLong { field, max: 4 }) if field == "user_name"));
+    }
+
+    #[test]
+    fn test_row_not_found_maps_to_not_found() {
+        assert!(matches!(RepositoryError::from(sqlx::Error::RowNotFound), RepositoryError::NotFound));
+        assert!(matches!(RepositoryError::from(sqlx::Error::PoolTimedOut), RepositoryError::Unavailable(_)));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::repo::base::{ DbRepo, DbConnGetter, DbTransaction };
+use crate::repo::error::{ RepositoryError, check_value_len };
+use crate::repo::event_cursor::model::EventCursorQueryResult;
+use async_trait::async_trait;
+use sqlx::{ Pool, Postgres, PgExecutor };
+use mockall::automock;
+
+mod private_members {
+    use super::*;
+
+    pub async fn query_event_cursor_inner(
+        conn: &Pool<Postgres>,
+        subscription: &str
+    ) -> Result<Option<EventCursorQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, EventCursorQueryResult>(
+            "select subscription, updated_at, tx_digest, event_seq from event_cursor where subscription = $1"
+        )
+        .bind(subscription)
+        .fetch_optional(conn).await?)
+    }
+
+    pub async fn upsert_event_cursor_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        subscription: &str,
+        tx_digest: &str,
+        event_seq: i64
+    ) -> Result<(), RepositoryError> {
+        check_value_len("subscription", subscription, 250)?;
+        check_value_len("tx_digest", tx_digest, 100)?;
+
+        sqlx::query::<_>(
+            r"
+                insert into event_cursor (subscription, tx_digest, event_seq) values ($1, $2, $3)
+                on conflict (subscription)
+                do update set tx_digest = excluded.tx_digest, event_seq = excluded.event_seq, updated_at = current_timestamp
+            "
+        )
+        .bind(subscription)
+        .bind(tx_digest)
+        .bind(event_seq)
+        .execute(conn).await?;
+
+        Ok(())
+    }
+}
+
+/// Returns None when the subscription has not processed any event yet
+#[automock]
+#[async_trait]
+pub trait QueryEventCursorFn {
+    async fn query_event_cursor(
+        &self,
+        subscription: &str
+    ) -> Result<Option<EventCursorQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryEventCursorFn for DbRepo {
+    async fn query_event_cursor(
+        &self,
+        subscription: &str
+    ) -> Result<Option<EventCursorQueryResult>, RepositoryError> {
+        private_members::query_event_cursor_inner(self.get_conn(), subscription).await
+    }
+}
+
+/// Moves the subscription's cursor to the given event, run it on the same transaction
+/// as the writes for that event so a restart never applies an event twice
+#[automock]
+#[async_trait]
+pub trait UpsertEventCursorFn {
+    async fn upsert_event_cursor(
+        &self,
+        subscription: &str,
+        tx_digest: &str,
+        event_seq: i64
+    ) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl UpsertEventCursorFn for DbRepo {
+    async fn upsert_event_cursor(
+        &self,
+        subscription: &str,
+        tx_digest: &str,
+        event_seq: i64
+    ) -> Result<(), RepositoryError> {
+        private_members::upsert_event_cursor_inner(self.get_conn(), subscription, tx_digest, event_seq).await
+    }
+}
+
+#[async_trait]
+impl UpsertEventCursorFn for DbTransaction {
+    async fn upsert_event_cursor(
+        &self,
+        subscription: &str,
+        tx_digest: &str,
+        event_seq: i64
+    ) -> Result<(), RepositoryError> {
+        private_members::upsert_event_cursor_inner(&mut **self.lock_conn().await, subscription, tx_digest, event_seq).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repo::base::{ BeginTransactionFn, TransactionFn };
+    use super::*;
+    use lazy_static::lazy_static;
+    use std::sync::{ Arc, RwLock };
+
+    #[derive(Clone)]
+    #[allow(unused)]
+    struct Fixtures {
+        db_repo: DbRepo
+    }
+
+    /// Helps prevent clashes between other running tests, by adding unique prefix values for data
+    const PREFIX: &str = "TestEventCursor";
+
+    lazy_static! {
+        static ref FIXTURES: Arc<RwLock<Option<Fixtures>>> = Arc::new(RwLock::new(None));
+    }
+
+    async fn setup_fixtures() {
+        let fixtures = Arc::clone(&FIXTURES);
+        let mut fx = fixtures.write().unwrap();
+        match fx.clone() {
+            Some(_) => (),
+            None => {
+                let db_repo = DbRepo::init().await;
+
+                *fx = Some(Fixtures { db_repo });
+            }
+        }
+    }
+
+    lazy_static! {
+        static ref RT: tokio::runtime::Runtime = {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+            rt.block_on(async {
+                setup_fixtures().await;
+            });
+
+            rt
+        };
+    }
+
+    fn fixtures() -> Fixtures {
+        Arc::clone(&FIXTURES).read().unwrap().clone().unwrap()
+    }
+
+    /// Subscriptions are unique per row, so every test run uses its own
+    fn get_subscription() -> String {
+        format!("{}{}", PREFIX, rand::random::<u32>())
+    }
+
+    mod test_mod_upsert_event_cursor {
+        use super::*;
+
+        async fn test_upsert_event_cursor_body() {
+            let fixtures = fixtures();
+            let subscription = get_subscription();
+            assert!(fixtures.db_repo.query_event_cursor(&subscription).await.unwrap().is_none());
+
+            fixtures.db_repo.upsert_event_cursor(&subscription, "digest1", 0).await.unwrap();
+            fixtures.db_repo.upsert_event_cursor(&subscription, "digest2", 3).await.unwrap();
+
+            let cursor = fixtures.db_repo.query_event_cursor(&subscription).await.unwrap().unwrap();
+            assert!(cursor.subscription == subscription);
+            assert!(cursor.tx_digest == "digest2");
+            assert!(cursor.event_seq == 3);
+        }
+
+        #[test]
+        fn test_upsert_event_cursor() {
+            RT.block_on(test_upsert_event_cursor_body())
+        }
+
+        async fn test_upsert_event_cursor_rolls_back_body() {
+            let fixtures = fixtures();
+            let subscription = get_subscription();
+
+            let tx = fixtures.db_repo.begin_transaction().await.unwrap();
+            tx.upsert_event_cursor(&subscription, "digest1", 0).await.unwrap();
+            tx.rollback().await.unwrap();
+
+            assert!(fixtures.db_repo.query_event_cursor(&subscription).await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_upsert_event_cursor_rolls_back() {
+            RT.block_on(test_upsert_event_cursor_rolls_back_body())
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+/// Last chain event a subscription has processed, for sui the transaction digest and event sequence
+#[derive(Deserialize, Serialize, FromRow, Clone, Debug)]
+pub struct EventCursorQueryResult {
+    pub subscription: String,
+    pub updated_at: DateTime<Utc>,
+    pub tx_digest: String,
+    pub event_seq: i64,
+}
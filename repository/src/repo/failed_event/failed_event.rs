@@ -0,0 +1,473 @@
+use crate::repo::base::{ DbRepo, DbConnGetter, DbTransaction, EntityId };
+use crate::repo::error::{ RepositoryError, check_value_len };
+use crate::repo::failed_event::model::{ FailedEventCreate, FailedEventQueryResult };
+use async_trait::async_trait;
+use chrono::{ DateTime, Utc };
+use sqlx::{ Pool, Postgres, PgExecutor };
+use mockall::automock;
+
+mod private_members {
+    use super::*;
+
+    pub async fn insert_failed_event_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        params: FailedEventCreate
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("subscription", &params.subscription, 250)?;
+        check_value_len("tx_digest", &params.tx_digest, 100)?;
+
+        let result = sqlx::query_as::<_, EntityId>(
+            r"
+                insert into failed_event (subscription, tx_digest, event_seq, payload, error, next_retry_at)
+                    values ($1, $2, $3, $4, $5, $6)
+                on conflict (subscription, tx_digest, event_seq)
+                do update set
+                    payload = excluded.payload,
+                    error = excluded.error,
+                    attempts = failed_event.attempts + 1,
+                    next_retry_at = excluded.next_retry_at,
+                    updated_at = current_timestamp
+                returning id
+            "
+        )
+        .bind(params.subscription)
+        .bind(params.tx_digest)
+        .bind(params.event_seq)
+        .bind(params.payload)
+        .bind(params.error)
+        .bind(params.next_retry_at)
+        .fetch_one(conn).await?;
+
+        Ok(result.id)
+    }
+
+    pub async fn query_failed_event_inner(
+        conn: &Pool<Postgres>,
+        id: i64
+    ) -> Result<Option<FailedEventQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, FailedEventQueryResult>("select * from failed_event where id = $1")
+            .bind(id)
+            .fetch_optional(conn).await?)
+    }
+
+    pub async fn query_failed_events_inner(
+        conn: &Pool<Postgres>,
+        last_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, FailedEventQueryResult>(
+            r"
+                select * from failed_event
+                where ($1::bigint is null or id > $1)
+                order by id
+                limit $2
+            "
+        )
+        .bind(last_id)
+        .bind(page_size)
+        .fetch_all(conn).await?)
+    }
+
+    pub async fn query_due_failed_events_inner(
+        conn: &Pool<Postgres>,
+        now: DateTime<Utc>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, FailedEventQueryResult>(
+            r"
+                select * from failed_event
+                where next_retry_at <= $1
+                order by next_retry_at, id
+                limit $2
+            "
+        )
+        .bind(now)
+        .bind(page_size)
+        .fetch_all(conn).await?)
+    }
+
+    pub async fn update_failed_event_retry_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        id: i64,
+        error: &str,
+        next_retry_at: Option<DateTime<Utc>>
+    ) -> Result<(), RepositoryError> {
+        let result = sqlx::query::<_>(
+            r"
+                update failed_event
+                set error = $1, attempts = attempts + 1, next_retry_at = $2, updated_at = current_timestamp
+                where id = $3
+            "
+        )
+        .bind(error)
+        .bind(next_retry_at)
+        .bind(id)
+        .execute(conn).await?;
+
+        match result.rows_affected() {
+            0 => Err(RepositoryError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn delete_failed_event_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        id: i64
+    ) -> Result<(), RepositoryError> {
+        let result = sqlx::query::<_>("delete from failed_event where id = $1")
+            .bind(id)
+            .execute(conn).await?;
+
+        match result.rows_affected() {
+            0 => Err(RepositoryError::NotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Dead letters an event. Failing the same event of a subscription again updates its row and counts
+/// another attempt, so a redelivered event never shows up twice
+#[automock]
+#[async_trait]
+pub trait InsertFailedEventFn {
+    async fn insert_failed_event(
+        &self,
+        params: FailedEventCreate
+    ) -> Result<i64, RepositoryError>;
+}
+
+#[async_trait]
+impl InsertFailedEventFn for DbRepo {
+    async fn insert_failed_event(
+        &self,
+        params: FailedEventCreate
+    ) -> Result<i64, RepositoryError> {
+        private_members::insert_failed_event_inner(self.get_conn(), params).await
+    }
+}
+
+#[async_trait]
+impl InsertFailedEventFn for DbTransaction {
+    async fn insert_failed_event(
+        &self,
+        params: FailedEventCreate
+    ) -> Result<i64, RepositoryError> {
+        private_members::insert_failed_event_inner(&mut **self.lock_conn().await, params).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait QueryFailedEventFn {
+    async fn query_failed_event(
+        &self,
+        id: i64
+    ) -> Result<Option<FailedEventQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryFailedEventFn for DbRepo {
+    async fn query_failed_event(
+        &self,
+        id: i64
+    ) -> Result<Option<FailedEventQueryResult>, RepositoryError> {
+        private_members::query_failed_event_inner(self.get_conn(), id).await
+    }
+}
+
+/// Every failed event oldest first, pass the last id of the previous page to get the next one
+#[automock]
+#[async_trait]
+pub trait QueryFailedEventsFn {
+    async fn query_failed_events(
+        &self,
+        last_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryFailedEventsFn for DbRepo {
+    async fn query_failed_events(
+        &self,
+        last_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError> {
+        private_members::query_failed_events_inner(self.get_conn(), last_id, page_size).await
+    }
+}
+
+/// Failed events whose next retry is at or before now, the longest waiting first
+#[automock]
+#[async_trait]
+pub trait QueryDueFailedEventsFn {
+    async fn query_due_failed_events(
+        &self,
+        now: DateTime<Utc>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryDueFailedEventsFn for DbRepo {
+    async fn query_due_failed_events(
+        &self,
+        now: DateTime<Utc>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError> {
+        private_members::query_due_failed_events_inner(self.get_conn(), now, page_size).await
+    }
+}
+
+/// Records another failed attempt, a next_retry_at of None stops automatic retries
+#[automock]
+#[async_trait]
+pub trait UpdateFailedEventRetryFn {
+    async fn update_failed_event_retry(
+        &self,
+        id: i64,
+        error: &str,
+        next_retry_at: Option<DateTime<Utc>>
+    ) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl UpdateFailedEventRetryFn for DbRepo {
+    async fn update_failed_event_retry(
+        &self,
+        id: i64,
+        error: &str,
+        next_retry_at: Option<DateTime<Utc>>
+    ) -> Result<(), RepositoryError> {
+        private_members::update_failed_event_retry_inner(self.get_conn(), id, error, next_retry_at).await
+    }
+}
+
+#[async_trait]
+impl UpdateFailedEventRetryFn for DbTransaction {
+    async fn update_failed_event_retry(
+        &self,
+        id: i64,
+        error: &str,
+        next_retry_at: Option<DateTime<Utc>>
+    ) -> Result<(), RepositoryError> {
+        private_members::update_failed_event_retry_inner(&mut **self.lock_conn().await, id, error, next_retry_at).await
+    }
+}
+
+/// Removes a failed event once it has been applied or discarded
+#[automock]
+#[async_trait]
+pub trait DeleteFailedEventFn {
+    async fn delete_failed_event(
+        &self,
+        id: i64
+    ) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl DeleteFailedEventFn for DbRepo {
+    async fn delete_failed_event(
+        &self,
+        id: i64
+    ) -> Result<(), RepositoryError> {
+        private_members::delete_failed_event_inner(self.get_conn(), id).await
+    }
+}
+
+#[async_trait]
+impl DeleteFailedEventFn for DbTransaction {
+    async fn delete_failed_event(
+        &self,
+        id: i64
+    ) -> Result<(), RepositoryError> {
+        private_members::delete_failed_event_inner(&mut **self.lock_conn().await, id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repo::base::{ BeginTransactionFn, TransactionFn };
+    use super::*;
+    use chrono::Duration;
+    use lazy_static::lazy_static;
+    use std::sync::{ Arc, RwLock };
+
+    #[derive(Clone)]
+    #[allow(unused)]
+    struct Fixtures {
+        db_repo: DbRepo
+    }
+
+    /// Helps prevent clashes between other running tests, by adding unique prefix values for data
+    const PREFIX: &str = "TestFailedEvent";
+
+    lazy_static! {
+        static ref FIXTURES: Arc<RwLock<Option<Fixtures>>> = Arc::new(RwLock::new(None));
+    }
+
+    async fn setup_fixtures() {
+        let fixtures = Arc::clone(&FIXTURES);
+        let mut fx = fixtures.write().unwrap();
+        match fx.clone() {
+            Some(_) => (),
+            None => {
+                let db_repo = DbRepo::init().await;
+
+                *fx = Some(Fixtures { db_repo });
+            }
+        }
+    }
+
+    lazy_static! {
+        static ref RT: tokio::runtime::Runtime = {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+            rt.block_on(async {
+                setup_fixtures().await;
+            });
+
+            rt
+        };
+    }
+
+    fn fixtures() -> Fixtures {
+        Arc::clone(&FIXTURES).read().unwrap().clone().unwrap()
+    }
+
+    /// Failed events are unique per subscription and event, so every test uses its own subscription
+    fn get_failed_event_create(next_retry_at: Option<DateTime<Utc>>) -> FailedEventCreate {
+        FailedEventCreate {
+            subscription: format!("{}{}", PREFIX, rand::random::<u32>()),
+            tx_digest: "digest".to_string(),
+            event_seq: 0,
+            payload: "{}".to_string(),
+            error: "first error".to_string(),
+            next_retry_at,
+        }
+    }
+
+    mod test_mod_insert_failed_event {
+        use super::*;
+
+        async fn test_insert_failed_event_body() {
+            let fixtures = fixtures();
+            let params = get_failed_event_create(None);
+
+            let id = fixtures.db_repo.insert_failed_event(params.clone()).await.unwrap();
+            let mut redelivered = params.clone();
+            redelivered.error = "second error".to_string();
+            let redelivered_id = fixtures.db_repo.insert_failed_event(redelivered).await.unwrap();
+
+            let failed_event = fixtures.db_repo.query_failed_event(id).await.unwrap().unwrap();
+            assert!(redelivered_id == id);
+            assert!(failed_event.subscription == params.subscription);
+            assert!(failed_event.error == "second error");
+            assert!(failed_event.attempts == 2);
+            assert!(failed_event.next_retry_at.is_none());
+        }
+
+        #[test]
+        fn test_insert_failed_event() {
+            RT.block_on(test_insert_failed_event_body())
+        }
+
+        async fn test_insert_failed_event_rolls_back_body() {
+            let fixtures = fixtures();
+
+            let tx = fixtures.db_repo.begin_transaction().await.unwrap();
+            let id = tx.insert_failed_event(get_failed_event_create(None)).await.unwrap();
+            tx.rollback().await.unwrap();
+
+            assert!(fixtures.db_repo.query_failed_event(id).await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_insert_failed_event_rolls_back() {
+            RT.block_on(test_insert_failed_event_rolls_back_body())
+        }
+    }
+
+    mod test_mod_query_failed_events {
+        use super::*;
+
+        async fn test_query_failed_events_body() {
+            let fixtures = fixtures();
+            let first_id = fixtures.db_repo.insert_failed_event(get_failed_event_create(None)).await.unwrap();
+            let second_id = fixtures.db_repo.insert_failed_event(get_failed_event_create(None)).await.unwrap();
+
+            let page = fixtures.db_repo.query_failed_events(Some(first_id - 1), 2).await.unwrap();
+            let ids: Vec<i64> = page.iter().map(|failed_event| failed_event.id).collect();
+            assert!(ids == vec![first_id, second_id]);
+            let next_page = fixtures.db_repo.query_failed_events(Some(second_id), 100).await.unwrap();
+            assert!(next_page.iter().all(|failed_event| failed_event.id > second_id));
+        }
+
+        #[test]
+        fn test_query_failed_events() {
+            RT.block_on(test_query_failed_events_body())
+        }
+
+        async fn test_query_due_failed_events_body() {
+            let fixtures = fixtures();
+            let now = Utc::now();
+            let due_id = fixtures.db_repo
+                .insert_failed_event(get_failed_event_create(Some(now - Duration::seconds(1))))
+                .await
+                .unwrap();
+            let later_id = fixtures.db_repo
+                .insert_failed_event(get_failed_event_create(Some(now + Duration::hours(1))))
+                .await
+                .unwrap();
+            let parked_id = fixtures.db_repo.insert_failed_event(get_failed_event_create(None)).await.unwrap();
+
+            let due = fixtures.db_repo.query_due_failed_events(now, i16::MAX).await.unwrap();
+
+            assert!(due.iter().any(|failed_event| failed_event.id == due_id));
+            assert!(!due.iter().any(|failed_event| failed_event.id == later_id || failed_event.id == parked_id));
+        }
+
+        #[test]
+        fn test_query_due_failed_events() {
+            RT.block_on(test_query_due_failed_events_body())
+        }
+    }
+
+    mod test_mod_update_failed_event {
+        use super::*;
+
+        async fn test_update_failed_event_retry_body() {
+            let fixtures = fixtures();
+            let id = fixtures.db_repo.insert_failed_event(get_failed_event_create(Some(Utc::now()))).await.unwrap();
+
+            fixtures.db_repo.update_failed_event_retry(id, "retry error", None).await.unwrap();
+
+            let failed_event = fixtures.db_repo.query_failed_event(id).await.unwrap().unwrap();
+            assert!(failed_event.error == "retry error");
+            assert!(failed_event.attempts == 2);
+            assert!(failed_event.next_retry_at.is_none());
+            let missing = fixtures.db_repo.update_failed_event_retry(i64::MAX, "retry error", None).await;
+            assert!(matches!(missing, Err(RepositoryError::NotFound)));
+        }
+
+        #[test]
+        fn test_update_failed_event_retry() {
+            RT.block_on(test_update_failed_event_retry_body())
+        }
+
+        async fn test_delete_failed_event_body() {
+            let fixtures = fixtures();
+            let id = fixtures.db_repo.insert_failed_event(get_failed_event_create(None)).await.unwrap();
+
+            fixtures.db_repo.delete_failed_event(id).await.unwrap();
+
+            assert!(fixtures.db_repo.query_failed_event(id).await.unwrap().is_none());
+            assert!(matches!(fixtures.db_repo.delete_failed_event(id).await, Err(RepositoryError::NotFound)));
+        }
+
+        #[test]
+        fn test_delete_failed_event() {
+            RT.block_on(test_delete_failed_event_body())
+        }
+    }
+}
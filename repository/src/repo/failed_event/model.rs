@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+/// A chain event that could not be decoded or applied. The payload is the raw event as json so it can be
+/// replayed later, next_retry_at is None once the event is left for an admin to replay or discard
+#[derive(Deserialize, Serialize, FromRow, Clone, Debug)]
+pub struct FailedEventQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub subscription: String,
+    pub tx_digest: String,
+    pub event_seq: i64,
+    pub payload: String,
+    pub error: String,
+    pub attempts: i32,
+    pub next_retry_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FailedEventCreate {
+    pub subscription: String,
+    pub tx_digest: String,
+    pub event_seq: i64,
+    pub payload: String,
+    pub error: String,
+    pub next_retry_at: Option<DateTime<Utc>>,
+}
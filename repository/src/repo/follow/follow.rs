@@ -0,0 +1,570 @@
+use crate::repo::base::{ DbRepo, DbConnGetter, DbTransaction, EntityId, UpsertedId };
+use crate::repo::profile::model::ProfileQueryResult;
+use crate::repo::error::RepositoryError;
+use async_trait::async_trait;
+use sqlx::{ Pool, Postgres, PgExecutor };
+use mockall::automock;
+#[allow(unused)]
+use log::{error, info};
+
+mod private_members {
+    use super::*;
+
+    pub async fn follow_user_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError> {
+        if follower_id == following_id {
+            return Err(RepositoryError::SelfFollow);
+        }
+
+        let result = sqlx
+            ::query_as::<_, EntityId>(
+                "insert into follow (follower_id, following_id) values ($1, $2) returning id"
+            )
+            .bind(follower_id)
+            .bind(following_id)
+            .fetch_one(conn).await;
+
+        match result {
+            Ok(r) => Ok(r.id),
+            Err(e) => {
+                error!("follow_user error: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Follows unless the follow already exists, either way returns its id. The no-op update takes the row lock,
+    /// so a writer racing another insert of the same follow waits for it and gets its id instead of no row
+    pub async fn upsert_follow_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<UpsertedId, RepositoryError> {
+        if follower_id == following_id {
+            return Err(RepositoryError::SelfFollow);
+        }
+
+        let result = sqlx
+            ::query_as::<_, UpsertedId>(
+                r"
+                insert into follow (follower_id, following_id) values ($1, $2)
+                on conflict (follower_id, following_id) do update set follower_id = excluded.follower_id
+                returning id, (xmax = 0) as inserted"
+            )
+            .bind(follower_id)
+            .bind(following_id)
+            .fetch_one(conn).await;
+
+        match result {
+            Ok(r) => Ok(r),
+            Err(e) => {
+                error!("upsert_follow error: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    pub async fn unfollow_user_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<(), RepositoryError> {
+        let result = sqlx
+            ::query::<_>("delete from follow where follower_id = $1 and following_id = $2")
+            .bind(follower_id)
+            .bind(following_id)
+            .execute(conn).await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn query_is_following_inner(
+        conn: &Pool<Postgres>,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<bool, RepositoryError> {
+        let result = sqlx
+            ::query_as::<_, EntityId>(
+                "select id from follow where follower_id = $1 and following_id = $2"
+            )
+            .bind(follower_id)
+            .bind(following_id)
+            .fetch_optional(conn).await;
+
+        match result {
+            Ok(row) => Ok(row.is_some()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn query_followers_inner(
+        conn: &Pool<Postgres>,
+        following_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, ProfileQueryResult>(
+            r"
+                select pe.*
+                from follow fl
+                    join
+                profile pe
+                    on fl.follower_id = pe.id
+                where fl.following_id = $1 and ($2::bigint is null or pe.id > $2)
+                order by pe.id
+                limit $3
+            "
+        )
+        .bind(following_id)
+        .bind(last_profile_id)
+        .bind(page_size)
+        .fetch_all(conn).await?)
+    }
+
+    pub async fn query_following_inner(
+        conn: &Pool<Postgres>,
+        follower_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, ProfileQueryResult>(
+            r"
+                select pe.*
+                from follow fl
+                    join
+                profile pe
+                    on fl.following_id = pe.id
+                where fl.follower_id = $1 and ($2::bigint is null or pe.id > $2)
+                order by pe.id
+                limit $3
+            "
+        )
+        .bind(follower_id)
+        .bind(last_profile_id)
+        .bind(page_size)
+        .fetch_all(conn).await?)
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait FollowUserFn {
+    async fn follow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError>;
+}
+
+#[async_trait]
+impl FollowUserFn for DbRepo {
+    async fn follow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError> {
+        private_members::follow_user_inner(self.get_conn(), follower_id, following_id).await
+    }
+}
+
+#[async_trait]
+impl FollowUserFn for DbTransaction {
+    async fn follow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError> {
+        private_members::follow_user_inner(&mut **self.lock_conn().await, follower_id, following_id).await
+    }
+}
+
+/// Follows coming from chain events, replaying a follow that was already applied returns the existing id
+#[automock]
+#[async_trait]
+pub trait UpsertFollowFn {
+    async fn upsert_follow(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError>;
+}
+
+#[async_trait]
+impl UpsertFollowFn for DbRepo {
+    async fn upsert_follow(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError> {
+        Ok(private_members::upsert_follow_inner(self.get_conn(), follower_id, following_id).await?.id)
+    }
+}
+
+#[async_trait]
+impl UpsertFollowFn for DbTransaction {
+    async fn upsert_follow(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError> {
+        Ok(private_members::upsert_follow_inner(&mut **self.lock_conn().await, follower_id, following_id).await?.id)
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait UnfollowUserFn {
+    async fn unfollow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl UnfollowUserFn for DbRepo {
+    async fn unfollow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<(), RepositoryError> {
+        private_members::unfollow_user_inner(self.get_conn(), follower_id, following_id).await
+    }
+}
+
+#[async_trait]
+impl UnfollowUserFn for DbTransaction {
+    async fn unfollow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<(), RepositoryError> {
+        private_members::unfollow_user_inner(&mut **self.lock_conn().await, follower_id, following_id).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait QueryIsFollowingFn {
+    async fn query_is_following(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<bool, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryIsFollowingFn for DbRepo {
+    async fn query_is_following(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<bool, RepositoryError> {
+        private_members::query_is_following_inner(self.get_conn(), follower_id, following_id).await
+    }
+}
+
+/// Pages are ordered by profile id, pass the last profile id of the previous page to get the next one
+#[automock]
+#[async_trait]
+pub trait QueryFollowersFn {
+    async fn query_followers(
+        &self,
+        following_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryFollowersFn for DbRepo {
+    async fn query_followers(
+        &self,
+        following_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError> {
+        private_members::query_followers_inner(self.get_conn(), following_id, last_profile_id, page_size).await
+    }
+}
+
+/// Pages are ordered by profile id, pass the last profile id of the previous page to get the next one
+#[automock]
+#[async_trait]
+pub trait QueryFollowingFn {
+    async fn query_following(
+        &self,
+        follower_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryFollowingFn for DbRepo {
+    async fn query_following(
+        &self,
+        follower_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError> {
+        private_members::query_following_inner(self.get_conn(), follower_id, last_profile_id, page_size).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repo::profile::{ profile::InsertProfileFn, model::ProfileCreate };
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+    use super::*;
+    use fake::{ faker::name::en::{ FirstName, LastName }, Fake };
+    use lazy_static::lazy_static;
+    use std::sync::{ Arc, RwLock };
+
+    #[derive(Clone)]
+    #[allow(unused)]
+    struct Fixtures {
+        db_repo: DbRepo
+    }
+
+    /// Helps prevent clashes between other running tests, by adding unique prefix values for data
+    const PREFIX: &str = "TestFollow";
+
+    lazy_static! {
+        static ref FIXTURES: Arc<RwLock<Option<Fixtures>>> = Arc::new(RwLock::new(None));
+    }
+
+    async fn setup_fixtures() {
+        let fixtures = Arc::clone(&FIXTURES);
+        let mut fx = fixtures.write().unwrap();
+        match fx.clone() {
+            Some(_) => (),
+            None => {
+                let db_repo = DbRepo::init().await;
+
+                *fx = Some(Fixtures { db_repo });
+            }
+        }
+    }
+
+    lazy_static! {
+        static ref RT: tokio::runtime::Runtime = {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+            rt.block_on(async {
+                setup_fixtures().await;
+            });
+
+            rt
+        };
+    }
+
+    #[allow(unused)]
+    fn fixtures() -> Fixtures {
+        Arc::clone(&FIXTURES).read().unwrap().clone().unwrap()
+    }
+
+    /// Every test gets fresh profiles so that follow pairs never clash between test runs
+    async fn insert_test_profile(db_repo: &DbRepo) -> i64 {
+        let first_name: String = FirstName().fake();
+        let last_name: String = LastName().fake();
+        db_repo
+            .insert_profile(ProfileCreate {
+                chain_asset_id: get_fake_chain_asset_id(),
+                chain_id: SUI_CHAIN_ID,
+                user_name: format!("{}{}", PREFIX, first_name),
+                full_name: format!("{} {}", first_name, last_name),
+                description: format!("{} a description", PREFIX),
+                main_url: None,
+                avatar: None,
+                owner_address: None,
+            }).await
+            .unwrap()
+    }
+
+    mod test_mod_follow_user {
+        use super::*;
+
+        async fn test_follow_user_body() {
+            let fixtures = fixtures();
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+
+            let follow_id = fixtures.db_repo.follow_user(follower_id, following_id).await.unwrap();
+            assert!(follow_id > 0);
+
+            let is_following = fixtures.db_repo.query_is_following(follower_id, following_id).await.unwrap();
+            assert!(is_following);
+            let is_followed_back = fixtures.db_repo.query_is_following(following_id, follower_id).await.unwrap();
+            assert!(!is_followed_back);
+        }
+
+        #[test]
+        fn test_follow_user() {
+            RT.block_on(test_follow_user_body())
+        }
+
+        async fn test_follow_user_rejects_duplicate_body() {
+            let fixtures = fixtures();
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+
+            fixtures.db_repo.follow_user(follower_id, following_id).await.unwrap();
+            let result = fixtures.db_repo.follow_user(follower_id, following_id).await;
+
+            assert!(matches!(result, Err(RepositoryError::UniqueViolation { .. })));
+        }
+
+        #[test]
+        fn test_follow_user_rejects_duplicate() {
+            RT.block_on(test_follow_user_rejects_duplicate_body())
+        }
+
+        async fn test_follow_user_rejects_self_body() {
+            let fixtures = fixtures();
+            let profile_id = insert_test_profile(&fixtures.db_repo).await;
+
+            let result = fixtures.db_repo.follow_user(profile_id, profile_id).await;
+
+            assert!(matches!(result, Err(RepositoryError::SelfFollow)));
+        }
+
+        #[test]
+        fn test_follow_user_rejects_self() {
+            RT.block_on(test_follow_user_rejects_self_body())
+        }
+
+        async fn test_upsert_follow_body() {
+            let fixtures = fixtures();
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+
+            let follow_id = fixtures.db_repo.upsert_follow(follower_id, following_id).await.unwrap();
+            let replayed_id = fixtures.db_repo.upsert_follow(follower_id, following_id).await.unwrap();
+
+            assert!(replayed_id == follow_id);
+            assert!(fixtures.db_repo.query_is_following(follower_id, following_id).await.unwrap());
+            let self_follow = fixtures.db_repo.upsert_follow(follower_id, follower_id).await;
+            assert!(matches!(self_follow, Err(RepositoryError::SelfFollow)));
+        }
+
+        #[test]
+        fn test_upsert_follow() {
+            RT.block_on(test_upsert_follow_body())
+        }
+
+        async fn test_upsert_follow_reports_inserted_body() {
+            let fixtures = fixtures();
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+
+            let first = private_members::upsert_follow_inner(fixtures.db_repo.get_conn(), follower_id, following_id).await.unwrap();
+            let second = private_members::upsert_follow_inner(fixtures.db_repo.get_conn(), follower_id, following_id).await.unwrap();
+
+            assert!(first.inserted);
+            assert!(!second.inserted);
+            assert!(second.id == first.id);
+        }
+
+        #[test]
+        fn test_upsert_follow_reports_inserted() {
+            RT.block_on(test_upsert_follow_reports_inserted_body())
+        }
+
+        async fn test_concurrent_upserts_return_one_follow_body() {
+            let fixtures = fixtures();
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+
+            let (first, second, third) = tokio::join!(
+                fixtures.db_repo.upsert_follow(follower_id, following_id),
+                fixtures.db_repo.upsert_follow(follower_id, following_id),
+                fixtures.db_repo.upsert_follow(follower_id, following_id)
+            );
+
+            let first = first.unwrap();
+            assert!(second.unwrap() == first);
+            assert!(third.unwrap() == first);
+        }
+
+        #[test]
+        fn test_concurrent_upserts_return_one_follow() {
+            RT.block_on(test_concurrent_upserts_return_one_follow_body())
+        }
+    }
+
+    mod test_mod_unfollow_user {
+        use super::*;
+
+        async fn test_unfollow_user_body() {
+            let fixtures = fixtures();
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+            fixtures.db_repo.follow_user(follower_id, following_id).await.unwrap();
+
+            fixtures.db_repo.unfollow_user(follower_id, following_id).await.unwrap();
+
+            let is_following = fixtures.db_repo.query_is_following(follower_id, following_id).await.unwrap();
+            assert!(!is_following);
+        }
+
+        #[test]
+        fn test_unfollow_user() {
+            RT.block_on(test_unfollow_user_body())
+        }
+    }
+
+    mod test_mod_query_followers_and_following {
+        use super::*;
+
+        async fn test_query_followers_body() {
+            let fixtures = fixtures();
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+            let mut follower_ids = vec![];
+            for _ in 0..3 {
+                let follower_id = insert_test_profile(&fixtures.db_repo).await;
+                fixtures.db_repo.follow_user(follower_id, following_id).await.unwrap();
+                follower_ids.push(follower_id);
+            }
+
+            let first_page = fixtures.db_repo.query_followers(following_id, None, 2).await.unwrap();
+            assert!(first_page.len() == 2);
+            let second_page = fixtures.db_repo
+                .query_followers(following_id, Some(first_page.last().unwrap().id), 2).await
+                .unwrap();
+            assert!(second_page.len() == 1);
+
+            let mut ids: Vec<i64> = first_page.iter().chain(second_page.iter()).map(|p| p.id).collect();
+            ids.sort();
+            assert!(ids == follower_ids);
+        }
+
+        #[test]
+        fn test_query_followers() {
+            RT.block_on(test_query_followers_body())
+        }
+
+        async fn test_query_following_body() {
+            let fixtures = fixtures();
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+            fixtures.db_repo.follow_user(follower_id, following_id).await.unwrap();
+
+            let following = fixtures.db_repo.query_following(follower_id, None, 10).await.unwrap();
+
+            assert!(following.len() == 1);
+            assert!(following[0].id == following_id);
+        }
+
+        #[test]
+        fn test_query_following() {
+            RT.block_on(test_query_following_body())
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use crate::repo::auth::auth::{
+    InsertAddressChallengeFn, InsertAuthChallengeFn, InsertSessionFn, QuerySessionByTokenHashFn, QuerySessionFn,
+    QuerySessionsByProfileFn, RevokeAllSessionsFn, RevokeSessionFn, RotateSessionTokenFn, TakeAuthChallengeFn
+};
+use crate::repo::auth::model::{ AuthChallengeQueryResult, SessionQueryResult };
+use crate::repo::error::{ RepositoryError, check_value_len };
+use crate::repo::in_memory::in_memory::{ InMemoryRepo, InMemoryState };
+use async_trait::async_trait;
+use chrono::{ DateTime, Utc };
+
+impl InMemoryState {
+    fn insert_challenge(
+        &mut self,
+        profile_id: Option<i64>,
+        address: Option<&str>,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("nonce", nonce, 64)?;
+        if let Some(address) = address {
+            check_value_len("address", address, 66)?;
+        }
+        if let Some(profile_id) = profile_id {
+            self.check_profile_exists("profile_id", profile_id)?;
+        }
+
+        let now = InMemoryState::now();
+        self.auth_challenges.retain(|_, challenge| challenge.expires_at > now);
+        if self.auth_challenges.values().any(|challenge| challenge.nonce == nonce) {
+            return Err(RepositoryError::UniqueViolation { field: "nonce".to_string() });
+        }
+        let outstanding = self.auth_challenges.values()
+            .filter(|challenge| challenge.profile_id == profile_id && challenge.address.as_deref() == address)
+            .count();
+        if outstanding as i64 >= max_outstanding {
+            let field = if profile_id.is_some() { "profile_id" } else { "address" };
+            return Err(RepositoryError::LimitReached { field: field.to_string(), max: max_outstanding });
+        }
+        let id = self.next_id();
+        self.auth_challenges.insert(id, AuthChallengeQueryResult {
+            id,
+            created_at: now,
+            profile_id,
+            address: address.map(|address| address.to_string()),
+            nonce: nonce.to_string(),
+            expires_at,
+        });
+
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl InsertAuthChallengeFn for InMemoryRepo {
+    async fn insert_auth_challenge(
+        &self,
+        profile_id: i64,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError> {
+        self.write().insert_challenge(Some(profile_id), None, nonce, expires_at, max_outstanding)
+    }
+}
+
+#[async_trait]
+impl InsertAddressChallengeFn for InMemoryRepo {
+    async fn insert_address_challenge(
+        &self,
+        address: &str,
+        nonce: &str,
+        expires_at: DateTime<Utc>,
+        max_outstanding: i64
+    ) -> Result<i64, RepositoryError> {
+        self.write().insert_challenge(None, Some(address), nonce, expires_at, max_outstanding)
+    }
+}
+
+#[async_trait]
+impl TakeAuthChallengeFn for InMemoryRepo {
+    async fn take_auth_challenge(
+        &self,
+        nonce: &str
+    ) -> Result<Option<AuthChallengeQueryResult>, RepositoryError> {
+        let mut state = self.write();
+        let id = state.auth_challenges.values().find(|challenge| challenge.nonce == nonce).map(|challenge| challenge.id);
+
+        Ok(id.and_then(|id| state.auth_challenges.remove(&id)))
+    }
+}
+
+#[async_trait]
+impl InsertSessionFn for InMemoryRepo {
+    async fn insert_session(
+        &self,
+        profile_id: i64,
+        token_hash: &str,
+        device_name: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("token_hash", token_hash, 64)?;
+        check_value_len("device_name", device_name, 100)?;
+
+        let mut state = self.write();
+        state.check_profile_exists("profile_id", profile_id)?;
+        if state.sessions.values().any(|session| session.token_hash == token_hash) {
+            return Err(RepositoryError::UniqueViolation { field: "token_hash".to_string() });
+        }
+        let id = state.next_id();
+        state.sessions.insert(id, SessionQueryResult {
+            id,
+            created_at: InMemoryState::now(),
+            profile_id,
+            token_hash: token_hash.to_string(),
+            device_name: device_name.to_string(),
+            refreshed_at: None,
+            expires_at,
+        });
+
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl QuerySessionByTokenHashFn for InMemoryRepo {
+    async fn query_session_by_token_hash(
+        &self,
+        token_hash: &str
+    ) -> Result<Option<SessionQueryResult>, RepositoryError> {
+        Ok(self.read().sessions.values().find(|session| session.token_hash == token_hash).cloned())
+    }
+}
+
+#[async_trait]
+impl QuerySessionFn for InMemoryRepo {
+    async fn query_session(
+        &self,
+        id: i64
+    ) -> Result<Option<SessionQueryResult>, RepositoryError> {
+        Ok(self.read().sessions.get(&id).cloned())
+    }
+}
+
+#[async_trait]
+impl RotateSessionTokenFn for InMemoryRepo {
+    async fn rotate_session_token(
+        &self,
+        id: i64,
+        token_hash: &str,
+        new_token_hash: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<(), RepositoryError> {
+        check_value_len("token_hash", new_token_hash, 64)?;
+
+        let mut state = self.write();
+        let session = state.sessions.get_mut(&id)
+            .filter(|session| session.token_hash == token_hash)
+            .ok_or(RepositoryError::NotFound)?;
+        session.token_hash = new_token_hash.to_string();
+        session.expires_at = expires_at;
+        session.refreshed_at = Some(InMemoryState::now());
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl QuerySessionsByProfileFn for InMemoryRepo {
+    async fn query_sessions_by_profile(
+        &self,
+        profile_id: i64
+    ) -> Result<Vec<SessionQueryResult>, RepositoryError> {
+        let now = InMemoryState::now();
+
+        Ok(self.read().sessions.values()
+            .rev()
+            .filter(|session| session.profile_id == profile_id && session.expires_at > now)
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl RevokeSessionFn for InMemoryRepo {
+    async fn revoke_session(
+        &self,
+        profile_id: i64,
+        id: i64
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.write();
+        match state.sessions.get(&id) {
+            Some(session) if session.profile_id == profile_id => {
+                state.sessions.remove(&id);
+                Ok(())
+            },
+            _ => Err(RepositoryError::NotFound),
+        }
+    }
+}
+
+#[async_trait]
+impl RevokeAllSessionsFn for InMemoryRepo {
+    async fn revoke_all_sessions(
+        &self,
+        profile_id: i64
+    ) -> Result<u64, RepositoryError> {
+        let mut state = self.write();
+        let count = state.sessions.len();
+        state.sessions.retain(|_, session| session.profile_id != profile_id);
+
+        Ok((count - state.sessions.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+    use chrono::Duration;
+
+    async fn insert_profile(repo: &InMemoryRepo) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: "jill".to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: None,
+        }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_auth_challenge_is_taken_once() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_profile(&repo).await;
+        let expires_at = Utc::now() + Duration::minutes(5);
+
+        repo.insert_auth_challenge(profile_id, "nonce", expires_at, 5).await.unwrap();
+
+        assert!(matches!(
+            repo.insert_auth_challenge(profile_id, "nonce", expires_at, 5).await,
+            Err(RepositoryError::UniqueViolation { .. })
+        ));
+        assert!(matches!(
+            repo.insert_auth_challenge(profile_id + 1, "other", expires_at, 5).await,
+            Err(RepositoryError::ForeignKeyViolation { .. })
+        ));
+        assert!(repo.take_auth_challenge("nonce").await.unwrap().unwrap().profile_id == Some(profile_id));
+        assert!(repo.take_auth_challenge("nonce").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_auth_challenges_are_purged_and_capped() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_profile(&repo).await;
+        let expires_at = Utc::now() + Duration::minutes(5);
+
+        repo.insert_auth_challenge(profile_id, "expired", Utc::now() - Duration::minutes(1), 2).await.unwrap();
+        repo.insert_auth_challenge(profile_id, "first", expires_at, 2).await.unwrap();
+        repo.insert_auth_challenge(profile_id, "second", expires_at, 2).await.unwrap();
+
+        assert!(matches!(
+            repo.insert_auth_challenge(profile_id, "third", expires_at, 2).await,
+            Err(RepositoryError::LimitReached { max: 2, .. })
+        ));
+        assert!(repo.take_auth_challenge("expired").await.unwrap().is_none());
+        assert!(repo.take_auth_challenge("first").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_address_challenges_are_capped_per_address() {
+        let repo = InMemoryRepo::new();
+        let expires_at = Utc::now() + Duration::minutes(5);
+
+        repo.insert_address_challenge("0xa1", "first", expires_at, 1).await.unwrap();
+        repo.insert_address_challenge("0xb2", "other", expires_at, 1).await.unwrap();
+
+        assert!(matches!(
+            repo.insert_address_challenge("0xa1", "second", expires_at, 1).await,
+            Err(RepositoryError::LimitReached { .. })
+        ));
+        let challenge = repo.take_auth_challenge("first").await.unwrap().unwrap();
+        assert!(challenge.address.as_deref() == Some("0xa1") && challenge.profile_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_session_by_token_hash() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_profile(&repo).await;
+
+        let id = repo.insert_session(profile_id, "hash", "laptop", Utc::now() + Duration::days(1)).await.unwrap();
+
+        let session = repo.query_session_by_token_hash("hash").await.unwrap().unwrap();
+        assert!(session.id == id && session.profile_id == profile_id);
+        assert!(repo.query_session_by_token_hash("other").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_and_revoke_sessions() {
+        let repo = InMemoryRepo::new();
+        let profile_id = insert_profile(&repo).await;
+        let expires_at = Utc::now() + Duration::days(1);
+        let laptop_id = repo.insert_session(profile_id, "laptop_hash", "laptop", expires_at).await.unwrap();
+        let phone_id = repo.insert_session(profile_id, "phone_hash", "phone", expires_at).await.unwrap();
+        repo.insert_session(profile_id, "old_hash", "old", Utc::now() - Duration::days(1)).await.unwrap();
+
+        repo.rotate_session_token(laptop_id, "laptop_hash", "rotated_hash", expires_at).await.unwrap();
+
+        assert!(repo.query_session_by_token_hash("rotated_hash").await.unwrap().unwrap().refreshed_at.is_some());
+        assert!(matches!(
+            repo.rotate_session_token(laptop_id, "laptop_hash", "other_hash", expires_at).await,
+            Err(RepositoryError::NotFound)
+        ));
+        let session_ids: Vec<i64> = repo.query_sessions_by_profile(profile_id).await.unwrap().iter().map(|session| session.id).collect();
+        assert!(session_ids == vec![phone_id, laptop_id]);
+        assert!(matches!(repo.revoke_session(profile_id + 1, phone_id).await, Err(RepositoryError::NotFound)));
+        repo.revoke_session(profile_id, phone_id).await.unwrap();
+        assert!(repo.query_session(phone_id).await.unwrap().is_none());
+        assert!(repo.revoke_all_sessions(profile_id).await.unwrap() == 2);
+        assert!(repo.query_sessions_by_profile(profile_id).await.unwrap().is_empty());
+    }
+}
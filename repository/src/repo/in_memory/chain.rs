@@ -0,0 +1,31 @@
+use crate::repo::chain::chain::QueryChainIdByNameFn;
+use crate::repo::error::RepositoryError;
+use crate::repo::in_memory::in_memory::InMemoryRepo;
+use async_trait::async_trait;
+
+#[async_trait]
+impl QueryChainIdByNameFn for InMemoryRepo {
+    async fn query_chain_id_by_name(
+        &self,
+        name: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        Ok(self.read().chains.iter().find(|(_, chain_name)| chain_name.as_str() == name).map(|(id, _)| *id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::chain::chain::LOCAL_CHAIN;
+    use crate::test_helpers::fixtures::SUI_CHAIN_ID;
+
+    #[tokio::test]
+    async fn test_query_chain_id_by_name() {
+        let repo = InMemoryRepo::new();
+
+        assert!(repo.query_chain_id_by_name("sui").await.unwrap() == Some(SUI_CHAIN_ID));
+        assert!(repo.query_chain_id_by_name("aptos").await.unwrap() == Some(2));
+        assert!(repo.query_chain_id_by_name(LOCAL_CHAIN).await.unwrap() == Some(3));
+        assert!(repo.query_chain_id_by_name("solana").await.unwrap().is_none());
+    }
+}
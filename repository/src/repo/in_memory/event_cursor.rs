@@ -0,0 +1,57 @@
+use crate::repo::error::{ RepositoryError, check_value_len };
+use crate::repo::event_cursor::event_cursor::{ QueryEventCursorFn, UpsertEventCursorFn };
+use crate::repo::event_cursor::model::EventCursorQueryResult;
+use crate::repo::in_memory::in_memory::{ InMemoryRepo, InMemoryState };
+use async_trait::async_trait;
+
+#[async_trait]
+impl QueryEventCursorFn for InMemoryRepo {
+    async fn query_event_cursor(
+        &self,
+        subscription: &str
+    ) -> Result<Option<EventCursorQueryResult>, RepositoryError> {
+        Ok(self.read().event_cursors.get(subscription).cloned())
+    }
+}
+
+#[async_trait]
+impl UpsertEventCursorFn for InMemoryRepo {
+    async fn upsert_event_cursor(
+        &self,
+        subscription: &str,
+        tx_digest: &str,
+        event_seq: i64
+    ) -> Result<(), RepositoryError> {
+        check_value_len("subscription", subscription, 250)?;
+        check_value_len("tx_digest", tx_digest, 100)?;
+
+        self.write().event_cursors.insert(subscription.to_string(), EventCursorQueryResult {
+            subscription: subscription.to_string(),
+            updated_at: InMemoryState::now(),
+            tx_digest: tx_digest.to_string(),
+            event_seq,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::base::{ BeginTransactionFn, TransactionFn };
+
+    #[tokio::test]
+    async fn test_upsert_event_cursor() {
+        let repo = InMemoryRepo::new();
+        assert!(repo.query_event_cursor("sui").await.unwrap().is_none());
+
+        repo.upsert_event_cursor("sui", "digest1", 0).await.unwrap();
+        let tx = repo.begin_transaction().await.unwrap();
+        tx.upsert_event_cursor("sui", "digest2", 1).await.unwrap();
+        tx.rollback().await.unwrap();
+
+        let cursor = repo.query_event_cursor("sui").await.unwrap().unwrap();
+        assert!(cursor.tx_digest == "digest1" && cursor.event_seq == 0);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::repo::error::{ RepositoryError, check_value_len };
+use crate::repo::failed_event::failed_event::{
+    DeleteFailedEventFn, InsertFailedEventFn, QueryDueFailedEventsFn, QueryFailedEventFn, QueryFailedEventsFn,
+    UpdateFailedEventRetryFn
+};
+use crate::repo::failed_event::model::{ FailedEventCreate, FailedEventQueryResult };
+use crate::repo::in_memory::in_memory::{ InMemoryRepo, InMemoryState };
+use async_trait::async_trait;
+use chrono::{ DateTime, Utc };
+
+#[async_trait]
+impl InsertFailedEventFn for InMemoryRepo {
+    async fn insert_failed_event(
+        &self,
+        params: FailedEventCreate
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("subscription", &params.subscription, 250)?;
+        check_value_len("tx_digest", &params.tx_digest, 100)?;
+
+        let mut state = self.write();
+        let now = InMemoryState::now();
+        let existing = state.failed_events.values_mut().find(|failed_event| {
+            failed_event.subscription == params.subscription
+                && failed_event.tx_digest == params.tx_digest
+                && failed_event.event_seq == params.event_seq
+        });
+        if let Some(failed_event) = existing {
+            failed_event.payload = params.payload;
+            failed_event.error = params.error;
+            failed_event.attempts += 1;
+            failed_event.next_retry_at = params.next_retry_at;
+            failed_event.updated_at = now;
+            return Ok(failed_event.id);
+        }
+
+        let id = state.next_id();
+        state.failed_events.insert(id, FailedEventQueryResult {
+            id,
+            created_at: now,
+            updated_at: now,
+            subscription: params.subscription,
+            tx_digest: params.tx_digest,
+            event_seq: params.event_seq,
+            payload: params.payload,
+            error: params.error,
+            attempts: 1,
+            next_retry_at: params.next_retry_at,
+        });
+
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl QueryFailedEventFn for InMemoryRepo {
+    async fn query_failed_event(
+        &self,
+        id: i64
+    ) -> Result<Option<FailedEventQueryResult>, RepositoryError> {
+        Ok(self.read().failed_events.get(&id).cloned())
+    }
+}
+
+#[async_trait]
+impl QueryFailedEventsFn for InMemoryRepo {
+    async fn query_failed_events(
+        &self,
+        last_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError> {
+        Ok(self.read().failed_events
+            .range(last_id.map_or(i64::MIN, |id| id + 1)..)
+            .map(|(_, failed_event)| failed_event.clone())
+            .take(page_size.max(0) as usize)
+            .collect())
+    }
+}
+
+#[async_trait]
+impl QueryDueFailedEventsFn for InMemoryRepo {
+    async fn query_due_failed_events(
+        &self,
+        now: DateTime<Utc>,
+        page_size: i16
+    ) -> Result<Vec<FailedEventQueryResult>, RepositoryError> {
+        let state = self.read();
+        let mut due: Vec<&FailedEventQueryResult> = state.failed_events.values()
+            .filter(|failed_event| failed_event.next_retry_at.is_some_and(|next_retry_at| next_retry_at <= now))
+            .collect();
+        due.sort_by_key(|failed_event| (failed_event.next_retry_at, failed_event.id));
+
+        Ok(due.into_iter().take(page_size.max(0) as usize).cloned().collect())
+    }
+}
+
+#[async_trait]
+impl UpdateFailedEventRetryFn for InMemoryRepo {
+    async fn update_failed_event_retry(
+        &self,
+        id: i64,
+        error: &str,
+        next_retry_at: Option<DateTime<Utc>>
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.write();
+        let failed_event = state.failed_events.get_mut(&id).ok_or(RepositoryError::NotFound)?;
+        failed_event.error = error.to_string();
+        failed_event.attempts += 1;
+        failed_event.next_retry_at = next_retry_at;
+        failed_event.updated_at = InMemoryState::now();
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeleteFailedEventFn for InMemoryRepo {
+    async fn delete_failed_event(
+        &self,
+        id: i64
+    ) -> Result<(), RepositoryError> {
+        self.write().failed_events.remove(&id).map(|_| ()).ok_or(RepositoryError::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn get_failed_event_create(event_seq: i64, next_retry_at: Option<DateTime<Utc>>) -> FailedEventCreate {
+        FailedEventCreate {
+            subscription: "sui".to_string(),
+            tx_digest: "digest".to_string(),
+            event_seq,
+            payload: "{}".to_string(),
+            error: "error".to_string(),
+            next_retry_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_event_lifecycle() {
+        let repo = InMemoryRepo::new();
+        let now = Utc::now();
+        let due = repo.insert_failed_event(get_failed_event_create(0, Some(now))).await.unwrap();
+        let parked = repo.insert_failed_event(get_failed_event_create(1, None)).await.unwrap();
+        assert!(repo.insert_failed_event(get_failed_event_create(1, None)).await.unwrap() == parked);
+
+        let due_ids: Vec<i64> = repo.query_due_failed_events(now, 10).await.unwrap().iter().map(|fe| fe.id).collect();
+        assert!(due_ids == vec![due]);
+        assert!(repo.query_failed_events(None, 10).await.unwrap().len() == 2);
+        assert!(repo.query_failed_events(Some(due), 10).await.unwrap()[0].attempts == 2);
+
+        repo.update_failed_event_retry(due, "retry error", Some(now + Duration::minutes(1))).await.unwrap();
+        assert!(repo.query_due_failed_events(now, 10).await.unwrap().is_empty());
+        repo.delete_failed_event(parked).await.unwrap();
+        assert!(repo.query_failed_event(parked).await.unwrap().is_none());
+        assert!(matches!(repo.delete_failed_event(parked).await, Err(RepositoryError::NotFound)));
+    }
+}
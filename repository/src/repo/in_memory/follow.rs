@@ -0,0 +1,201 @@
+use crate::repo::error::RepositoryError;
+use crate::repo::follow::follow::{
+    FollowUserFn, QueryFollowersFn, QueryFollowingFn, QueryIsFollowingFn, UnfollowUserFn, UpsertFollowFn
+};
+use crate::repo::in_memory::in_memory::{ FollowRecord, InMemoryRepo, InMemoryState };
+use crate::repo::profile::model::ProfileQueryResult;
+use async_trait::async_trait;
+
+fn page_profiles(
+    state: &InMemoryState,
+    profile_ids: impl Iterator<Item = i64>,
+    last_profile_id: Option<i64>,
+    page_size: i16
+) -> Vec<ProfileQueryResult> {
+    let mut profile_ids: Vec<i64> = profile_ids
+        .filter(|id| match last_profile_id {
+            Some(last_id) => *id > last_id,
+            None => true
+        })
+        .collect();
+    profile_ids.sort();
+
+    profile_ids
+        .into_iter()
+        .take(page_size.max(0) as usize)
+        .filter_map(|id| state.profiles.get(&id).cloned())
+        .collect()
+}
+
+impl InMemoryState {
+    pub(crate) fn insert_follow(&mut self, follower_id: i64, following_id: i64) -> Result<i64, RepositoryError> {
+        if follower_id == following_id {
+            return Err(RepositoryError::SelfFollow);
+        }
+        self.check_profile_exists("follower_id", follower_id)?;
+        self.check_profile_exists("following_id", following_id)?;
+        if self.follow_id(follower_id, following_id).is_some() {
+            return Err(RepositoryError::UniqueViolation { field: "follower_id, following_id".to_string() });
+        }
+        let id = self.next_id();
+        self.follows.push(FollowRecord { id, follower_id, following_id });
+
+        Ok(id)
+    }
+
+    fn follow_id(&self, follower_id: i64, following_id: i64) -> Option<i64> {
+        self.follows.iter()
+            .find(|fl| fl.follower_id == follower_id && fl.following_id == following_id)
+            .map(|fl| fl.id)
+    }
+}
+
+#[async_trait]
+impl FollowUserFn for InMemoryRepo {
+    async fn follow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError> {
+        self.write().insert_follow(follower_id, following_id)
+    }
+}
+
+#[async_trait]
+impl UpsertFollowFn for InMemoryRepo {
+    /// The lookup and the insert happen under one write lock, so concurrent upserts of a follow get the same id
+    async fn upsert_follow(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<i64, RepositoryError> {
+        let mut state = self.write();
+        match state.follow_id(follower_id, following_id) {
+            Some(id) => Ok(id),
+            None => state.insert_follow(follower_id, following_id),
+        }
+    }
+}
+
+#[async_trait]
+impl UnfollowUserFn for InMemoryRepo {
+    async fn unfollow_user(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<(), RepositoryError> {
+        self.write().follows.retain(|fl| !(fl.follower_id == follower_id && fl.following_id == following_id));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl QueryIsFollowingFn for InMemoryRepo {
+    async fn query_is_following(
+        &self,
+        follower_id: i64,
+        following_id: i64
+    ) -> Result<bool, RepositoryError> {
+        Ok(self.read().follows.iter().any(|fl| fl.follower_id == follower_id && fl.following_id == following_id))
+    }
+}
+
+#[async_trait]
+impl QueryFollowersFn for InMemoryRepo {
+    async fn query_followers(
+        &self,
+        following_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError> {
+        let state = self.read();
+        let follower_ids = state.follows.iter()
+            .filter(|fl| fl.following_id == following_id)
+            .map(|fl| fl.follower_id);
+
+        Ok(page_profiles(&state, follower_ids, last_profile_id, page_size))
+    }
+}
+
+#[async_trait]
+impl QueryFollowingFn for InMemoryRepo {
+    async fn query_following(
+        &self,
+        follower_id: i64,
+        last_profile_id: Option<i64>,
+        page_size: i16
+    ) -> Result<Vec<ProfileQueryResult>, RepositoryError> {
+        let state = self.read();
+        let following_ids = state.follows.iter()
+            .filter(|fl| fl.follower_id == follower_id)
+            .map(|fl| fl.following_id);
+
+        Ok(page_profiles(&state, following_ids, last_profile_id, page_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    async fn insert_profile(repo: &InMemoryRepo, user_name: &str) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: user_name.to_string(),
+            full_name: user_name.to_string(),
+            description: "description".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: None,
+        }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_follow_and_unfollow() {
+        let repo = InMemoryRepo::new();
+        let dave = insert_profile(&repo, "dave").await;
+        let jill = insert_profile(&repo, "jill").await;
+
+        repo.follow_user(dave, jill).await.unwrap();
+        assert!(repo.query_is_following(dave, jill).await.unwrap());
+        assert!(matches!(repo.follow_user(dave, jill).await, Err(RepositoryError::UniqueViolation { .. })));
+        assert!(matches!(repo.follow_user(dave, dave).await, Err(RepositoryError::SelfFollow)));
+
+        repo.unfollow_user(dave, jill).await.unwrap();
+        assert!(!repo.query_is_following(dave, jill).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_upsert_follow() {
+        let repo = InMemoryRepo::new();
+        let dave = insert_profile(&repo, "dave").await;
+        let jill = insert_profile(&repo, "jill").await;
+
+        let follow_id = repo.upsert_follow(dave, jill).await.unwrap();
+
+        assert!(repo.upsert_follow(dave, jill).await.unwrap() == follow_id);
+        assert!(matches!(repo.upsert_follow(dave, dave).await, Err(RepositoryError::SelfFollow)));
+    }
+
+    #[tokio::test]
+    async fn test_query_followers_pages() {
+        let repo = InMemoryRepo::new();
+        let jill = insert_profile(&repo, "jill").await;
+        let mut follower_ids = vec![];
+        for user_name in ["dave", "tom", "linda"] {
+            let follower_id = insert_profile(&repo, user_name).await;
+            repo.follow_user(follower_id, jill).await.unwrap();
+            follower_ids.push(follower_id);
+        }
+
+        let first_page = repo.query_followers(jill, None, 2).await.unwrap();
+        let second_page = repo.query_followers(jill, Some(first_page[1].id), 2).await.unwrap();
+
+        let ids: Vec<i64> = first_page.iter().chain(second_page.iter()).map(|p| p.id).collect();
+        assert!(ids == follower_ids);
+        assert!(repo.query_following(follower_ids[0], None, 10).await.unwrap()[0].id == jill);
+    }
+}
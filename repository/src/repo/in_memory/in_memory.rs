@@ -0,0 +1,212 @@
+use crate::repo::auth::model::{ AuthChallengeQueryResult, SessionQueryResult };
+use crate::repo::base::{ BeginTransactionFn, TransactionFn };
+use crate::repo::error::RepositoryError;
+use crate::repo::event_cursor::model::EventCursorQueryResult;
+use crate::repo::failed_event::model::FailedEventQueryResult;
+use crate::repo::profile::model::ProfileQueryResult;
+use async_trait::async_trait;
+use chrono::{ DateTime, TimeZone, Utc };
+use std::collections::BTreeMap;
+use std::sync::{ Arc, RwLock, RwLockReadGuard, RwLockWriteGuard };
+
+#[derive(Clone, Debug)]
+pub(crate) struct PostRecord {
+    pub id: i64,
+    pub updated_at: DateTime<Utc>,
+    pub chain_asset_id: String,
+    pub chain_id: i64,
+    pub user_id: i64,
+    pub message: Option<String>,
+    pub image: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct PostResponseRecord {
+    pub respondee_post_id: i64,
+    pub responder_post_id: i64,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct PostShareRecord {
+    pub sharee_post_id: i64,
+    pub sharer_post_id: i64,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FollowRecord {
+    pub id: i64,
+    pub follower_id: i64,
+    pub following_id: i64,
+}
+
+/// Mirrors the tables of the Postgres schema, including the seeded chain rows
+#[derive(Clone, Debug)]
+pub(crate) struct InMemoryState {
+    last_id: i64,
+    pub chains: BTreeMap<i64, String>,
+    pub profiles: BTreeMap<i64, ProfileQueryResult>,
+    pub posts: BTreeMap<i64, PostRecord>,
+    pub post_responses: Vec<PostResponseRecord>,
+    pub post_shares: Vec<PostShareRecord>,
+    pub follows: Vec<FollowRecord>,
+    pub event_cursors: BTreeMap<String, EventCursorQueryResult>,
+    pub failed_events: BTreeMap<i64, FailedEventQueryResult>,
+    pub auth_challenges: BTreeMap<i64, AuthChallengeQueryResult>,
+    pub sessions: BTreeMap<i64, SessionQueryResult>,
+}
+
+impl Default for InMemoryState {
+    fn default() -> Self {
+        Self {
+            last_id: 0,
+            chains: BTreeMap::from([(1, "sui".to_string()), (2, "aptos".to_string()), (3, "local".to_string())]),
+            profiles: BTreeMap::new(),
+            posts: BTreeMap::new(),
+            post_responses: vec![],
+            post_shares: vec![],
+            follows: vec![],
+            event_cursors: BTreeMap::new(),
+            failed_events: BTreeMap::new(),
+            auth_challenges: BTreeMap::new(),
+            sessions: BTreeMap::new(),
+        }
+    }
+}
+
+impl InMemoryState {
+    /// One sequence is shared by all tables, ids only need to be unique and increasing
+    pub fn next_id(&mut self) -> i64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Same millisecond precision as the timestamptz(3) columns
+    pub fn now() -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(Utc::now().timestamp_millis()).unwrap()
+    }
+
+    pub fn check_chain_exists(&self, chain_id: i64) -> Result<(), RepositoryError> {
+        if !self.chains.contains_key(&chain_id) {
+            return Err(RepositoryError::ForeignKeyViolation { field: "chain_id".to_string() });
+        }
+        Ok(())
+    }
+
+    pub fn profile_id_by_chain_asset(&self, chain_id: i64, chain_asset_id: &str) -> Option<i64> {
+        self.profiles.values()
+            .find(|profile| profile.chain_id == chain_id && profile.chain_asset_id == chain_asset_id)
+            .map(|profile| profile.id)
+    }
+
+    pub fn post_id_by_chain_asset(&self, chain_id: i64, chain_asset_id: &str) -> Option<i64> {
+        self.posts.values()
+            .find(|post| post.chain_id == chain_id && post.chain_asset_id == chain_asset_id)
+            .map(|post| post.id)
+    }
+
+    pub fn check_profile_exists(&self, field: &str, profile_id: i64) -> Result<(), RepositoryError> {
+        if !self.profiles.contains_key(&profile_id) {
+            return Err(RepositoryError::ForeignKeyViolation { field: field.to_string() });
+        }
+        Ok(())
+    }
+
+    pub fn check_post_exists(&self, field: &str, post_id: i64) -> Result<(), RepositoryError> {
+        if !self.posts.contains_key(&post_id) {
+            return Err(RepositoryError::ForeignKeyViolation { field: field.to_string() });
+        }
+        Ok(())
+    }
+}
+
+/// Postgres free implementation of every repo trait, meant for tests that should not need a database.
+/// Clones share the same data. A transaction is an InMemoryRepo working on a snapshot of its parent,
+/// commit swaps the snapshot back into the parent so the last committed transaction wins.
+#[derive(Clone, Default)]
+pub struct InMemoryRepo {
+    state: Arc<RwLock<InMemoryState>>,
+    parent: Option<Arc<RwLock<InMemoryState>>>,
+}
+
+impl InMemoryRepo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, InMemoryState> {
+        self.state.read().unwrap()
+    }
+
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, InMemoryState> {
+        self.state.write().unwrap()
+    }
+}
+
+#[async_trait]
+impl TransactionFn for InMemoryRepo {
+    async fn commit(self) -> Result<(), RepositoryError> {
+        if let Some(parent) = &self.parent {
+            *parent.write().unwrap() = self.read().clone();
+        }
+        Ok(())
+    }
+
+    async fn rollback(self) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BeginTransactionFn for InMemoryRepo {
+    type Transaction = InMemoryRepo;
+
+    async fn begin_transaction(&self) -> Result<Self::Transaction, RepositoryError> {
+        Ok(InMemoryRepo {
+            state: Arc::new(RwLock::new(self.read().clone())),
+            parent: Some(self.state.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::profile::{ profile::{ InsertProfileFn, QueryProfileByUserNameFn }, model::ProfileCreate };
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    fn get_profile_create(user_name: &str) -> ProfileCreate {
+        ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: user_name.to_string(),
+            full_name: "Dave Choi".to_string(),
+            description: "I am a chef".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commit() {
+        let repo = InMemoryRepo::new();
+
+        let tx = repo.begin_transaction().await.unwrap();
+        tx.insert_profile(get_profile_create("dave")).await.unwrap();
+        assert!(repo.query_profile_by_user_name("dave").await.unwrap().is_none());
+        tx.commit().await.unwrap();
+
+        assert!(repo.query_profile_by_user_name("dave").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_rollback() {
+        let repo = InMemoryRepo::new();
+
+        let tx = repo.begin_transaction().await.unwrap();
+        tx.insert_profile(get_profile_create("dave")).await.unwrap();
+        tx.rollback().await.unwrap();
+
+        assert!(repo.query_profile_by_user_name("dave").await.unwrap().is_none());
+    }
+}
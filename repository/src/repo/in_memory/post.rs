@@ -0,0 +1,506 @@
+use crate::repo::base::EntityId;
+use crate::repo::error::{ RepositoryError, check_value_len };
+use crate::repo::in_memory::in_memory::{
+    InMemoryRepo, InMemoryState, PostRecord, PostResponseRecord, PostShareRecord
+};
+use crate::repo::post::model::{
+    PostCursor, PostThreadNode, PostWithProfileAndShareeQueryResult, PostWithProfileQueryResult
+};
+use crate::repo::post::post::{
+    InsertPostFn, InsertResponsePostFn, InsertSharePostFn, QueryHomeTimelineFn, QueryPostFn, QueryPostThreadFn,
+    QueryPostIdByChainAssetFn, QueryPostsByUserFn, QuerySharedPostFn, UpsertPostFn, UpsertResponsePostFn,
+    UpsertSharePostFn
+};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+impl InMemoryState {
+    pub(crate) fn insert_post(
+        &mut self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        image: Option<Vec<u8>>
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("chain_asset_id", chain_asset_id, 500)?;
+        if let Some(message) = &message {
+            check_value_len("message", message, 140)?;
+        }
+        self.check_chain_exists(chain_id)?;
+        self.check_profile_exists("user_id", user_id)?;
+        if self.post_id_by_chain_asset(chain_id, chain_asset_id).is_some() {
+            return Err(RepositoryError::UniqueViolation { field: "chain_id, chain_asset_id".to_string() });
+        }
+
+        let id = self.next_id();
+        self.posts.insert(id, PostRecord {
+            id,
+            updated_at: InMemoryState::now(),
+            chain_asset_id: chain_asset_id.to_string(),
+            chain_id,
+            user_id,
+            message,
+            image,
+        });
+        Ok(id)
+    }
+
+    pub(crate) fn respondee_post_id(&self, post_id: i64) -> Option<i64> {
+        self.post_responses.iter()
+            .find(|pr| pr.responder_post_id == post_id)
+            .map(|pr| pr.respondee_post_id)
+    }
+
+    pub(crate) fn post_with_profile(&self, post_id: i64) -> Option<PostWithProfileQueryResult> {
+        let post = self.posts.get(&post_id)?;
+        let profile = self.profiles.get(&post.user_id)?;
+
+        Some(PostWithProfileQueryResult {
+            id: post.id,
+            updated_at: post.updated_at,
+            chain_asset_id: post.chain_asset_id.clone(),
+            chain_id: post.chain_id,
+            message: post.message.clone(),
+            image: post.image.clone(),
+            user_id: post.user_id,
+            user_name: profile.user_name.clone(),
+            full_name: profile.full_name.clone(),
+            avatar: profile.avatar.clone(),
+            respondee_post_id: self.respondee_post_id(post.id),
+        })
+    }
+
+    pub(crate) fn post_with_sharee(&self, post_id: i64) -> Option<PostWithProfileAndShareeQueryResult> {
+        let post = self.post_with_profile(post_id)?;
+        let sharee_post = self.post_shares.iter()
+            .find(|ps| ps.sharer_post_id == post_id)
+            .and_then(|ps| self.post_with_profile(ps.sharee_post_id));
+
+        Some(PostWithProfileAndShareeQueryResult {
+            id: post.id,
+            updated_at: post.updated_at,
+            chain_asset_id: post.chain_asset_id,
+            chain_id: post.chain_id,
+            message: post.message,
+            image: post.image,
+            user_id: post.user_id,
+            user_name: post.user_name,
+            full_name: post.full_name,
+            avatar: post.avatar,
+            respondee_post_id: post.respondee_post_id,
+            sharee_post_id: sharee_post.as_ref().map(|sp| sp.id),
+            sharee_post_updated_at: sharee_post.as_ref().map(|sp| sp.updated_at),
+            sharee_post_chain_asset_id: sharee_post.as_ref().map(|sp| sp.chain_asset_id.clone()),
+            sharee_post_chain_id: sharee_post.as_ref().map(|sp| sp.chain_id),
+            sharee_post_message: sharee_post.as_ref().and_then(|sp| sp.message.clone()),
+            sharee_post_image: sharee_post.as_ref().and_then(|sp| sp.image.clone()),
+            sharee_post_user_id: sharee_post.as_ref().map(|sp| sp.user_id),
+            sharee_post_user_name: sharee_post.as_ref().map(|sp| sp.user_name.clone()),
+            sharee_post_full_name: sharee_post.as_ref().map(|sp| sp.full_name.clone()),
+            sharee_post_avatar: sharee_post.and_then(|sp| sp.avatar),
+        })
+    }
+
+    fn reply_ids_in_order(&self, post_id: i64) -> Vec<i64> {
+        let mut reply_ids: Vec<i64> = self.post_responses.iter()
+            .filter(|pr| pr.respondee_post_id == post_id)
+            .map(|pr| pr.responder_post_id)
+            .collect();
+        reply_ids.sort_by_key(|id| (self.posts[id].updated_at, *id));
+        reply_ids
+    }
+
+    fn to_thread_node(&self, post_id: i64, depth: i32, thread: &HashMap<i64, i32>) -> Option<PostThreadNode> {
+        let reply_ids = self.reply_ids_in_order(post_id);
+        let replies = reply_ids.iter()
+            .filter(|id| thread.contains_key(id))
+            .filter_map(|id| self.to_thread_node(*id, depth + 1, thread))
+            .collect();
+
+        Some(PostThreadNode {
+            post: self.post_with_profile(post_id)?,
+            depth,
+            reply_count: reply_ids.len() as i64,
+            replies,
+        })
+    }
+}
+
+#[async_trait]
+impl InsertPostFn for InMemoryRepo {
+    async fn insert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError> {
+        let id = self.write().insert_post(chain_asset_id, chain_id, user_id, Some(message.to_string()), image)?;
+        Ok(EntityId { id })
+    }
+}
+
+#[async_trait]
+impl InsertResponsePostFn for InMemoryRepo {
+    async fn insert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut state = self.write();
+        state.check_post_exists("respondee_post_id", respondee_post_id)?;
+        let id = state.insert_post(chain_asset_id, chain_id, user_id, Some(message.to_string()), image)?;
+        state.post_responses.push(PostResponseRecord { respondee_post_id, responder_post_id: id });
+
+        Ok(EntityId { id })
+    }
+}
+
+#[async_trait]
+impl InsertSharePostFn for InMemoryRepo {
+    async fn insert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut state = self.write();
+        state.check_post_exists("sharee_post_id", sharee_post_id)?;
+        let id = state.insert_post(chain_asset_id, chain_id, user_id, message, None)?;
+        state.post_shares.push(PostShareRecord { sharee_post_id, sharer_post_id: id });
+
+        Ok(EntityId { id })
+    }
+}
+
+#[async_trait]
+impl UpsertPostFn for InMemoryRepo {
+    async fn upsert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError> {
+        let mut state = self.write();
+        let id = match state.post_id_by_chain_asset(chain_id, chain_asset_id) {
+            Some(id) => id,
+            None => state.insert_post(chain_asset_id, chain_id, user_id, Some(message.to_string()), image)?,
+        };
+
+        Ok(EntityId { id })
+    }
+}
+
+#[async_trait]
+impl UpsertResponsePostFn for InMemoryRepo {
+    async fn upsert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut state = self.write();
+        if let Some(id) = state.post_id_by_chain_asset(chain_id, chain_asset_id) {
+            return Ok(EntityId { id });
+        }
+        state.check_post_exists("respondee_post_id", respondee_post_id)?;
+        let id = state.insert_post(chain_asset_id, chain_id, user_id, Some(message.to_string()), image)?;
+        state.post_responses.push(PostResponseRecord { respondee_post_id, responder_post_id: id });
+
+        Ok(EntityId { id })
+    }
+}
+
+#[async_trait]
+impl UpsertSharePostFn for InMemoryRepo {
+    async fn upsert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut state = self.write();
+        if let Some(id) = state.post_id_by_chain_asset(chain_id, chain_asset_id) {
+            return Ok(EntityId { id });
+        }
+        state.check_post_exists("sharee_post_id", sharee_post_id)?;
+        let id = state.insert_post(chain_asset_id, chain_id, user_id, message, None)?;
+        state.post_shares.push(PostShareRecord { sharee_post_id, sharer_post_id: id });
+
+        Ok(EntityId { id })
+    }
+}
+
+#[async_trait]
+impl QueryPostFn for InMemoryRepo {
+    async fn query_post(
+        &self,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileQueryResult>, RepositoryError> {
+        Ok(self.read().post_with_profile(post_id))
+    }
+}
+
+#[async_trait]
+impl QueryPostIdByChainAssetFn for InMemoryRepo {
+    async fn query_post_id_by_chain_asset(
+        &self,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        Ok(self.read().post_id_by_chain_asset(chain_id, chain_asset_id))
+    }
+}
+
+#[async_trait]
+impl QueryPostsByUserFn for InMemoryRepo {
+    async fn query_posts_by_user(
+        &self,
+        user_id: i64,
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileQueryResult>, RepositoryError> {
+        let state = self.read();
+        let mut posts: Vec<&PostRecord> = state.posts.values()
+            .filter(|post| post.user_id == user_id)
+            .filter(|post| match &cursor {
+                Some(cursor) => (post.updated_at, post.id) < (cursor.updated_at, cursor.id),
+                None => true
+            })
+            .collect();
+        posts.sort_by_key(|post| std::cmp::Reverse((post.updated_at, post.id)));
+
+        Ok(posts
+            .into_iter()
+            .take(page_size.max(0) as usize)
+            .filter_map(|post| state.post_with_profile(post.id))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl QuerySharedPostFn for InMemoryRepo {
+    async fn query_shared_post(
+        &self,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileAndShareeQueryResult>, RepositoryError> {
+        Ok(self.read().post_with_sharee(post_id).filter(|post| post.sharee_post_id.is_some()))
+    }
+}
+
+#[async_trait]
+impl QueryHomeTimelineFn for InMemoryRepo {
+    async fn query_home_timeline(
+        &self,
+        profile_id: i64,
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileAndShareeQueryResult>, RepositoryError> {
+        let state = self.read();
+        let mut posts: Vec<&PostRecord> = state.posts.values()
+            .filter(|post| {
+                post.user_id == profile_id
+                    || state.follows.iter().any(|fl| fl.follower_id == profile_id && fl.following_id == post.user_id)
+            })
+            .filter(|post| match &cursor {
+                Some(cursor) => (post.updated_at, post.id) < (cursor.updated_at, cursor.id),
+                None => true
+            })
+            .collect();
+        posts.sort_by_key(|post| std::cmp::Reverse((post.updated_at, post.id)));
+
+        Ok(posts
+            .into_iter()
+            .take(page_size.max(0) as usize)
+            .filter_map(|post| state.post_with_sharee(post.id))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl QueryPostThreadFn for InMemoryRepo {
+    async fn query_post_thread(
+        &self,
+        post_id: i64,
+        max_depth: i32
+    ) -> Result<Option<PostThreadNode>, RepositoryError> {
+        let state = self.read();
+        if !state.posts.contains_key(&post_id) {
+            return Ok(None);
+        }
+
+        let mut ancestor_ids = vec![post_id];
+        while let Some(respondee_post_id) = state.respondee_post_id(*ancestor_ids.last().unwrap()) {
+            ancestor_ids.push(respondee_post_id);
+        }
+        let post_depth = ancestor_ids.len() as i32 - 1;
+        let mut thread: HashMap<i64, i32> = ancestor_ids.iter()
+            .enumerate()
+            .map(|(hops, id)| (*id, post_depth - hops as i32))
+            .collect();
+
+        let mut level = vec![post_id];
+        for hops in 1..=max_depth {
+            level = level.iter().flat_map(|id| state.reply_ids_in_order(*id)).collect();
+            for id in level.iter() {
+                thread.insert(*id, post_depth + hops);
+            }
+        }
+
+        Ok(state.to_thread_node(*ancestor_ids.last().unwrap(), 0, &thread))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::follow::follow::FollowUserFn;
+    use crate::repo::profile::{ model::ProfileCreate, profile::InsertProfileFn };
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    async fn insert_profile(repo: &InMemoryRepo, user_name: &str) -> i64 {
+        repo.insert_profile(ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: user_name.to_string(),
+            full_name: user_name.to_string(),
+            description: "description".to_string(),
+            main_url: None,
+            avatar: None,
+            owner_address: None,
+        }).await.unwrap()
+    }
+
+    async fn insert_post(repo: &InMemoryRepo, user_id: i64) -> i64 {
+        repo.insert_standalone_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, user_id, "message", None).await.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn test_insert_response_post_is_atomic() {
+        let repo = InMemoryRepo::new();
+        let dave = insert_profile(&repo, "dave").await;
+
+        let result = repo.insert_response_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, dave, "reply", None, 999).await;
+
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation { field }) if field == "respondee_post_id"));
+        assert!(repo.read().posts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_home_timeline_with_shares() {
+        let repo = InMemoryRepo::new();
+        let dave = insert_profile(&repo, "dave").await;
+        let jill = insert_profile(&repo, "jill").await;
+        let tom = insert_profile(&repo, "tom").await;
+        repo.follow_user(dave, jill).await.unwrap();
+
+        let dave_post = insert_post(&repo, dave).await;
+        let jill_post = insert_post(&repo, jill).await;
+        let tom_post = insert_post(&repo, tom).await;
+        let share = repo.insert_share_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, jill, None, tom_post).await.unwrap();
+
+        let first_page = repo.query_home_timeline(dave, None, 2).await.unwrap();
+        let last = first_page.last().unwrap();
+        let second_page = repo
+            .query_home_timeline(dave, Some(PostCursor { updated_at: last.updated_at, id: last.id }), 2)
+            .await
+            .unwrap();
+
+        let ids: Vec<i64> = first_page.iter().chain(second_page.iter()).map(|post| post.id).collect();
+        assert!(ids == vec![share.id, jill_post, dave_post]);
+        assert!(first_page[0].sharee_post_id == Some(tom_post));
+        assert!(first_page[0].sharee_post_user_name == Some("tom".to_string()));
+        assert!(repo.query_shared_post(share.id).await.unwrap().is_some());
+        assert!(repo.query_shared_post(tom_post).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_post_thread() {
+        let repo = InMemoryRepo::new();
+        let dave = insert_profile(&repo, "dave").await;
+        let root = insert_post(&repo, dave).await;
+        let middle = repo.insert_response_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, dave, "reply", None, root).await.unwrap().id;
+        _ = repo.insert_response_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, dave, "sibling", None, root).await.unwrap();
+        let child = repo.insert_response_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, dave, "reply", None, middle).await.unwrap().id;
+        _ = repo.insert_response_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, dave, "grandchild", None, child).await.unwrap();
+
+        let thread = repo.query_post_thread(middle, 1).await.unwrap().unwrap();
+
+        assert!(thread.post.id == root && thread.depth == 0 && thread.reply_count == 2);
+        assert!(thread.replies.len() == 1);
+        let middle_node = &thread.replies[0];
+        assert!(middle_node.post.id == middle && middle_node.depth == 1);
+        let child_node = &middle_node.replies[0];
+        assert!(child_node.post.id == child && child_node.depth == 2 && child_node.reply_count == 1);
+        assert!(child_node.replies.is_empty());
+        assert!(repo.query_post_thread(999, 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_post_and_posts_by_user() {
+        let repo = InMemoryRepo::new();
+        let dave = insert_profile(&repo, "dave").await;
+        let jill = insert_profile(&repo, "jill").await;
+        let first = insert_post(&repo, dave).await;
+        _ = insert_post(&repo, jill).await;
+        let reply = repo.insert_response_post(&get_fake_chain_asset_id(), SUI_CHAIN_ID, dave, "reply", Some(vec![1, 2]), first).await.unwrap().id;
+
+        let post = repo.query_post(reply).await.unwrap().unwrap();
+        assert!(post.user_name == "dave");
+        assert!(post.image == Some(vec![1, 2]));
+        assert!(post.respondee_post_id == Some(first));
+        assert!(repo.query_post(999).await.unwrap().is_none());
+
+        let first_page = repo.query_posts_by_user(dave, None, 1).await.unwrap();
+        let last = first_page.last().unwrap();
+        let second_page = repo
+            .query_posts_by_user(dave, Some(PostCursor { updated_at: last.updated_at, id: last.id }), 1)
+            .await
+            .unwrap();
+
+        let ids: Vec<i64> = first_page.iter().chain(second_page.iter()).map(|post| post.id).collect();
+        assert!(ids == vec![reply, first]);
+    }
+
+    #[tokio::test]
+    async fn test_upserts_are_idempotent() {
+        let repo = InMemoryRepo::new();
+        let dave = insert_profile(&repo, "dave").await;
+        let post_asset_id = get_fake_chain_asset_id();
+        let reply_asset_id = get_fake_chain_asset_id();
+        let share_asset_id = get_fake_chain_asset_id();
+
+        let post = repo.upsert_standalone_post(&post_asset_id, SUI_CHAIN_ID, dave, "message", None).await.unwrap().id;
+        let reply = repo.upsert_response_post(&reply_asset_id, SUI_CHAIN_ID, dave, "reply", None, post).await.unwrap().id;
+        let share = repo.upsert_share_post(&share_asset_id, SUI_CHAIN_ID, dave, None, post).await.unwrap().id;
+
+        assert!(repo.upsert_standalone_post(&post_asset_id, SUI_CHAIN_ID, dave, "replayed", None).await.unwrap().id == post);
+        assert!(repo.upsert_response_post(&reply_asset_id, SUI_CHAIN_ID, dave, "reply", None, post).await.unwrap().id == reply);
+        assert!(repo.upsert_share_post(&share_asset_id, SUI_CHAIN_ID, dave, None, post).await.unwrap().id == share);
+        {
+            let state = repo.read();
+            assert!(state.posts.len() == 3 && state.post_responses.len() == 1 && state.post_shares.len() == 1);
+            assert!(state.posts[&post].message == Some("message".to_string()));
+        }
+        assert!(repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &reply_asset_id).await.unwrap() == Some(reply));
+        assert!(repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &get_fake_chain_asset_id()).await.unwrap().is_none());
+
+        let duplicate = repo.insert_standalone_post(&post_asset_id, SUI_CHAIN_ID, dave, "message", None).await;
+        assert!(matches!(duplicate, Err(RepositoryError::UniqueViolation { field }) if field == "chain_id, chain_asset_id"));
+    }
+}
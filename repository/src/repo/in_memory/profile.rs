@@ -0,0 +1,207 @@
+use crate::repo::error::{ RepositoryError, check_value_len };
+use crate::repo::in_memory::in_memory::{ InMemoryRepo, InMemoryState };
+use crate::repo::profile::model::{ ProfileCreate, ProfileQueryResult, ProfileUpdate };
+use crate::repo::profile::profile::{
+    InsertProfileFn, QueryProfileByChainAssetFn, QueryProfileByUserNameFn, QueryProfileFn, UpdateProfileFn,
+    UpsertProfileFn
+};
+use async_trait::async_trait;
+
+impl InMemoryState {
+    pub(crate) fn insert_profile(&mut self, params: ProfileCreate) -> Result<i64, RepositoryError> {
+        check_value_len("chain_asset_id", &params.chain_asset_id, 500)?;
+        check_value_len("user_name", &params.user_name, 50)?;
+        check_value_len("full_name", &params.full_name, 100)?;
+        check_value_len("description", &params.description, 250)?;
+        if let Some(main_url) = &params.main_url {
+            check_value_len("main_url", main_url, 250)?;
+        }
+        if let Some(owner_address) = &params.owner_address {
+            check_value_len("owner_address", owner_address, 66)?;
+        }
+        self.check_chain_exists(params.chain_id)?;
+        if self.profile_id_by_chain_asset(params.chain_id, &params.chain_asset_id).is_some() {
+            return Err(RepositoryError::UniqueViolation { field: "chain_id, chain_asset_id".to_string() });
+        }
+
+        let id = self.next_id();
+        self.profiles.insert(id, ProfileQueryResult {
+            id,
+            updated_at: InMemoryState::now(),
+            chain_asset_id: params.chain_asset_id,
+            chain_id: params.chain_id,
+            user_name: params.user_name,
+            full_name: params.full_name,
+            description: params.description,
+            main_url: params.main_url,
+            avatar: params.avatar,
+            owner_address: params.owner_address,
+        });
+
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl InsertProfileFn for InMemoryRepo {
+    async fn insert_profile(
+        &self,
+        params: ProfileCreate
+    ) -> Result<i64, RepositoryError> {
+        self.write().insert_profile(params)
+    }
+}
+
+#[async_trait]
+impl UpsertProfileFn for InMemoryRepo {
+    /// The lookup and the insert happen under one write lock, so concurrent upserts of an asset get the same id
+    async fn upsert_profile(
+        &self,
+        params: ProfileCreate
+    ) -> Result<i64, RepositoryError> {
+        let mut state = self.write();
+        match state.profile_id_by_chain_asset(params.chain_id, &params.chain_asset_id) {
+            Some(id) => Ok(id),
+            None => state.insert_profile(params),
+        }
+    }
+}
+
+#[async_trait]
+impl UpdateProfileFn for InMemoryRepo {
+    async fn update_profile(
+        &self,
+        user_id: i64,
+        params: ProfileUpdate
+    ) -> Result<(), RepositoryError> {
+        check_value_len("full_name", &params.full_name, 100)?;
+        check_value_len("description", &params.description, 250)?;
+        if let Some(main_url) = &params.main_url {
+            check_value_len("main_url", main_url, 250)?;
+        }
+
+        let mut state = self.write();
+        let profile = state.profiles.get_mut(&user_id).ok_or(RepositoryError::NotFound)?;
+        profile.full_name = params.full_name;
+        profile.description = params.description;
+        profile.main_url = params.main_url;
+        profile.avatar = params.avatar;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl QueryProfileFn for InMemoryRepo {
+    async fn query_profile(
+        &self,
+        id: i64
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        Ok(self.read().profiles.get(&id).cloned())
+    }
+}
+
+#[async_trait]
+impl QueryProfileByUserNameFn for InMemoryRepo {
+    async fn query_profile_by_user_name(
+        &self,
+        user_name: &str
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        Ok(self.read().profiles.values().find(|profile| profile.user_name == user_name).cloned())
+    }
+}
+
+#[async_trait]
+impl QueryProfileByChainAssetFn for InMemoryRepo {
+    async fn query_profile_by_chain_asset(
+        &self,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        let state = self.read();
+        Ok(state.profile_id_by_chain_asset(chain_id, chain_asset_id).and_then(|id| state.profiles.get(&id).cloned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+
+    fn get_profile_create(user_name: &str) -> ProfileCreate {
+        ProfileCreate {
+            chain_asset_id: get_fake_chain_asset_id(),
+            chain_id: SUI_CHAIN_ID,
+            user_name: user_name.to_string(),
+            full_name: "Jill Simon".to_string(),
+            description: "I am a developer".to_string(),
+            main_url: Some("http://dev.com".to_string()),
+            avatar: None,
+            owner_address: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_update_profile() {
+        let repo = InMemoryRepo::new();
+        let profile_id = repo.insert_profile(get_profile_create("jill")).await.unwrap();
+
+        repo.update_profile(profile_id, ProfileUpdate {
+            full_name: "Jill Updated".to_string(),
+            description: "Updated description".to_string(),
+            main_url: None,
+            avatar: Some(vec![]),
+        }).await.unwrap();
+
+        let profile = repo.query_profile_by_user_name("jill").await.unwrap().unwrap();
+        assert!(profile.id == profile_id);
+        assert!(repo.query_profile(profile_id).await.unwrap().unwrap().user_name == "jill");
+        assert!(profile.full_name == "Jill Updated");
+        assert!(profile.main_url.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_insert_profile_errors() {
+        let repo = InMemoryRepo::new();
+
+        let too_long = repo.insert_profile(get_profile_create(&"x".repeat(51))).await;
+        assert!(matches!(too_long, Err(RepositoryError::ValueTooLong { field, max: 50 }) if field == "user_name"));
+
+        let mut unknown_chain = get_profile_create("jill");
+        unknown_chain.chain_id = 99;
+        let unknown_chain = repo.insert_profile(unknown_chain).await;
+        assert!(matches!(unknown_chain, Err(RepositoryError::ForeignKeyViolation { field }) if field == "chain_id"));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_profile() {
+        let repo = InMemoryRepo::new();
+        let profile_create = get_profile_create("jill");
+
+        let profile_id = repo.upsert_profile(profile_create.clone()).await.unwrap();
+        let mut replayed = profile_create.clone();
+        replayed.full_name = "Replayed".to_string();
+        assert!(repo.upsert_profile(replayed).await.unwrap() == profile_id);
+
+        assert!(repo.query_profile(profile_id).await.unwrap().unwrap().full_name == "Jill Simon");
+        assert!(repo.read().profiles.len() == 1);
+        let by_chain_asset = repo.query_profile_by_chain_asset(SUI_CHAIN_ID, &profile_create.chain_asset_id).await.unwrap();
+        assert!(by_chain_asset.unwrap().id == profile_id);
+        let duplicate = repo.insert_profile(profile_create).await;
+        assert!(matches!(duplicate, Err(RepositoryError::UniqueViolation { field }) if field == "chain_id, chain_asset_id"));
+    }
+
+    #[tokio::test]
+    async fn test_update_missing_profile() {
+        let repo = InMemoryRepo::new();
+
+        let result = repo.update_profile(1, ProfileUpdate {
+            full_name: "Missing".to_string(),
+            description: "Missing".to_string(),
+            main_url: None,
+            avatar: None,
+        }).await;
+
+        assert!(matches!(result, Err(RepositoryError::NotFound)));
+    }
+}
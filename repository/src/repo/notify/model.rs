@@ -0,0 +1,15 @@
+use serde::{Serialize, Deserialize};
+
+/// The tables that notify on insert, named like the table. Payloads of other tables fail to deserialize
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Post,
+}
+
+/// Payload of the entity_inserted channel, e.g. {"entity":"post","id":7}
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct EntityInserted {
+    pub entity: EntityKind,
+    pub id: i64,
+}
@@ -0,0 +1,185 @@
+use crate::repo::base::{ DbRepo, DbConnGetter };
+use crate::repo::error::RepositoryError;
+use crate::repo::notify::model::EntityInserted;
+use log::warn;
+use sqlx::postgres::PgListener;
+
+/// Channel the insert triggers of migration 0009 notify on
+pub const ENTITY_INSERTED_CHANNEL: &str = "entity_inserted";
+
+/// Holds its own connection outside the pool. When that connection drops, recv reconnects and listens again,
+/// but whatever was inserted while it was down is not notified again
+pub struct EntityInsertedListener {
+    listener: PgListener,
+}
+
+impl EntityInsertedListener {
+    /// Waits for the next insert, payloads this version does not know are skipped
+    pub async fn recv(&mut self) -> Result<EntityInserted, RepositoryError> {
+        loop {
+            let notification = self.listener.recv().await?;
+            match serde_json::from_str::<EntityInserted>(notification.payload()) {
+                Ok(inserted) => return Ok(inserted),
+                Err(e) => warn!("skipping {} notification {}: {}", ENTITY_INSERTED_CHANNEL, notification.payload(), e),
+            }
+        }
+    }
+}
+
+impl DbRepo {
+    /// Only Postgres can notify other processes, so unlike the repo traits this has no in memory counterpart
+    pub async fn listen_entity_inserted(&self) -> Result<EntityInsertedListener, RepositoryError> {
+        let mut listener = PgListener::connect_with(self.get_conn()).await?;
+        listener.listen(ENTITY_INSERTED_CHANNEL).await?;
+
+        Ok(EntityInsertedListener { listener })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repo::follow::follow::FollowUserFn;
+    use crate::repo::notify::model::EntityKind;
+    use crate::repo::post::post::{ InsertPostFn, InsertResponsePostFn };
+    use crate::repo::profile::{ profile::InsertProfileFn, model::ProfileCreate };
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+    use super::*;
+    use fake::{ faker::name::en::{ FirstName, LastName }, Fake };
+    use lazy_static::lazy_static;
+    use std::sync::{ Arc, RwLock };
+
+    #[derive(Clone)]
+    #[allow(unused)]
+    struct Fixtures {
+        db_repo: DbRepo
+    }
+
+    /// Helps prevent clashes between other running tests, by adding unique prefix values for data
+    const PREFIX: &str = "TestNotify";
+
+    lazy_static! {
+        static ref FIXTURES: Arc<RwLock<Option<Fixtures>>> = Arc::new(RwLock::new(None));
+    }
+
+    async fn setup_fixtures() {
+        let fixtures = Arc::clone(&FIXTURES);
+        let mut fx = fixtures.write().unwrap();
+        match fx.clone() {
+            Some(_) => (),
+            None => {
+                let db_repo = DbRepo::init().await;
+
+                *fx = Some(Fixtures { db_repo });
+            }
+        }
+    }
+
+    lazy_static! {
+        static ref RT: tokio::runtime::Runtime = {
+            let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+            rt.block_on(async {
+                setup_fixtures().await;
+            });
+
+            rt
+        };
+    }
+
+    #[allow(unused)]
+    fn fixtures() -> Fixtures {
+        Arc::clone(&FIXTURES).read().unwrap().clone().unwrap()
+    }
+
+    async fn insert_test_profile(db_repo: &DbRepo) -> i64 {
+        let first_name: String = FirstName().fake();
+        let last_name: String = LastName().fake();
+        db_repo
+            .insert_profile(ProfileCreate {
+                chain_asset_id: get_fake_chain_asset_id(),
+                chain_id: SUI_CHAIN_ID,
+                user_name: format!("{}{}", PREFIX, first_name),
+                full_name: format!("{} {}", first_name, last_name),
+                description: format!("{} a description", PREFIX),
+                main_url: None,
+                avatar: None,
+                owner_address: None,
+            }).await
+            .unwrap()
+    }
+
+    /// Other tests insert concurrently, so notifications of their rows are passed over
+    async fn recv_inserted(listener: &mut EntityInsertedListener, expected: &EntityInserted) {
+        loop {
+            let inserted = listener.recv().await.unwrap();
+            if inserted == *expected {
+                return;
+            }
+        }
+    }
+
+    mod test_mod_listen_entity_inserted {
+        use super::*;
+
+        async fn test_listen_entity_inserted_body() {
+            let fixtures = fixtures();
+            let mut listener = fixtures.db_repo.listen_entity_inserted().await.unwrap();
+
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+            let post_id = fixtures.db_repo.insert_response_post(
+                &get_fake_chain_asset_id(),
+                SUI_CHAIN_ID,
+                follower_id,
+                &format!("{} reply", PREFIX),
+                None,
+                fixtures.db_repo.insert_standalone_post(
+                    &get_fake_chain_asset_id(),
+                    SUI_CHAIN_ID,
+                    following_id,
+                    &format!("{} post", PREFIX),
+                    None
+                ).await.unwrap().id
+            ).await.unwrap();
+
+            recv_inserted(&mut listener, &EntityInserted { entity: EntityKind::Post, id: post_id.id }).await;
+        }
+
+        #[test]
+        fn test_listen_entity_inserted() {
+            RT.block_on(test_listen_entity_inserted_body())
+        }
+
+        /// Read raw, a profile or follow payload would show here even though EntityInsertedListener skips it
+        async fn test_only_posts_notify_body() {
+            let fixtures = fixtures();
+            let mut listener = PgListener::connect_with(fixtures.db_repo.get_conn()).await.unwrap();
+            listener.listen(ENTITY_INSERTED_CHANNEL).await.unwrap();
+
+            let follower_id = insert_test_profile(&fixtures.db_repo).await;
+            let following_id = insert_test_profile(&fixtures.db_repo).await;
+            fixtures.db_repo.follow_user(follower_id, following_id).await.unwrap();
+            let post_id = fixtures.db_repo.insert_standalone_post(
+                &get_fake_chain_asset_id(),
+                SUI_CHAIN_ID,
+                follower_id,
+                &format!("{} post", PREFIX),
+                None
+            ).await.unwrap();
+
+            loop {
+                let notification = listener.recv().await.unwrap();
+                let payload: serde_json::Value = serde_json::from_str(notification.payload()).unwrap();
+                assert!(payload["entity"] == "post", "{} notified", notification.payload());
+                if payload["id"] == post_id.id {
+                    break;
+                }
+            }
+        }
+
+        #[test]
+        fn test_only_posts_notify() {
+            RT.block_on(test_only_posts_notify_body())
+        }
+    }
+}
@@ -29,14 +29,43 @@ pub struct PostWithProfileAndShareeQueryResult {
     pub user_name: String,
     pub full_name: String,
     pub avatar: Option<Vec<u8>>,
+    pub respondee_post_id: Option<i64>,
     pub sharee_post_id: Option<i64>,
     pub sharee_post_updated_at: Option<DateTime<Utc>>,
-    pub sharee_post_chain_asset_id: String,
-    pub sharee_post_chain_id: i64,
+    pub sharee_post_chain_asset_id: Option<String>,
+    pub sharee_post_chain_id: Option<i64>,
     pub sharee_post_message: Option<String>,   
     pub sharee_post_image: Option<Vec<u8>>,
     pub sharee_post_user_id: Option<i64>,
     pub sharee_post_user_name: Option<String>,
     pub sharee_post_full_name: Option<String>,
     pub sharee_post_avatar: Option<Vec<u8>>
-}
\ No newline at end of file
+}
+
+/// Keyset cursor for paging posts newest first, built from the last post of the previous page
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PostCursor {
+    pub updated_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+/// Single row of a thread query, depth is counted from the root post of the conversation
+#[derive(Deserialize, Serialize, FromRow, Clone, Debug)]
+pub struct PostThreadQueryResult {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub post: PostWithProfileQueryResult,
+    pub depth: i32,
+    pub reply_count: i64,
+}
+
+/// Conversation tree node, reply_count is the total number of direct replies
+/// which can be larger than replies.len() when the tree was cut off at a max depth
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PostThreadNode {
+    #[serde(flatten)]
+    pub post: PostWithProfileQueryResult,
+    pub depth: i32,
+    pub reply_count: i64,
+    pub replies: Vec<PostThreadNode>,
+}
@@ -1,139 +1,1023 @@
-use crate::repo::base::{EntityId, DbRepo, DbConnGetter};
+use crate::repo::base::{EntityId, UpsertedId, DbRepo, DbConnGetter, DbTransaction};
+use crate::repo::error::{RepositoryError, check_value_len};
+use crate::repo::post::model::{
+    PostCursor, PostWithProfileQueryResult, PostWithProfileAndShareeQueryResult, PostThreadQueryResult, PostThreadNode
+};
+use std::collections::HashMap;
 use mockall::automock;
-use sqlx::{ Pool, Postgres };
+use sqlx::{ Pool, Postgres, PgConnection, PgExecutor };
 use async_trait::async_trait;
 
 mod private_members {
     use super::*;
 
-    pub async fn insert_standalone_post_inner(
-        conn: &Pool<Postgres>,
+    pub async fn insert_standalone_post_inner<'e>(
+        conn: impl PgExecutor<'e>,
         chain_asset_id: &str,
         chain_id: i64,
         user_id: i64,
-        message: &str
-    ) -> Result<EntityId, sqlx::Error> {
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError> {
+        check_value_len("chain_asset_id", chain_asset_id, 500)?;
+        check_value_len("message", message, 140)?;
+
         let insert_msg_result = sqlx
             ::query_as::<_, EntityId>(
-                "insert into post (chain_asset_id, chain_id, user_id, message) values ($1, $2, $3, $4) returning id"
+                "insert into post (chain_asset_id, chain_id, user_id, message, image) values ($1, $2, $3, $4, $5) returning id"
             )
             .bind(chain_asset_id)
             .bind(chain_id)
             .bind(user_id)
             .bind(message)
+            .bind(image)
             .fetch_one(conn)
             .await;
 
         match insert_msg_result {
             Ok(row) => Ok(row),
-            Err(e) => Err(e)
+            Err(e) => Err(e.into())
+        }
+    }
+
+    /// Both statements must run on the same transaction connection, otherwise a failed
+    /// post_response insert leaves behind a post that looks standalone
+    pub async fn insert_response_post_inner(
+        conn: &mut PgConnection,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let insert_post_id = insert_standalone_post_inner(
+            &mut *conn,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            image
+        ).await?;
+
+        let insert_response_result = sqlx::query_as::<_, EntityId>(
+            "insert into post_response (respondee_post_id, responder_post_id) values ($1, $2) returning id"
+        )
+        .bind(respondee_post_id)
+        .bind(insert_post_id.id)
+        .fetch_one(conn)
+        .await;
+
+        match insert_response_result {
+            Ok(_) => Ok(insert_post_id),
+            Err(e) => Err(e.into())
+        }        
+    }
+
+    pub async fn insert_share_post_inner(
+        conn: &mut PgConnection,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        check_value_len("chain_asset_id", chain_asset_id, 500)?;
+        if let Some(message) = &message {
+            check_value_len("message", message, 140)?;
+        }
+
+        let insert_post_id = sqlx
+            ::query_as::<_, EntityId>(
+                "insert into post (chain_asset_id, chain_id, user_id, message) values ($1, $2, $3, $4) returning id"
+            )
+            .bind(chain_asset_id)
+            .bind(chain_id)
+            .bind(user_id)
+            .bind(message)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        let insert_share_result = sqlx::query_as::<_, EntityId>(
+            "insert into post_share (sharee_post_id, sharer_post_id) values ($1, $2) returning id"
+        )
+        .bind(sharee_post_id)
+        .bind(insert_post_id.id)
+        .fetch_one(conn)
+        .await;
+
+        match insert_share_result {
+            Ok(_) => Ok(insert_post_id),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    /// Inserts the post row unless the chain asset already has one, either way returns its id. The no-op update
+    /// locks and returns a row another transaction inserted concurrently, where do nothing would return no row
+    pub async fn upsert_post_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        image: Option<Vec<u8>>
+    ) -> Result<UpsertedId, RepositoryError> {
+        check_value_len("chain_asset_id", chain_asset_id, 500)?;
+        if let Some(message) = &message {
+            check_value_len("message", message, 140)?;
+        }
+
+        Ok(sqlx::query_as::<_, UpsertedId>(
+            r"
+                insert into post (chain_asset_id, chain_id, user_id, message, image) values ($1, $2, $3, $4, $5)
+                on conflict (chain_id, chain_asset_id) do update set chain_id = excluded.chain_id
+                returning id, (xmax = 0) as inserted
+            "
+        )
+        .bind(chain_asset_id)
+        .bind(chain_id)
+        .bind(user_id)
+        .bind(message)
+        .bind(image)
+        .fetch_one(conn)
+        .await?)
+    }
+
+    /// The post_response row is only added along with a new post, an existing post keeps whatever it responds to
+    pub async fn upsert_response_post_inner(
+        conn: &mut PgConnection,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let upserted = upsert_post_inner(
+            &mut *conn,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            Some(message.to_string()),
+            image
+        ).await?;
+
+        if upserted.inserted {
+            sqlx::query("insert into post_response (respondee_post_id, responder_post_id) values ($1, $2)")
+                .bind(respondee_post_id)
+                .bind(upserted.id)
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(EntityId { id: upserted.id })
+    }
+
+    pub async fn upsert_share_post_inner(
+        conn: &mut PgConnection,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let upserted = upsert_post_inner(&mut *conn, chain_asset_id, chain_id, user_id, message, None).await?;
+
+        if upserted.inserted {
+            sqlx::query("insert into post_share (sharee_post_id, sharer_post_id) values ($1, $2)")
+                .bind(sharee_post_id)
+                .bind(upserted.id)
+                .execute(conn)
+                .await?;
+        }
+
+        Ok(EntityId { id: upserted.id })
+    }
+
+    pub async fn query_post_id_by_chain_asset_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        let result = sqlx::query_as::<_, EntityId>("select id from post where chain_id = $1 and chain_asset_id = $2")
+            .bind(chain_id)
+            .bind(chain_asset_id)
+            .fetch_optional(conn)
+            .await?;
+
+        Ok(result.map(|post| post.id))
+    }
+
+    pub async fn query_post_inner(
+        conn: &Pool<Postgres>,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, PostWithProfileQueryResult>(
+            r"
+                select
+                    pt.id,
+                    pt.updated_at,
+                    pt.chain_asset_id,
+                    pt.chain_id,
+                    pt.message,
+                    pt.image,
+                    pt.user_id,
+                    pe.user_name,
+                    pe.full_name,
+                    pe.avatar,
+                    pr.respondee_post_id
+                from post pt
+                    join
+                profile pe
+                    on pt.user_id = pe.id
+                    left join
+                post_response pr
+                    on pt.id = pr.responder_post_id
+                where pt.id = $1
+            "
+        )
+        .bind(post_id)
+        .fetch_optional(conn)
+        .await?)
+    }
+
+    pub async fn query_posts_by_user_inner(
+        conn: &Pool<Postgres>,
+        user_id: i64,
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileQueryResult>, RepositoryError> {
+        let (last_updated_at, last_id) = match cursor {
+            Some(cursor) => (Some(cursor.updated_at), Some(cursor.id)),
+            None => (None, None)
+        };
+
+        Ok(sqlx::query_as::<_, PostWithProfileQueryResult>(
+            r"
+                select
+                    pt.id,
+                    pt.updated_at,
+                    pt.chain_asset_id,
+                    pt.chain_id,
+                    pt.message,
+                    pt.image,
+                    pt.user_id,
+                    pe.user_name,
+                    pe.full_name,
+                    pe.avatar,
+                    pr.respondee_post_id
+                from post pt
+                    join
+                profile pe
+                    on pt.user_id = pe.id
+                    left join
+                post_response pr
+                    on pt.id = pr.responder_post_id
+                where pt.user_id = $1
+                and ($2::timestamptz is null or (pt.updated_at, pt.id) < ($2, $3))
+                order by pt.updated_at desc, pt.id desc
+                limit $4
+            "
+        )
+        .bind(user_id)
+        .bind(last_updated_at)
+        .bind(last_id)
+        .bind(page_size)
+        .fetch_all(conn)
+        .await?)
+    }
+
+    pub async fn query_shared_post_inner(
+        conn: &Pool<Postgres>,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileAndShareeQueryResult>, RepositoryError> {
+        Ok(sqlx::query_as::<_, PostWithProfileAndShareeQueryResult>(
+            r"
+                select
+                    pt.id,
+                    pt.updated_at,
+                    pt.chain_asset_id,
+                    pt.chain_id,
+                    pt.message,
+                    pt.image,
+                    pt.user_id,
+                    pe.user_name,
+                    pe.full_name,
+                    pe.avatar,
+                    pr.respondee_post_id,
+                    ps.sharee_post_id,
+                    spt.updated_at as sharee_post_updated_at,
+                    spt.chain_asset_id as sharee_post_chain_asset_id,
+                    spt.chain_id as sharee_post_chain_id,
+                    spt.message as sharee_post_message,
+                    spt.image as sharee_post_image,
+                    spt.user_id as sharee_post_user_id,
+                    spe.user_name as sharee_post_user_name,
+                    spe.full_name as sharee_post_full_name,
+                    spe.avatar as sharee_post_avatar
+                from post pt
+                    join
+                profile pe
+                    on pt.user_id = pe.id
+                    left join
+                post_response pr
+                    on pt.id = pr.responder_post_id
+                    join
+                post_share ps
+                    on pt.id = ps.sharer_post_id
+                    join
+                post spt
+                    on ps.sharee_post_id = spt.id
+                    join
+                profile spe
+                    on spt.user_id = spe.id
+                where pt.id = $1
+            "
+        )
+        .bind(post_id)
+        .fetch_optional(conn)
+        .await?)
+    }
+
+    pub async fn query_home_timeline_inner(
+        conn: &Pool<Postgres>,
+        profile_id: i64,
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileAndShareeQueryResult>, RepositoryError> {
+        let (last_updated_at, last_id) = match cursor {
+            Some(cursor) => (Some(cursor.updated_at), Some(cursor.id)),
+            None => (None, None)
+        };
+
+        Ok(sqlx::query_as::<_, PostWithProfileAndShareeQueryResult>(
+            r"
+                select
+                    pt.id,
+                    pt.updated_at,
+                    pt.chain_asset_id,
+                    pt.chain_id,
+                    pt.message,
+                    pt.image,
+                    pt.user_id,
+                    pe.user_name,
+                    pe.full_name,
+                    pe.avatar,
+                    pr.respondee_post_id,
+                    ps.sharee_post_id,
+                    spt.updated_at as sharee_post_updated_at,
+                    spt.chain_asset_id as sharee_post_chain_asset_id,
+                    spt.chain_id as sharee_post_chain_id,
+                    spt.message as sharee_post_message,
+                    spt.image as sharee_post_image,
+                    spt.user_id as sharee_post_user_id,
+                    spe.user_name as sharee_post_user_name,
+                    spe.full_name as sharee_post_full_name,
+                    spe.avatar as sharee_post_avatar
+                from post pt
+                    join
+                profile pe
+                    on pt.user_id = pe.id
+                    left join
+                post_response pr
+                    on pt.id = pr.responder_post_id
+                    left join
+                post_share ps
+                    on pt.id = ps.sharer_post_id
+                    left join
+                post spt
+                    on ps.sharee_post_id = spt.id
+                    left join
+                profile spe
+                    on spt.user_id = spe.id
+                where (
+                    pt.user_id = $1
+                    or pt.user_id in (select following_id from follow where follower_id = $1)
+                )
+                and ($2::timestamptz is null or (pt.updated_at, pt.id) < ($2, $3))
+                order by pt.updated_at desc, pt.id desc
+                limit $4
+            "
+        )
+        .bind(profile_id)
+        .bind(last_updated_at)
+        .bind(last_id)
+        .bind(page_size)
+        .fetch_all(conn)
+        .await?)
+    }
+
+    pub async fn query_post_thread_inner(
+        conn: &Pool<Postgres>,
+        post_id: i64,
+        max_depth: i32
+    ) -> Result<Option<PostThreadNode>, RepositoryError> {
+        let rows = sqlx::query_as::<_, PostThreadQueryResult>(
+            r"
+                with recursive ancestor as (
+                    select pt.id, pr.respondee_post_id as parent_id, 0 as hops
+                    from post pt
+                        left join
+                    post_response pr
+                        on pt.id = pr.responder_post_id
+                    where pt.id = $1
+                    union all
+                    select an.parent_id, pr.respondee_post_id, an.hops + 1
+                    from ancestor an
+                        left join
+                    post_response pr
+                        on an.parent_id = pr.responder_post_id
+                    where an.parent_id is not null
+                ),
+                descendant as (
+                    select pr.responder_post_id as id, 1 as hops
+                    from post_response pr
+                    where pr.respondee_post_id = $1 and $2 > 0
+                    union all
+                    select pr.responder_post_id, de.hops + 1
+                    from descendant de
+                        join
+                    post_response pr
+                        on de.id = pr.respondee_post_id
+                    where de.hops < $2
+                ),
+                thread as (
+                    select id, (select max(hops) from ancestor) - hops as depth from ancestor
+                    union
+                    select id, (select max(hops) from ancestor) + hops as depth from descendant
+                )
+                select
+                    pt.id,
+                    pt.updated_at,
+                    pt.chain_asset_id,
+                    pt.chain_id,
+                    pt.message,
+                    pt.image,
+                    pt.user_id,
+                    pe.user_name,
+                    pe.full_name,
+                    pe.avatar,
+                    pr.respondee_post_id,
+                    th.depth,
+                    (select count(*) from post_response rc where rc.respondee_post_id = pt.id) as reply_count
+                from thread th
+                    join
+                post pt
+                    on th.id = pt.id
+                    join
+                profile pe
+                    on pt.user_id = pe.id
+                    left join
+                post_response pr
+                    on pt.id = pr.responder_post_id
+                order by th.depth, pt.updated_at, pt.id
+            "
+        )
+        .bind(post_id)
+        .bind(max_depth)
+        .fetch_all(conn)
+        .await?;
+
+        Ok(build_thread_tree(rows))
+    }
+
+    /// Rows arrive ordered by depth so the root is always first
+    fn build_thread_tree(rows: Vec<PostThreadQueryResult>) -> Option<PostThreadNode> {
+        let mut rows = rows.into_iter();
+        let root = rows.next()?;
+        let mut replies_by_respondee: HashMap<i64, Vec<PostThreadQueryResult>> = HashMap::new();
+        for row in rows {
+            if let Some(respondee_post_id) = row.post.respondee_post_id {
+                replies_by_respondee.entry(respondee_post_id).or_default().push(row);
+            }
         }
+
+        Some(to_thread_node(root, &mut replies_by_respondee))
+    }
+
+    fn to_thread_node(
+        row: PostThreadQueryResult,
+        replies_by_respondee: &mut HashMap<i64, Vec<PostThreadQueryResult>>
+    ) -> PostThreadNode {
+        let replies = replies_by_respondee
+            .remove(&row.post.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|reply| to_thread_node(reply, replies_by_respondee))
+            .collect();
+
+        PostThreadNode {
+            post: row.post,
+            depth: row.depth,
+            reply_count: row.reply_count,
+            replies
+        }
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait InsertPostFn {
+    async fn insert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError>;
+}
+
+#[async_trait]
+impl InsertPostFn for DbRepo {
+    async fn insert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError> {
+        private_members::insert_standalone_post_inner(
+            self.get_conn(),
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            image
+        ).await
+    }
+}
+
+#[async_trait]
+impl InsertPostFn for DbTransaction {
+    async fn insert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError> {
+        private_members::insert_standalone_post_inner(
+            &mut **self.lock_conn().await,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            image
+        ).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait InsertResponsePostFn {
+    async fn insert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError>;
+}
+
+#[async_trait]
+impl InsertResponsePostFn for DbRepo {
+    async fn insert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut tx = self.get_conn().begin().await?;
+        let insert_post_id = private_members::insert_response_post_inner(
+            &mut tx,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            image,
+            respondee_post_id
+        ).await?;
+        tx.commit().await?;
+
+        Ok(insert_post_id)
+    }
+}
+
+#[async_trait]
+impl InsertResponsePostFn for DbTransaction {
+    async fn insert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        private_members::insert_response_post_inner(
+            &mut **self.lock_conn().await,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            image,
+            respondee_post_id
+        ).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait InsertSharePostFn {
+    async fn insert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError>;
+}
+
+#[async_trait]
+impl InsertSharePostFn for DbRepo {
+    async fn insert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut tx = self.get_conn().begin().await?;
+        let insert_post_id = private_members::insert_share_post_inner(
+            &mut tx,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            sharee_post_id
+        ).await?;
+        tx.commit().await?;
+
+        Ok(insert_post_id)
+    }
+}
+
+#[async_trait]
+impl InsertSharePostFn for DbTransaction {
+    async fn insert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        private_members::insert_share_post_inner(
+            &mut **self.lock_conn().await,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            sharee_post_id
+        ).await
+    }
+}
+
+/// Upserts insert posts that exist on chain. Inserting a chain asset that already has a post is a no-op
+/// returning the existing id, so replayed chain events do not create duplicates
+#[automock]
+#[async_trait]
+pub trait UpsertPostFn {
+    async fn upsert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError>;
+}
+
+#[async_trait]
+impl UpsertPostFn for DbRepo {
+    async fn upsert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError> {
+        let upserted = private_members::upsert_post_inner(
+            self.get_conn(),
+            chain_asset_id,
+            chain_id,
+            user_id,
+            Some(message.to_string()),
+            image
+        ).await?;
+
+        Ok(EntityId { id: upserted.id })
+    }
+}
+
+#[async_trait]
+impl UpsertPostFn for DbTransaction {
+    async fn upsert_standalone_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>
+    ) -> Result<EntityId, RepositoryError> {
+        let upserted = private_members::upsert_post_inner(
+            &mut **self.lock_conn().await,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            Some(message.to_string()),
+            image
+        ).await?;
+
+        Ok(EntityId { id: upserted.id })
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait UpsertResponsePostFn {
+    async fn upsert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError>;
+}
+
+#[async_trait]
+impl UpsertResponsePostFn for DbRepo {
+    async fn upsert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut tx = self.get_conn().begin().await?;
+        let post_id = private_members::upsert_response_post_inner(
+            &mut tx,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            image,
+            respondee_post_id
+        ).await?;
+        tx.commit().await?;
+
+        Ok(post_id)
     }
+}
+
+#[async_trait]
+impl UpsertResponsePostFn for DbTransaction {
+    async fn upsert_response_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: &str,
+        image: Option<Vec<u8>>,
+        respondee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        private_members::upsert_response_post_inner(
+            &mut **self.lock_conn().await,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            image,
+            respondee_post_id
+        ).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait UpsertSharePostFn {
+    async fn upsert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError>;
+}
+
+#[async_trait]
+impl UpsertSharePostFn for DbRepo {
+    async fn upsert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        let mut tx = self.get_conn().begin().await?;
+        let post_id = private_members::upsert_share_post_inner(
+            &mut tx,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            sharee_post_id
+        ).await?;
+        tx.commit().await?;
+
+        Ok(post_id)
+    }
+}
+
+#[async_trait]
+impl UpsertSharePostFn for DbTransaction {
+    async fn upsert_share_post(
+        &self,
+        chain_asset_id: &str,
+        chain_id: i64,
+        user_id: i64,
+        message: Option<String>,
+        sharee_post_id: i64
+    ) -> Result<EntityId, RepositoryError> {
+        private_members::upsert_share_post_inner(
+            &mut **self.lock_conn().await,
+            chain_asset_id,
+            chain_id,
+            user_id,
+            message,
+            sharee_post_id
+        ).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait QueryPostFn {
+    async fn query_post(
+        &self,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryPostFn for DbRepo {
+    async fn query_post(
+        &self,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileQueryResult>, RepositoryError> {
+        private_members::query_post_inner(self.get_conn(), post_id).await
+    }
+}
+
+/// Resolves the on-chain object of a post to its id, also available in a transaction
+#[automock]
+#[async_trait]
+pub trait QueryPostIdByChainAssetFn {
+    async fn query_post_id_by_chain_asset(
+        &self,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<i64>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryPostIdByChainAssetFn for DbRepo {
+    async fn query_post_id_by_chain_asset(
+        &self,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        private_members::query_post_id_by_chain_asset_inner(self.get_conn(), chain_id, chain_asset_id).await
+    }
+}
 
-    pub async fn insert_response_post_inner(
-        conn: &Pool<Postgres>,
-        chain_asset_id: &str,
+#[async_trait]
+impl QueryPostIdByChainAssetFn for DbTransaction {
+    async fn query_post_id_by_chain_asset(
+        &self,
         chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<i64>, RepositoryError> {
+        private_members::query_post_id_by_chain_asset_inner(&mut **self.lock_conn().await, chain_id, chain_asset_id).await
+    }
+}
+
+/// Posts written by a single profile newest first, paged the same way as the home timeline
+#[automock]
+#[async_trait]
+pub trait QueryPostsByUserFn {
+    async fn query_posts_by_user(
+        &self,
         user_id: i64,
-        message: &str,
-        respondee_post_id: i64
-    ) -> Result<EntityId, sqlx::Error> {
-        let insert_post_result = sqlx
-            ::query_as::<_, EntityId>(
-                "insert into post (chain_asset_id, chain_id, user_id, message) values ($1, $2, $3, $4) returning id"
-            )
-            .bind(chain_asset_id)
-            .bind(chain_id)
-            .bind(user_id)
-            .bind(message)
-            .fetch_one(conn)
-            .await;
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileQueryResult>, RepositoryError>;
+}
 
-        let insert_post_id = match insert_post_result {
-            Ok(row) => Ok(row),
-            Err(e) => Err(e)
-        };
-        if insert_post_id.is_err() {
-            return insert_post_id;
-        }
+#[async_trait]
+impl QueryPostsByUserFn for DbRepo {
+    async fn query_posts_by_user(
+        &self,
+        user_id: i64,
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileQueryResult>, RepositoryError> {
+        private_members::query_posts_by_user_inner(self.get_conn(), user_id, cursor, page_size).await
+    }
+}
 
-        let insert_response_result = sqlx::query_as::<_, EntityId>(
-            "insert into post_response (respondee_post_id, responder_post_id) values ($1, $2) returning id"
-        )
-        .bind(respondee_post_id)
-        .bind(insert_post_id.as_ref().unwrap().id)
-        .fetch_one(conn)
-        .await;
+/// Returns None when the post does not exist or is not a share
+#[automock]
+#[async_trait]
+pub trait QuerySharedPostFn {
+    async fn query_shared_post(
+        &self,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileAndShareeQueryResult>, RepositoryError>;
+}
 
-        match insert_response_result {
-            Ok(_) => insert_post_id,
-            Err(e) => Err(e)
-        }        
+#[async_trait]
+impl QuerySharedPostFn for DbRepo {
+    async fn query_shared_post(
+        &self,
+        post_id: i64
+    ) -> Result<Option<PostWithProfileAndShareeQueryResult>, RepositoryError> {
+        private_members::query_shared_post_inner(self.get_conn(), post_id).await
     }
 }
 
+/// Posts by the profile and everyone it follows, newest first. Shares carry the original post and its author.
+/// Pass the cursor of the last post of the previous page to get the next one
 #[automock]
 #[async_trait]
-pub trait InsertPostFn {
-    async fn insert_standalone_post(
+pub trait QueryHomeTimelineFn {
+    async fn query_home_timeline(
         &self,
-        chain_asset_id: &str,
-        chain_id: i64,
-        user_id: i64,
-        message: &str,
-    ) -> Result<EntityId, sqlx::Error>;
+        profile_id: i64,
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileAndShareeQueryResult>, RepositoryError>;
 }
 
 #[async_trait]
-impl InsertPostFn for DbRepo {
-    async fn insert_standalone_post(
+impl QueryHomeTimelineFn for DbRepo {
+    async fn query_home_timeline(
         &self,
-        chain_asset_id: &str,
-        chain_id: i64,
-        user_id: i64,
-        message: &str
-    ) -> Result<EntityId, sqlx::Error> {
-        private_members::insert_standalone_post_inner(
-            self.get_conn(),
-            chain_asset_id,
-            chain_id,
-            user_id,
-            message
-        ).await
+        profile_id: i64,
+        cursor: Option<PostCursor>,
+        page_size: i16
+    ) -> Result<Vec<PostWithProfileAndShareeQueryResult>, RepositoryError> {
+        private_members::query_home_timeline_inner(self.get_conn(), profile_id, cursor, page_size).await
     }
 }
 
+/// Returns the whole conversation around a post as a tree starting at its root post.
+/// Ancestors are always included, replies below the given post are cut off after max_depth levels
 #[automock]
 #[async_trait]
-pub trait InsertResponsePostFn {
-    async fn insert_response_post(
+pub trait QueryPostThreadFn {
+    async fn query_post_thread(
         &self,
-        chain_asset_id: &str,
-        chain_id: i64,
-        user_id: i64,
-        message: &str,
-        respondee_post_id: i64
-    ) -> Result<EntityId, sqlx::Error>;
+        post_id: i64,
+        max_depth: i32
+    ) -> Result<Option<PostThreadNode>, RepositoryError>;
 }
 
 #[async_trait]
-impl InsertResponsePostFn for DbRepo {
-    async fn insert_response_post(
+impl QueryPostThreadFn for DbRepo {
+    async fn query_post_thread(
         &self,
-        chain_asset_id: &str,
-        chain_id: i64,
-        user_id: i64,
-        message: &str,
-        respondee_post_id: i64
-    ) -> Result<EntityId, sqlx::Error> {
-        private_members::insert_response_post_inner(
-            self.get_conn(),
-            chain_asset_id,
-            chain_id,
-            user_id,
-            message,
-            respondee_post_id
-        ).await
+        post_id: i64,
+        max_depth: i32
+    ) -> Result<Option<PostThreadNode>, RepositoryError> {
+        private_members::query_post_thread_inner(self.get_conn(), post_id, max_depth).await
     }
 }
 
@@ -145,8 +1029,9 @@ mod tests {
     use crate::repo::{profile::{
         profile::{InsertProfileFn, MockInsertProfileFn},
         model::{ProfileCreate, ProfileQueryResult},
-    }, post::model::PostWithProfileQueryResult};
-    use crate::test_helpers::fixtures::SUI_CHAIN_ID;
+    }, follow::follow::FollowUserFn};
+    use crate::test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id };
+    use crate::repo::base::{BeginTransactionFn, TransactionFn};
     use super::*;
 
     #[allow(unused)]
@@ -170,13 +1055,14 @@ mod tests {
         let last_name: String = LastName().fake();
         let user_name = "johnson";
         ProfileCreate {
-            chain_asset_id: "chain_id123".to_string(),
+            chain_asset_id: get_fake_chain_asset_id(),
             chain_id: SUI_CHAIN_ID,
             user_name: format!("{}{}", PREFIX, user_name),
             full_name: format!("{} {}", first_name, last_name),
             description: format!("{} a description", PREFIX),
             main_url: Some("http://whatever.com".to_string()),
             avatar: None::<Vec<u8>>,
+            owner_address: None,
         }
     }
 
@@ -233,12 +1119,12 @@ mod tests {
                 match option_post {
                     None => {
                         let respondee_post_id = db_repo
-                        .insert_standalone_post(format!("{}chain_id", PREFIX).as_str(), SUI_CHAIN_ID, profile_id, respondee_message.as_str())
+                        .insert_standalone_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, profile_id, respondee_message.as_str(), None)
                         .await
                         .unwrap();
 
                         _ = db_repo
-                        .insert_response_post(format!("{}chain_id", PREFIX).as_str(), SUI_CHAIN_ID, profile_id, format!("{}Responder message 123", PREFIX).as_str(), respondee_post_id.id)
+                        .insert_response_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, profile_id, format!("{}Responder message 123", PREFIX).as_str(), None, respondee_post_id.id)
                         .await
                         .unwrap();
                     },
@@ -391,15 +1277,17 @@ mod tests {
                     description: "dummy".to_string(),
                     main_url: Some("dummy".to_string()),
                     avatar: Some(vec![]),
+                    owner_address: None,
                 }).await
                 .unwrap();
 
             let respondee_post_id = fixtures.db_repo
                 .insert_standalone_post(
-                    format!("{}chain_id", PREFIX).as_str(),
+                    get_fake_chain_asset_id().as_str(),
                     SUI_CHAIN_ID,
                     profile_id,
-                    format!("{}test_insert_post", PREFIX).as_str()
+                    format!("{}test_insert_post", PREFIX).as_str(),
+                    None
                 )
                 .await
                 .unwrap();
@@ -429,15 +1317,17 @@ mod tests {
                     description: "dummy".to_string(),
                     main_url: Some("dummy".to_string()),
                     avatar: Some(vec![]),
+                    owner_address: None,
                 }).await
                 .unwrap();
 
             let responder_post_id = fixtures.db_repo
                 .insert_response_post(
-                    format!("{}chain_id", PREFIX).as_str(),
+                    get_fake_chain_asset_id().as_str(),
                     SUI_CHAIN_ID,
                     profile_id,
                     format!("{}test_insert_response_post", PREFIX).as_str(),
+                    None,
                     fixtures.respondee_post_id
                 )
                 .await
@@ -451,4 +1341,429 @@ mod tests {
             RT.block_on(test_insert_response_post_body())
         }
     }
-}
\ No newline at end of file
+
+    mod test_mod_upsert_post {
+        use super::*;
+
+        async fn count_posts_with_chain_asset(db_repo: &DbRepo, chain_asset_id: &str) -> i64 {
+            sqlx::query_scalar::<_, i64>("select count(*) from post where chain_asset_id = $1")
+                .bind(chain_asset_id)
+                .fetch_one(db_repo.get_conn())
+                .await
+                .unwrap()
+        }
+
+        async fn test_upserts_are_idempotent_body() {
+            let fixtures = fixtures();
+            let db_repo = &fixtures.db_repo;
+            let post_asset_id = get_fake_chain_asset_id();
+            let reply_asset_id = get_fake_chain_asset_id();
+            let share_asset_id = get_fake_chain_asset_id();
+            let message = format!("{}upsert", PREFIX);
+
+            let post_id = db_repo
+                .upsert_standalone_post(post_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, message.as_str(), None)
+                .await
+                .unwrap()
+                .id;
+            let reply_id = db_repo
+                .upsert_response_post(reply_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, message.as_str(), None, post_id)
+                .await
+                .unwrap()
+                .id;
+            let share_id = db_repo
+                .upsert_share_post(share_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, None, post_id)
+                .await
+                .unwrap()
+                .id;
+
+            let replayed_message = format!("{}replayed", PREFIX);
+            let replayed_post_id = db_repo
+                .upsert_standalone_post(post_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, replayed_message.as_str(), None)
+                .await
+                .unwrap()
+                .id;
+            let replayed_reply_id = db_repo
+                .upsert_response_post(reply_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, message.as_str(), None, post_id)
+                .await
+                .unwrap()
+                .id;
+            let replayed_share_id = db_repo
+                .upsert_share_post(share_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, None, post_id)
+                .await
+                .unwrap()
+                .id;
+
+            assert!(replayed_post_id == post_id && replayed_reply_id == reply_id && replayed_share_id == share_id);
+            for chain_asset_id in [&post_asset_id, &reply_asset_id, &share_asset_id] {
+                assert!(count_posts_with_chain_asset(db_repo, chain_asset_id).await == 1);
+            }
+            let response_count = sqlx::query_scalar::<_, i64>("select count(*) from post_response where responder_post_id = $1")
+                .bind(reply_id)
+                .fetch_one(db_repo.get_conn())
+                .await
+                .unwrap();
+            assert!(response_count == 1);
+            let post = db_repo.query_post(post_id).await.unwrap().unwrap();
+            assert!(post.message == Some(message));
+        }
+
+        #[test]
+        fn test_upserts_are_idempotent() {
+            RT.block_on(test_upserts_are_idempotent_body())
+        }
+
+        async fn test_insert_duplicate_chain_asset_body() {
+            let fixtures = fixtures();
+            let chain_asset_id = get_fake_chain_asset_id();
+            let message = format!("{}duplicate", PREFIX);
+
+            _ = fixtures.db_repo
+                .insert_standalone_post(chain_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, message.as_str(), None)
+                .await
+                .unwrap();
+            let duplicate = fixtures.db_repo
+                .insert_standalone_post(chain_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, message.as_str(), None)
+                .await;
+
+            assert!(matches!(duplicate, Err(RepositoryError::UniqueViolation { field }) if field == "chain_id, chain_asset_id"));
+        }
+
+        #[test]
+        fn test_insert_duplicate_chain_asset() {
+            RT.block_on(test_insert_duplicate_chain_asset_body())
+        }
+    }
+
+    mod test_mod_query_home_timeline {
+        use super::*;
+
+        async fn insert_timeline_profile(db_repo: &DbRepo, name: &str) -> i64 {
+            let mut profile_create = get_test_profile_create();
+            profile_create.user_name = format!("{}{}", PREFIX, name);
+            db_repo.insert_profile(profile_create).await.unwrap()
+        }
+
+        async fn test_query_home_timeline_body() {
+            let fixtures = fixtures();
+            let db_repo = &fixtures.db_repo;
+            let reader_id = insert_timeline_profile(db_repo, "timeline_reader").await;
+            let followed_id = insert_timeline_profile(db_repo, "timeline_followed").await;
+            let stranger_id = insert_timeline_profile(db_repo, "timeline_stranger").await;
+            db_repo.follow_user(reader_id, followed_id).await.unwrap();
+
+            let mut expected_ids = vec![];
+            for user_id in [reader_id, followed_id, reader_id, followed_id] {
+                let post = db_repo
+                    .insert_standalone_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, user_id, format!("{}timeline", PREFIX).as_str(), None)
+                    .await
+                    .unwrap();
+                expected_ids.push(post.id);
+            }
+            let stranger_post = db_repo
+                .insert_standalone_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, stranger_id, format!("{}timeline", PREFIX).as_str(), None)
+                .await
+                .unwrap();
+            let share = db_repo
+                .insert_share_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, followed_id, None, stranger_post.id)
+                .await
+                .unwrap();
+            expected_ids.push(share.id);
+            expected_ids.reverse();
+
+            let first_page = db_repo.query_home_timeline(reader_id, None, 3).await.unwrap();
+            assert!(first_page.len() == 3);
+            assert!(first_page[0].sharee_post_id == Some(stranger_post.id), "shares of strangers' posts show up through the sharer");
+            assert!(first_page[0].sharee_post_user_id == Some(stranger_id));
+            let last = first_page.last().unwrap();
+            let second_page = db_repo
+                .query_home_timeline(reader_id, Some(PostCursor { updated_at: last.updated_at, id: last.id }), 3)
+                .await
+                .unwrap();
+            assert!(second_page.len() == 2);
+            assert!(second_page.iter().all(|post| post.sharee_post_id.is_none()));
+
+            let timeline_ids: Vec<i64> = first_page.iter().chain(second_page.iter()).map(|post| post.id).collect();
+            assert!(timeline_ids == expected_ids);
+            assert!(first_page.iter().chain(second_page.iter()).all(|post| post.user_id != stranger_id));
+        }
+
+        #[test]
+        fn test_query_home_timeline() {
+            RT.block_on(test_query_home_timeline_body())
+        }
+    }
+
+    mod test_mod_query_post_thread {
+        use super::*;
+
+        async fn test_query_post_thread_body() {
+            let fixtures = fixtures();
+            let db_repo = &fixtures.db_repo;
+            let message = format!("{}thread", PREFIX);
+            let insert_reply = |respondee_post_id: i64| {
+                let message = message.clone();
+                async move {
+                    db_repo
+                        .insert_response_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, fixtures.profile_id, message.as_str(), None, respondee_post_id)
+                        .await
+                        .unwrap()
+                        .id
+                }
+            };
+
+            let root_id = db_repo
+                .insert_standalone_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, fixtures.profile_id, message.as_str(), None)
+                .await
+                .unwrap()
+                .id;
+            let middle_id = insert_reply(root_id).await;
+            _ = insert_reply(root_id).await;
+            let child_id = insert_reply(middle_id).await;
+            _ = insert_reply(child_id).await;
+
+            let root = db_repo.query_post_thread(middle_id, 1).await.unwrap().unwrap();
+
+            assert!(root.post.id == root_id);
+            assert!(root.depth == 0);
+            assert!(root.reply_count == 2);
+            assert!(root.replies.len() == 1, "siblings of ancestors are not part of the thread");
+            let middle = &root.replies[0];
+            assert!(middle.post.id == middle_id);
+            assert!(middle.depth == 1);
+            assert!(middle.post.respondee_post_id == Some(root_id));
+            let child = &middle.replies[0];
+            assert!(child.post.id == child_id);
+            assert!(child.depth == 2);
+            assert!(child.reply_count == 1);
+            assert!(child.replies.is_empty(), "grandchild is beyond max depth");
+        }
+
+        #[test]
+        fn test_query_post_thread() {
+            RT.block_on(test_query_post_thread_body())
+        }
+
+        async fn test_query_post_thread_missing_post_body() {
+            let fixtures = fixtures();
+
+            let thread = fixtures.db_repo.query_post_thread(i64::MAX, 5).await.unwrap();
+
+            assert!(thread.is_none());
+        }
+
+        #[test]
+        fn test_query_post_thread_missing_post() {
+            RT.block_on(test_query_post_thread_missing_post_body())
+        }
+    }
+
+    mod test_mod_transaction {
+        use super::*;
+
+        async fn count_posts_with_message(db_repo: &DbRepo, message: &str) -> i64 {
+            sqlx::query_scalar::<_, i64>("select count(*) from post where message = $1")
+                .bind(message)
+                .fetch_one(db_repo.get_conn())
+                .await
+                .unwrap()
+        }
+
+        async fn test_insert_response_post_rolls_back_on_failure_body() {
+            let fixtures = fixtures();
+            let message = format!("{}orphan {}", PREFIX, rand::random::<u32>());
+
+            let result = fixtures.db_repo
+                .insert_response_post(
+                    get_fake_chain_asset_id().as_str(),
+                    SUI_CHAIN_ID,
+                    fixtures.profile_id,
+                    message.as_str(),
+                    None,
+                    i64::MAX
+                )
+                .await;
+
+            assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation { field }) if field == "respondee_post_id"));
+            assert!(count_posts_with_message(&fixtures.db_repo, message.as_str()).await == 0);
+        }
+
+        #[test]
+        fn test_insert_response_post_rolls_back_on_failure() {
+            RT.block_on(test_insert_response_post_rolls_back_on_failure_body())
+        }
+
+        async fn test_transaction_across_traits_body() {
+            let fixtures = fixtures();
+            let rolled_back_message = format!("{}rolled back {}", PREFIX, rand::random::<u32>());
+            let committed_message = format!("{}committed {}", PREFIX, rand::random::<u32>());
+
+            let tx = fixtures.db_repo.begin_transaction().await.unwrap();
+            let profile_id = tx.insert_profile(get_test_profile_create()).await.unwrap();
+            _ = tx.insert_standalone_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, profile_id, rolled_back_message.as_str(), None).await.unwrap();
+            tx.rollback().await.unwrap();
+
+            assert!(count_posts_with_message(&fixtures.db_repo, rolled_back_message.as_str()).await == 0);
+            let profile = sqlx::query_as::<_, ProfileQueryResult>("select * from profile where id = $1")
+                .bind(profile_id)
+                .fetch_optional(fixtures.db_repo.get_conn())
+                .await
+                .unwrap();
+            assert!(profile.is_none());
+
+            let tx = fixtures.db_repo.begin_transaction().await.unwrap();
+            let profile_id = tx.insert_profile(get_test_profile_create()).await.unwrap();
+            let post_id = tx.insert_standalone_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, profile_id, committed_message.as_str(), None).await.unwrap();
+            _ = tx.insert_response_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, profile_id, committed_message.as_str(), None, post_id.id).await.unwrap();
+            tx.commit().await.unwrap();
+
+            assert!(count_posts_with_message(&fixtures.db_repo, committed_message.as_str()).await == 2);
+        }
+
+        #[test]
+        fn test_transaction_across_traits() {
+            RT.block_on(test_transaction_across_traits_body())
+        }
+    }
+
+    mod test_mod_share_post {
+        use super::*;
+
+        async fn test_insert_and_query_share_post_body() {
+            let fixtures = fixtures();
+            let message = format!("{}sharing this", PREFIX);
+
+            let share = fixtures.db_repo
+                .insert_share_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, fixtures.profile_id, Some(message.clone()), fixtures.respondee_post_id)
+                .await
+                .unwrap();
+            let shared_post = fixtures.db_repo.query_shared_post(share.id).await.unwrap().unwrap();
+
+            let sharee_post = sqlx::query_as::<_, (String, Option<String>)>("select chain_asset_id, message from post where id = $1")
+                .bind(fixtures.respondee_post_id)
+                .fetch_one(fixtures.db_repo.get_conn())
+                .await
+                .unwrap();
+            let sharee_profile = sqlx::query_as::<_, ProfileQueryResult>("select * from profile where id = $1")
+                .bind(fixtures.profile_id)
+                .fetch_one(fixtures.db_repo.get_conn())
+                .await
+                .unwrap();
+            assert!(shared_post.id == share.id);
+            assert!(shared_post.message == Some(message));
+            assert!(shared_post.sharee_post_id == Some(fixtures.respondee_post_id));
+            assert!(shared_post.sharee_post_updated_at.is_some());
+            assert!(shared_post.sharee_post_chain_asset_id == Some(sharee_post.0));
+            assert!(shared_post.sharee_post_chain_id == Some(SUI_CHAIN_ID));
+            assert!(shared_post.sharee_post_message == sharee_post.1);
+            assert!(shared_post.sharee_post_user_id == Some(fixtures.profile_id));
+            assert!(shared_post.sharee_post_user_name == Some(sharee_profile.user_name));
+            assert!(shared_post.sharee_post_full_name == Some(sharee_profile.full_name));
+            assert!(shared_post.sharee_post_avatar == sharee_profile.avatar);
+        }
+
+        #[test]
+        fn test_insert_and_query_share_post() {
+            RT.block_on(test_insert_and_query_share_post_body())
+        }
+
+        async fn test_query_shared_post_not_a_share_body() {
+            let fixtures = fixtures();
+
+            let shared_post = fixtures.db_repo.query_shared_post(fixtures.respondee_post_id).await.unwrap();
+
+            assert!(shared_post.is_none());
+        }
+
+        #[test]
+        fn test_query_shared_post_not_a_share() {
+            RT.block_on(test_query_shared_post_not_a_share_body())
+        }
+    }
+
+    mod test_mod_query_post {
+        use super::*;
+
+        async fn test_query_post_body() {
+            let fixtures = fixtures();
+            let image = vec![0xFF, 0xD8, 1, 2, 3, 0xFF, 0xD9];
+
+            let post_id = fixtures.db_repo
+                .insert_response_post(
+                    get_fake_chain_asset_id().as_str(),
+                    SUI_CHAIN_ID,
+                    fixtures.profile_id,
+                    format!("{}test_query_post", PREFIX).as_str(),
+                    Some(image.clone()),
+                    fixtures.respondee_post_id
+                )
+                .await
+                .unwrap();
+            let post = fixtures.db_repo.query_post(post_id.id).await.unwrap().unwrap();
+
+            assert!(post.id == post_id.id);
+            assert!(post.image == Some(image));
+            assert!(post.user_id == fixtures.profile_id);
+            assert!(post.respondee_post_id == Some(fixtures.respondee_post_id));
+            assert!(fixtures.db_repo.query_post(i64::MAX).await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_query_post() {
+            RT.block_on(test_query_post_body())
+        }
+
+        async fn test_query_post_id_by_chain_asset_body() {
+            let fixtures = fixtures();
+            let chain_asset_id = get_fake_chain_asset_id();
+            let post_id = fixtures.db_repo
+                .insert_standalone_post(chain_asset_id.as_str(), SUI_CHAIN_ID, fixtures.profile_id, format!("{}by_chain_asset", PREFIX).as_str(), None)
+                .await
+                .unwrap();
+
+            let found = fixtures.db_repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &chain_asset_id).await.unwrap();
+
+            assert!(found == Some(post_id.id));
+            assert!(fixtures.db_repo.query_post_id_by_chain_asset(SUI_CHAIN_ID, &get_fake_chain_asset_id()).await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_query_post_id_by_chain_asset() {
+            RT.block_on(test_query_post_id_by_chain_asset_body())
+        }
+
+        async fn test_query_posts_by_user_body() {
+            let fixtures = fixtures();
+            let db_repo = &fixtures.db_repo;
+            let mut profile_create = get_test_profile_create();
+            profile_create.user_name = format!("{}posts_by_user", PREFIX);
+            let user_id = db_repo.insert_profile(profile_create).await.unwrap();
+
+            let mut expected_ids = vec![];
+            for _ in 0..3 {
+                let post = db_repo
+                    .insert_standalone_post(get_fake_chain_asset_id().as_str(), SUI_CHAIN_ID, user_id, format!("{}by_user", PREFIX).as_str(), None)
+                    .await
+                    .unwrap();
+                expected_ids.push(post.id);
+            }
+            expected_ids.reverse();
+
+            let first_page = db_repo.query_posts_by_user(user_id, None, 2).await.unwrap();
+            let last = first_page.last().unwrap();
+            let second_page = db_repo
+                .query_posts_by_user(user_id, Some(PostCursor { updated_at: last.updated_at, id: last.id }), 2)
+                .await
+                .unwrap();
+
+            let post_ids: Vec<i64> = first_page.iter().chain(second_page.iter()).map(|post| post.id).collect();
+            assert!(first_page.len() == 2);
+            assert!(post_ids == expected_ids);
+        }
+
+        #[test]
+        fn test_query_posts_by_user() {
+            RT.block_on(test_query_posts_by_user_body())
+        }
+    }
+}
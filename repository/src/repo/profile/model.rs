@@ -13,8 +13,10 @@ pub struct ProfileQueryResult {
     pub description: String,
     pub main_url: Option<String>,
     pub avatar: Option<Vec<u8>>,
+    pub owner_address: Option<String>,
 }
 
+/// owner_address is the wallet that created the profile on chain, logins are signed by it
 #[derive(Deserialize, Serialize, Clone)]
 pub struct ProfileCreate {
     pub chain_asset_id: String,
@@ -24,6 +26,7 @@ pub struct ProfileCreate {
     pub description: String,
     pub main_url: Option<String>,
     pub avatar: Option<Vec<u8>>,
+    pub owner_address: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
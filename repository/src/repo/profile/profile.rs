@@ -1,8 +1,9 @@
-use crate::repo::base::{ DbRepo, DbConnGetter };
+use crate::repo::base::{ DbRepo, DbConnGetter, DbTransaction };
 use crate::repo::profile::model::{ProfileCreate, ProfileUpdate, ProfileQueryResult};
-use crate::repo::base::EntityId;
+use crate::repo::base::{ EntityId, UpsertedId };
+use crate::repo::error::{RepositoryError, check_value_len};
 use async_trait::async_trait;
-use sqlx::{ Pool, Postgres };
+use sqlx::{ Pool, Postgres, PgExecutor };
 use mockall::automock;
 use mockall::predicate::*;
 #[allow(unused)]
@@ -11,17 +12,28 @@ use log::{error, info};
 mod private_members {
     use super::*;
 
-    pub async fn insert_profile_inner(
-        conn: &Pool<Postgres>,
+    pub async fn insert_profile_inner<'e>(
+        conn: impl PgExecutor<'e>,
         params: ProfileCreate
-    ) -> Result<i64, sqlx::Error> {
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("chain_asset_id", &params.chain_asset_id, 500)?;
+        check_value_len("user_name", &params.user_name, 50)?;
+        check_value_len("full_name", &params.full_name, 100)?;
+        check_value_len("description", &params.description, 250)?;
+        if let Some(main_url) = &params.main_url {
+            check_value_len("main_url", main_url, 250)?;
+        }
+        if let Some(owner_address) = &params.owner_address {
+            check_value_len("owner_address", owner_address, 66)?;
+        }
+
         let result = sqlx
             ::query_as::<_, EntityId>(
                 r"
                 insert into Profile 
-                    (chain_asset_id, chain_id, user_name, full_name, description, main_url, avatar) 
+                    (chain_asset_id, chain_id, user_name, full_name, description, main_url, avatar, owner_address) 
                     values 
-                    ($1, $2, $3, $4, $5, $6, $7)
+                    ($1, $2, $3, $4, $5, $6, $7, $8)
                 returning id"
             )
             .bind(&params.chain_asset_id)
@@ -31,22 +43,75 @@ mod private_members {
             .bind(&params.description)
             .bind(&params.main_url)
             .bind(&params.avatar)
+            .bind(&params.owner_address)
             .fetch_one(conn).await;
 
         match result {
             Ok(r) => Ok(r.id),
             Err(e) => {
                 error!("create_profile error: {}", e);
-                Err(e)
+                Err(e.into())
             }
         }
     }
 
-    pub async fn update_profile_inner(
-        conn: &Pool<Postgres>,
+    /// Returns the existing profile's id when the chain asset was inserted before, also when that insert is
+    /// concurrent, see upsert_post_inner
+    pub async fn upsert_profile_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        params: ProfileCreate
+    ) -> Result<i64, RepositoryError> {
+        check_value_len("chain_asset_id", &params.chain_asset_id, 500)?;
+        check_value_len("user_name", &params.user_name, 50)?;
+        check_value_len("full_name", &params.full_name, 100)?;
+        check_value_len("description", &params.description, 250)?;
+        if let Some(main_url) = &params.main_url {
+            check_value_len("main_url", main_url, 250)?;
+        }
+        if let Some(owner_address) = &params.owner_address {
+            check_value_len("owner_address", owner_address, 66)?;
+        }
+
+        let result = sqlx
+            ::query_as::<_, UpsertedId>(
+                r"
+                insert into Profile
+                    (chain_asset_id, chain_id, user_name, full_name, description, main_url, avatar, owner_address)
+                    values
+                    ($1, $2, $3, $4, $5, $6, $7, $8)
+                on conflict (chain_id, chain_asset_id) do update set chain_id = excluded.chain_id
+                returning id, (xmax = 0) as inserted"
+            )
+            .bind(&params.chain_asset_id)
+            .bind(params.chain_id)
+            .bind(&params.user_name)
+            .bind(&params.full_name)
+            .bind(&params.description)
+            .bind(&params.main_url)
+            .bind(&params.avatar)
+            .bind(&params.owner_address)
+            .fetch_one(conn).await;
+
+        match result {
+            Ok(r) => Ok(r.id),
+            Err(e) => {
+                error!("upsert_profile error: {}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    pub async fn update_profile_inner<'e>(
+        conn: impl PgExecutor<'e>,
         user_id: i64,
         params: ProfileUpdate
-    ) -> Result<(), sqlx::Error> {
+    ) -> Result<(), RepositoryError> {
+        check_value_len("full_name", &params.full_name, 100)?;
+        check_value_len("description", &params.description, 250)?;
+        if let Some(main_url) = &params.main_url {
+            check_value_len("main_url", main_url, 250)?;
+        }
+
         let update_result = sqlx
             ::query::<_>("update profile set full_name = $1, description = $2, main_url = $3, avatar = $4 where id = $5")
             .bind(params.full_name)
@@ -57,18 +122,45 @@ mod private_members {
             .execute(conn).await;
 
         match update_result {
+            Ok(r) if r.rows_affected() == 0 => Err(RepositoryError::NotFound),
             Ok(_) => Ok(()),
-            Err(err) => Err(err),
+            Err(err) => Err(err.into()),
         }
     }
 
+    pub async fn query_profile_inner(
+        conn: &Pool<Postgres>,
+        id: i64
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        Ok(
+            sqlx::query_as::<_, ProfileQueryResult>("select * from profile where id = $1")
+                .bind(id)
+                .fetch_optional(conn).await?
+        )
+    }
+
     pub async fn query_profile_by_user_name_inner(
         conn: &Pool<Postgres>,
         user_name: &str
-    ) -> Result<Option<ProfileQueryResult>, sqlx::Error> {
-        sqlx::query_as::<_, ProfileQueryResult>("select * from profile where user_name = $1")
-            .bind(user_name)
-            .fetch_optional(conn).await
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        Ok(
+            sqlx::query_as::<_, ProfileQueryResult>("select * from profile where user_name = $1")
+                .bind(user_name)
+                .fetch_optional(conn).await?
+        )
+    }
+
+    pub async fn query_profile_by_chain_asset_inner<'e>(
+        conn: impl PgExecutor<'e>,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        Ok(
+            sqlx::query_as::<_, ProfileQueryResult>("select * from profile where chain_id = $1 and chain_asset_id = $2")
+                .bind(chain_id)
+                .bind(chain_asset_id)
+                .fetch_optional(conn).await?
+        )
     }
 }
 
@@ -78,7 +170,7 @@ pub trait InsertProfileFn {
     async fn insert_profile(
         &self,
         params: ProfileCreate
-    ) -> Result<i64, sqlx::Error>;
+    ) -> Result<i64, RepositoryError>;
 }
 
 #[async_trait]
@@ -86,11 +178,52 @@ impl InsertProfileFn for DbRepo {
     async fn insert_profile(
         &self,
         params: ProfileCreate
-    ) -> Result<i64, sqlx::Error> {
+    ) -> Result<i64, RepositoryError> {
         private_members::insert_profile_inner(self.get_conn(), params).await
     }
 }
 
+#[async_trait]
+impl InsertProfileFn for DbTransaction {
+    async fn insert_profile(
+        &self,
+        params: ProfileCreate
+    ) -> Result<i64, RepositoryError> {
+        private_members::insert_profile_inner(&mut **self.lock_conn().await, params).await
+    }
+}
+
+/// Insert for profiles that exist on chain, a second insert of the same chain asset is a no-op
+/// that returns the id of the first, so replayed chain events do not create duplicates
+#[automock]
+#[async_trait]
+pub trait UpsertProfileFn {
+    async fn upsert_profile(
+        &self,
+        params: ProfileCreate
+    ) -> Result<i64, RepositoryError>;
+}
+
+#[async_trait]
+impl UpsertProfileFn for DbRepo {
+    async fn upsert_profile(
+        &self,
+        params: ProfileCreate
+    ) -> Result<i64, RepositoryError> {
+        private_members::upsert_profile_inner(self.get_conn(), params).await
+    }
+}
+
+#[async_trait]
+impl UpsertProfileFn for DbTransaction {
+    async fn upsert_profile(
+        &self,
+        params: ProfileCreate
+    ) -> Result<i64, RepositoryError> {
+        private_members::upsert_profile_inner(&mut **self.lock_conn().await, params).await
+    }
+}
+
 #[automock]
 #[async_trait]
 pub trait UpdateProfileFn {
@@ -98,7 +231,7 @@ pub trait UpdateProfileFn {
         &self,
         user_id: i64,
         params: ProfileUpdate
-    ) -> Result<(), sqlx::Error>;
+    ) -> Result<(), RepositoryError>;
 }
 
 #[async_trait]
@@ -107,18 +240,48 @@ impl UpdateProfileFn for DbRepo {
         &self,
         user_id: i64,
         params: ProfileUpdate
-    ) -> Result<(), sqlx::Error> {
+    ) -> Result<(), RepositoryError> {
         private_members::update_profile_inner(self.get_conn(), user_id, params).await
     }
 }
 
+#[async_trait]
+impl UpdateProfileFn for DbTransaction {
+    async fn update_profile(
+        &self,
+        user_id: i64,
+        params: ProfileUpdate
+    ) -> Result<(), RepositoryError> {
+        private_members::update_profile_inner(&mut **self.lock_conn().await, user_id, params).await
+    }
+}
+
+#[automock]
+#[async_trait]
+pub trait QueryProfileFn {
+    async fn query_profile(
+        &self,
+        id: i64
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryProfileFn for DbRepo {
+    async fn query_profile(
+        &self,
+        id: i64
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        private_members::query_profile_inner(self.get_conn(), id).await
+    }
+}
+
 #[automock]
 #[async_trait]
 pub trait QueryProfileByUserNameFn {
     async fn query_profile_by_user_name(
         &self,
         user_name: &str
-    ) -> Result<Option<ProfileQueryResult>, sqlx::Error>;
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError>;
 }
 
 #[async_trait]
@@ -126,14 +289,48 @@ impl QueryProfileByUserNameFn for DbRepo {
     async fn query_profile_by_user_name(
         &self,
         user_name: &str
-    ) -> Result<Option<ProfileQueryResult>, sqlx::Error> {
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
         private_members::query_profile_by_user_name_inner(self.get_conn(), user_name).await
     }
 }
 
+/// Resolves the on-chain object of a profile, also available in a transaction so event handlers can look up
+/// what earlier events of the same transaction wrote
+#[automock]
+#[async_trait]
+pub trait QueryProfileByChainAssetFn {
+    async fn query_profile_by_chain_asset(
+        &self,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError>;
+}
+
+#[async_trait]
+impl QueryProfileByChainAssetFn for DbRepo {
+    async fn query_profile_by_chain_asset(
+        &self,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        private_members::query_profile_by_chain_asset_inner(self.get_conn(), chain_id, chain_asset_id).await
+    }
+}
+
+#[async_trait]
+impl QueryProfileByChainAssetFn for DbTransaction {
+    async fn query_profile_by_chain_asset(
+        &self,
+        chain_id: i64,
+        chain_asset_id: &str
+    ) -> Result<Option<ProfileQueryResult>, RepositoryError> {
+        private_members::query_profile_by_chain_asset_inner(&mut **self.lock_conn().await, chain_id, chain_asset_id).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{repo::base::EntityId, test_helpers::fixtures::SUI_CHAIN_ID};
+    use crate::{repo::base::EntityId, test_helpers::fixtures::{ SUI_CHAIN_ID, get_fake_chain_asset_id }};
     use super::*;
     use lazy_static::lazy_static;
     use std::sync::{ Arc, RwLock };
@@ -180,7 +377,7 @@ mod tests {
                     returning id
                 "
             )
-            .bind("chain_id123")
+            .bind(get_fake_chain_asset_id())
             .bind(SUI_CHAIN_ID)
             .bind(username_dave)
             .bind(format!("{}Dave Choi", PREFIX))
@@ -215,7 +412,7 @@ mod tests {
                     returning id
                 "
             )
-            .bind("chain_id123")
+            .bind(get_fake_chain_asset_id())
             .bind(SUI_CHAIN_ID)
             .bind(username_jill)
             .bind(format!("{}Jill Simon", PREFIX))
@@ -279,7 +476,6 @@ mod tests {
 
     mod test_mod_insert_profile {
         use super::*;
-        use crate::test_helpers::fixtures::SUI_CHAIN_ID;
 
         async fn test_insert_profile_body() {
             let fixtures = fixtures();
@@ -289,13 +485,14 @@ mod tests {
             let description = format!("{}Insert Test description", PREFIX);
             let profile_id = fixtures.db_repo
                 .insert_profile(ProfileCreate {
-                    chain_asset_id: "chain_id123".to_string(),
+                    chain_asset_id: get_fake_chain_asset_id(),
                     chain_id: SUI_CHAIN_ID,
                     user_name: user_name.clone(),
                     full_name: full_name.clone(),
                     description: description.clone(),
                     main_url: Some("http://whatever.com".to_string()),
                     avatar: Some(vec![]),
+                    owner_address: None,
                 }).await
                 .unwrap();
 
@@ -319,6 +516,93 @@ mod tests {
         fn test_insert_profile() {
             RT.block_on(test_insert_profile_body())
         }
+
+        async fn test_insert_profile_value_too_long_body() {
+            let fixtures = fixtures();
+
+            let result = fixtures.db_repo
+                .insert_profile(ProfileCreate {
+                    chain_asset_id: get_fake_chain_asset_id(),
+                    chain_id: SUI_CHAIN_ID,
+                    user_name: format!("{}{}", PREFIX, "x".repeat(50)),
+                    full_name: format!("{}Too Long", PREFIX),
+                    description: format!("{}Too long description", PREFIX),
+                    main_url: None,
+                    avatar: None,
+                    owner_address: None,
+                }).await;
+
+            assert!(matches!(result, Err(RepositoryError::ValueTooLong { field, max: 50 }) if field == "user_name"));
+        }
+
+        #[test]
+        fn test_insert_profile_value_too_long() {
+            RT.block_on(test_insert_profile_value_too_long_body())
+        }
+
+        async fn test_upsert_profile_body() {
+            let fixtures = fixtures();
+            let profile_create = ProfileCreate {
+                chain_asset_id: get_fake_chain_asset_id(),
+                chain_id: SUI_CHAIN_ID,
+                user_name: format!("{}upsert_tester", PREFIX),
+                full_name: format!("{}Upsert Tester", PREFIX),
+                description: format!("{}Upsert Test description", PREFIX),
+                main_url: None,
+                avatar: None,
+                owner_address: None,
+            };
+
+            let profile_id = fixtures.db_repo.upsert_profile(profile_create.clone()).await.unwrap();
+            let mut replayed = profile_create.clone();
+            replayed.full_name = format!("{}Replayed", PREFIX);
+            let replayed_id = fixtures.db_repo.upsert_profile(replayed).await.unwrap();
+
+            let rows = sqlx::query_as::<_, ProfileQueryResult>("select * from profile where chain_asset_id = $1")
+                .bind(&profile_create.chain_asset_id)
+                .fetch_all(fixtures.db_repo.get_conn())
+                .await
+                .unwrap();
+            assert!(replayed_id == profile_id);
+            assert!(rows.len() == 1);
+            assert!(rows[0].full_name == profile_create.full_name);
+
+            let duplicate = fixtures.db_repo.insert_profile(profile_create).await;
+            assert!(matches!(duplicate, Err(RepositoryError::UniqueViolation { field }) if field == "chain_id, chain_asset_id"));
+        }
+
+        #[test]
+        fn test_upsert_profile() {
+            RT.block_on(test_upsert_profile_body())
+        }
+
+        async fn test_concurrent_upserts_return_one_profile_body() {
+            let fixtures = fixtures();
+            let profile_create = ProfileCreate {
+                chain_asset_id: get_fake_chain_asset_id(),
+                chain_id: SUI_CHAIN_ID,
+                user_name: format!("{}concurrent_tester", PREFIX),
+                full_name: format!("{}Concurrent Tester", PREFIX),
+                description: format!("{}Concurrent Test description", PREFIX),
+                main_url: None,
+                avatar: None,
+                owner_address: None,
+            };
+
+            let (first, second, third) = tokio::join!(
+                fixtures.db_repo.upsert_profile(profile_create.clone()),
+                fixtures.db_repo.upsert_profile(profile_create.clone()),
+                fixtures.db_repo.upsert_profile(profile_create.clone())
+            );
+            let first = first.unwrap();
+            assert!(second.unwrap() == first);
+            assert!(third.unwrap() == first);
+        }
+
+        #[test]
+        fn test_concurrent_upserts_return_one_profile() {
+            RT.block_on(test_concurrent_upserts_return_one_profile_body())
+        }
     }
 
     mod test_mod_update_profile {
@@ -363,6 +647,28 @@ mod tests {
         fn test_update_profile() {
             RT.block_on(test_update_profile_body())
         }
+
+        async fn test_update_missing_profile_body() {
+            let fixtures = fixtures();
+
+            let result = fixtures.db_repo
+                .update_profile(
+                    i64::MAX,
+                    ProfileUpdate {
+                        full_name: format!("{}Missing", PREFIX),
+                        description: format!("{}Missing description", PREFIX),
+                        main_url: None,
+                        avatar: None,
+                    }
+                ).await;
+
+            assert!(matches!(result, Err(RepositoryError::NotFound)));
+        }
+
+        #[test]
+        fn test_update_missing_profile() {
+            RT.block_on(test_update_missing_profile_body())
+        }
     }
 
     mod test_query_profile_by_user_name {
@@ -387,4 +693,57 @@ mod tests {
             RT.block_on(test_query_profile_by_user_name_body())
         }
     }
+
+    mod test_query_profile {
+        use super::*;
+
+        async fn test_query_profile_body() {
+            let fixtures = fixtures();
+            let username = format!("{}dave", PREFIX);
+            let profile_by_user_name = fixtures.db_repo
+                .query_profile_by_user_name(username.as_str())
+                .await
+                .unwrap()
+                .unwrap();
+
+            let profile = fixtures.db_repo
+                .query_profile(profile_by_user_name.id)
+                .await
+                .unwrap()
+                .unwrap();
+
+            assert!(profile.id == profile_by_user_name.id);
+            assert!(profile.user_name == username);
+            assert!(fixtures.db_repo.query_profile(i64::MAX).await.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_query_profile() {
+            RT.block_on(test_query_profile_body())
+        }
+
+        async fn test_query_profile_by_chain_asset_body() {
+            let fixtures = fixtures();
+            let profile = fixtures.db_repo
+                .query_profile_by_user_name(format!("{}dave", PREFIX).as_str())
+                .await
+                .unwrap()
+                .unwrap();
+
+            let by_chain_asset = fixtures.db_repo
+                .query_profile_by_chain_asset(profile.chain_id, &profile.chain_asset_id)
+                .await
+                .unwrap()
+                .unwrap();
+
+            assert!(by_chain_asset.id == profile.id);
+            let missing = fixtures.db_repo.query_profile_by_chain_asset(SUI_CHAIN_ID, &get_fake_chain_asset_id()).await;
+            assert!(missing.unwrap().is_none());
+        }
+
+        #[test]
+        fn test_query_profile_by_chain_asset() {
+            RT.block_on(test_query_profile_by_chain_asset_body())
+        }
+    }
 }
\ No newline at end of file
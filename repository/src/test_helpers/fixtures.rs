@@ -73,6 +73,11 @@ pub fn get_fake_message_body(prefix: Option<String>) -> String {
     body
 }
 
+/// Random sui style object id, chain assets are unique per chain so tests must not share them
+pub fn get_fake_chain_asset_id() -> String {
+    format!("0x{:032x}{:032x}", rand::random::<u128>(), rand::random::<u128>())
+}
+
 pub fn get_fake_main_url() -> String {
     let mut domain = CompanyName().fake::<String>();
     domain.retain(|str| !str.is_whitespace());